@@ -0,0 +1,241 @@
+//! 死锁的避免和检测
+//!
+//! - [`is_safe`]：银行家算法的安全性检查
+//! - [`WaitForGraph`]：等待图，在线程开始等待时查找环
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+/// 银行家算法的安全性检查
+///
+/// `claims` 为每个进程对每种资源声明的最大需求，`allocation` 为每个进程已经获得的数量，
+/// `available` 为每种资源的剩余数量。只考虑 `claims` 中的进程，返回是否存在让它们依次满足最大需求并结束的顺序
+pub fn is_safe<P: Ord + Copy, R: Ord + Copy>(
+    claims: &BTreeMap<P, BTreeMap<R, usize>>,
+    allocation: &BTreeMap<P, BTreeMap<R, usize>>,
+    mut available: BTreeMap<R, usize>,
+) -> bool {
+    let allocated = |process: &P, resource: &R| {
+        allocation
+            .get(process)
+            .and_then(|allocation| allocation.get(resource).copied())
+            .unwrap_or(0)
+    };
+    let mut finished = BTreeSet::new();
+    loop {
+        let next = claims.iter().find(|(process, claims)| {
+            !finished.contains(*process)
+                && claims.iter().all(|(resource, max)| {
+                    let need = max.saturating_sub(allocated(process, resource));
+                    need <= available.get(resource).copied().unwrap_or(0)
+                })
+        });
+        match next {
+            Some((process, _)) => {
+                // 该进程可以结束，归还它获得的全部资源
+                if let Some(allocation) = allocation.get(process) {
+                    for (resource, count) in allocation.iter() {
+                        *available.entry(*resource).or_default() += count;
+                    }
+                }
+                finished.insert(*process);
+            }
+            None => return finished.len() == claims.len(),
+        }
+    }
+}
+
+/// 等待图（wait-for graph）
+///
+/// 线程 `T` 指向它正在等待的资源 `R`，资源指向持有它的线程。
+/// 同一个线程持有同一资源的多份（如信号量）时，在持有者中出现多次
+pub struct WaitForGraph<T, R> {
+    /// 每个资源的持有者
+    holders: BTreeMap<R, Vec<T>>,
+    /// 每个正在等待的线程所等待的资源
+    waiting: BTreeMap<T, R>,
+}
+
+impl<T: Ord, R: Ord> Default for WaitForGraph<T, R> {
+    fn default() -> Self {
+        Self {
+            holders: BTreeMap::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Copy, R: Ord + Copy> WaitForGraph<T, R> {
+    /// `thread` 获得了 `resource`，同时不再等待
+    pub fn acquire(&mut self, thread: T, resource: R) {
+        self.waiting.remove(&thread);
+        self.holders.entry(resource).or_default().push(thread);
+    }
+
+    /// `thread` 释放了 `resource`
+    ///
+    /// `thread` 没有持有（或为 `None`）时，视为替最早的持有者释放
+    pub fn release(&mut self, thread: Option<T>, resource: R) {
+        if let Some(holders) = self.holders.get_mut(&resource) {
+            let index = holders
+                .iter()
+                .position(|holder| Some(*holder) == thread)
+                .unwrap_or(0);
+            if index < holders.len() {
+                holders.remove(index);
+            }
+            if holders.is_empty() {
+                self.holders.remove(&resource);
+            }
+        }
+    }
+
+    /// `thread` 开始等待 `resource`
+    ///
+    /// 如果因此形成了环，返回环上依次的线程及其等待的资源
+    pub fn wait(&mut self, thread: T, resource: R) -> Option<Vec<(T, R)>> {
+        self.waiting.insert(thread, resource);
+        let mut path = Vec::new();
+        let mut visited = BTreeSet::new();
+        if self.find_path(thread, thread, &mut visited, &mut path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// 线程已经结束，移除它的等待和持有记录
+    pub fn forget(&mut self, thread: T) {
+        self.waiting.remove(&thread);
+        let resources: Vec<R> = self.holders.keys().copied().collect();
+        for resource in resources {
+            let holders = self.holders.get_mut(&resource).unwrap();
+            holders.retain(|holder| *holder != thread);
+            if holders.is_empty() {
+                self.holders.remove(&resource);
+            }
+        }
+    }
+
+    /// 资源的持有者
+    pub fn holders(&self, resource: &R) -> &[T] {
+        self.holders
+            .get(resource)
+            .map_or(&[], |holders| &holders[..])
+    }
+
+    /// 沿着等待图从 `from` 开始寻找到达 `target` 的路径
+    ///
+    /// 找到时返回 `true`，`path` 中依次为路径上的线程及其等待的资源
+    fn find_path(
+        &self,
+        from: T,
+        target: T,
+        visited: &mut BTreeSet<T>,
+        path: &mut Vec<(T, R)>,
+    ) -> bool {
+        let resource = match self.waiting.get(&from) {
+            Some(resource) => *resource,
+            None => return false,
+        };
+        path.push((from, resource));
+        if let Some(holders) = self.holders.get(&resource) {
+            for &holder in holders.iter() {
+                if holder == target {
+                    return true;
+                }
+                if visited.insert(holder) && self.find_path(holder, target, visited, path) {
+                    return true;
+                }
+            }
+        }
+        path.pop();
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn table(entries: &[(usize, &[(usize, usize)])]) -> BTreeMap<usize, BTreeMap<usize, usize>> {
+        entries
+            .iter()
+            .map(|(process, counts)| (*process, counts.iter().copied().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn banker_safe_state() {
+        // 经典的例子：资源 0 共 12 个，剩余 3 个
+        let claims = table(&[(0, &[(0, 10)]), (1, &[(0, 4)]), (2, &[(0, 9)])]);
+        let allocation = table(&[(0, &[(0, 5)]), (1, &[(0, 2)]), (2, &[(0, 2)])]);
+        let available: BTreeMap<_, _> = [(0, 3)].iter().copied().collect();
+        assert!(is_safe(&claims, &allocation, available));
+    }
+
+    #[test]
+    fn banker_unsafe_state() {
+        let claims = table(&[(0, &[(0, 10)]), (1, &[(0, 4)]), (2, &[(0, 9)])]);
+        // 在安全状态下再分给进程 1 一个，仍然安全
+        let allocation = table(&[(0, &[(0, 5)]), (1, &[(0, 3)]), (2, &[(0, 2)])]);
+        let available: BTreeMap<_, _> = [(0, 2)].iter().copied().collect();
+        assert!(is_safe(&claims, &allocation, available));
+        // 改为分给进程 2，进程 1 结束后剩余的 4 个不能满足其他任何进程
+        let allocation = table(&[(0, &[(0, 5)]), (1, &[(0, 2)]), (2, &[(0, 3)])]);
+        let available: BTreeMap<_, _> = [(0, 2)].iter().copied().collect();
+        assert!(!is_safe(&claims, &allocation, available));
+    }
+
+    #[test]
+    fn banker_multiple_resources() {
+        // 两个进程各持有一种资源并需要另一种：不安全
+        let claims = table(&[(0, &[(0, 1), (1, 1)]), (1, &[(0, 1), (1, 1)])]);
+        let allocation = table(&[(0, &[(0, 1)]), (1, &[(1, 1)])]);
+        assert!(!is_safe(&claims, &allocation, BTreeMap::new()));
+        // 同一个进程持有两种资源时，另一个进程可以在它结束后满足
+        let allocation = table(&[(0, &[(0, 1), (1, 1)])]);
+        assert!(is_safe(&claims, &allocation, BTreeMap::new()));
+    }
+
+    #[test]
+    fn detect_cycle() {
+        let mut graph = WaitForGraph::default();
+        graph.acquire(1, 'a');
+        graph.acquire(2, 'b');
+        graph.acquire(3, 'c');
+        assert_eq!(graph.wait(1, 'b'), None);
+        assert_eq!(graph.wait(2, 'c'), None);
+        assert_eq!(graph.wait(3, 'a'), Some(vec![(3, 'a'), (1, 'b'), (2, 'c')]));
+    }
+
+    #[test]
+    fn release_breaks_cycle() {
+        let mut graph = WaitForGraph::default();
+        graph.acquire(1, 'a');
+        graph.acquire(2, 'b');
+        assert_eq!(graph.wait(1, 'b'), None);
+        graph.release(Some(2), 'b');
+        graph.acquire(1, 'b');
+        assert_eq!(graph.holders(&'b'), &[1]);
+        assert_eq!(graph.wait(2, 'a'), None);
+        // 由没有持有的线程释放时，扣除最早的持有者
+        graph.acquire(3, 'b');
+        graph.release(None, 'b');
+        assert_eq!(graph.holders(&'b'), &[3]);
+    }
+
+    #[test]
+    fn forget_removes_thread() {
+        let mut graph = WaitForGraph::default();
+        graph.acquire(1, 'a');
+        graph.acquire(1, 'a');
+        graph.acquire(2, 'b');
+        assert_eq!(graph.wait(1, 'b'), None);
+        graph.forget(1);
+        assert!(graph.holders(&'a').is_empty());
+        // 线程 1 不再持有 a，线程 2 等待 a 不会形成环
+        assert_eq!(graph.wait(2, 'a'), None);
+    }
+}
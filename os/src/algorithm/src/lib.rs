@@ -8,10 +8,14 @@
 extern crate alloc;
 
 mod allocator;
+mod deadlock;
+mod random;
 mod scheduler;
 pub mod unity;
 mod unsafe_wrapper;
 
 pub use allocator::*;
+pub use deadlock::{is_safe, WaitForGraph};
+pub use random::XorShift;
 pub use scheduler::*;
 pub use unsafe_wrapper::{StaticUnsafeWrapper, UnsafeWrapper};
@@ -0,0 +1,74 @@
+//! 伪随机数生成器 [`XorShift`]
+//!
+//! 内核中没有可用的随机源，这里使用 xorshift64* 算法，种子由调用者提供
+
+/// xorshift64* 伪随机数生成器
+#[derive(Clone, Copy, Debug)]
+pub struct XorShift {
+    /// 当前状态，永远不为 0
+    state: u64,
+}
+
+impl XorShift {
+    /// 用给定的种子创建生成器
+    ///
+    /// 算法要求状态非零，种子为 0 时使用一个固定的非零值
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    /// 生成下一个 64 位随机数
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// 生成 `[0, bound)` 范围内的随机数，`bound` 必须大于 0
+    pub fn next_below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0);
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic_for_seed() {
+        let mut a = XorShift::new(42);
+        let mut b = XorShift::new(42);
+        for _ in 0..16 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(XorShift::new(1).next_u64(), XorShift::new(2).next_u64());
+    }
+
+    #[test]
+    fn zero_seed_is_usable() {
+        let mut random = XorShift::new(0);
+        let first = random.next_u64();
+        assert_ne!(first, 0);
+        assert_ne!(first, random.next_u64());
+    }
+
+    #[test]
+    fn below_bound_covers_range() {
+        let mut random = XorShift::new(7);
+        let mut seen = [false; 8];
+        for _ in 0..256 {
+            let value = random.next_below(8);
+            assert!(value < 8);
+            seen[value] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+}
@@ -0,0 +1,238 @@
+//! 完全公平调度器 [`CfsScheduler`]
+
+use super::Scheduler;
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// nice 值为 0 的线程的权重
+const NICE_0_WEIGHT: usize = 1024;
+
+/// nice 值从 -20 到 19 对应的权重，相邻两级之间约相差 1.25 倍
+const NICE_TO_WEIGHT: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// 线程被选中后至少执行的时间，避免过于频繁地切换
+///
+/// 单位与 [`Scheduler::thread_ran()`] 的参数相同，在 QEMU 中 `time` 寄存器为 10MHz，即 2ms
+const MIN_GRANULARITY: usize = 20_000;
+
+/// 被唤醒的线程最多可以比当前最小的虚拟运行时间提前多少，让等待 I/O 的线程能较快得到执行
+const WAKEUP_CREDIT: usize = MIN_GRANULARITY;
+
+/// 由优先级计算权重
+///
+/// 优先级 20 对应 nice 值 0，优先级每高 1，nice 值减 1，超出范围时取边界
+fn weight_of(priority: usize) -> usize {
+    NICE_TO_WEIGHT[40usize.saturating_sub(priority).min(39)]
+}
+
+/// 线程的调度信息
+struct Entity<ThreadType: Clone + Eq> {
+    /// 线程数据
+    thread: ThreadType,
+    /// 在有序树中的键值，即（虚拟运行时间，序号）
+    key: (usize, usize),
+    /// 权重
+    weight: usize,
+}
+
+/// 采用 CFS（完全公平调度）算法的调度器
+///
+/// 每个线程的虚拟运行时间按 `实际运行时间 * NICE_0_WEIGHT / 权重` 增长，
+/// 每次选择虚拟运行时间最小的线程执行。实际运行时间通过 [`Scheduler::thread_ran()`] 传入。
+pub struct CfsScheduler<ThreadType: Clone + Eq> {
+    /// 按虚拟运行时间排序的线程，相同时按加入的先后排序
+    tree: BTreeMap<(usize, usize), ThreadType>,
+    /// 所有线程的调度信息
+    entities: Vec<Entity<ThreadType>>,
+    /// 下一个线程的序号
+    next_sequence: usize,
+    /// 单调递增的最小虚拟运行时间，新加入的线程以此为起点
+    min_vruntime: usize,
+    /// 正在执行的线程，以及它被选中后执行的时间
+    current: Option<(ThreadType, usize)>,
+    /// 休眠中的线程，以及它们的虚拟运行时间
+    blocked: Vec<(ThreadType, usize)>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq> Default for CfsScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            tree: BTreeMap::new(),
+            entities: Vec::new(),
+            next_sequence: 0,
+            min_vruntime: 0,
+            current: None,
+            blocked: Vec::new(),
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq> CfsScheduler<ThreadType> {
+    /// 找到线程的调度信息
+    fn position(&self, thread: &ThreadType) -> Option<usize> {
+        self.entities
+            .iter()
+            .position(|entity| entity.thread == *thread)
+    }
+
+    /// 移除线程，返回它的虚拟运行时间
+    fn take(&mut self, thread: &ThreadType) -> Option<usize> {
+        let entity = self.entities.swap_remove(self.position(thread)?);
+        self.tree.remove(&entity.key);
+        if let Some((current, _)) = &self.current {
+            if current == thread {
+                self.current = None;
+            }
+        }
+        Some(entity.key.0)
+    }
+
+    /// 用最左侧线程的虚拟运行时间更新 `min_vruntime`
+    fn update_min_vruntime(&mut self) {
+        if let Some(&(vruntime, _)) = self.tree.keys().next() {
+            self.min_vruntime = self.min_vruntime.max(vruntime);
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for CfsScheduler<ThreadType> {
+    type Priority = usize;
+    /// 向线程池中添加一个线程
+    ///
+    /// 新线程从当前最小的虚拟运行时间开始，被唤醒的线程不会落后最小值超过 [`WAKEUP_CREDIT`]
+    fn add_thread(&mut self, thread: ThreadType, priority: Self::Priority) {
+        let vruntime = match self.blocked.iter().position(|(t, _)| *t == thread) {
+            Some(index) => {
+                let (_, vruntime) = self.blocked.swap_remove(index);
+                vruntime.max(self.min_vruntime.saturating_sub(WAKEUP_CREDIT))
+            }
+            None => self.min_vruntime,
+        };
+        let key = (vruntime, self.next_sequence);
+        self.next_sequence += 1;
+        self.tree.insert(key, thread.clone());
+        self.entities.push(Entity {
+            thread,
+            key,
+            weight: weight_of(priority),
+        });
+    }
+    /// 获取下一个时间段应当执行的线程
+    ///
+    /// 正在执行的线程执行不足 [`MIN_GRANULARITY`] 时继续执行，否则选择虚拟运行时间最小的线程
+    fn get_next(&mut self) -> Option<ThreadType> {
+        if let Some((thread, ran)) = &self.current {
+            if *ran < MIN_GRANULARITY {
+                return Some(thread.clone());
+            }
+        }
+        let thread = self.tree.values().next()?.clone();
+        self.current = Some((thread.clone(), 0));
+        Some(thread)
+    }
+    /// 移除一个线程
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        self.take(thread);
+    }
+    /// 设置线程的优先级，从下一次计算虚拟运行时间开始生效
+    fn set_priority(&mut self, thread: ThreadType, priority: Self::Priority) {
+        if let Some(index) = self.position(&thread) {
+            self.entities[index].weight = weight_of(priority);
+        }
+    }
+    /// 线程进入休眠，记录它的虚拟运行时间
+    fn block_thread(&mut self, thread: &ThreadType) {
+        if let Some(vruntime) = self.take(thread) {
+            self.blocked.push((thread.clone(), vruntime));
+        }
+    }
+    /// 休眠中的线程被结束，清除记录
+    fn forget_thread(&mut self, thread: &ThreadType) {
+        self.blocked.retain(|(t, _)| t != thread);
+    }
+    /// 按照线程实际执行的时间增加其虚拟运行时间
+    fn thread_ran(&mut self, thread: &ThreadType, elapsed: usize) {
+        let index = match self.position(thread) {
+            Some(index) => index,
+            None => return,
+        };
+        let entity = &mut self.entities[index];
+        let thread = self.tree.remove(&entity.key).unwrap();
+        entity.key.0 += elapsed * NICE_0_WEIGHT / entity.weight;
+        self.tree.insert(entity.key, thread);
+        if let Some((current, ran)) = &mut self.current {
+            if *current == entity.thread {
+                *ran += elapsed;
+            }
+        }
+        self.update_min_vruntime();
+    }
+    /// 按权重分配时间片：所有线程平均每人 `default`，权重越大分到的越长，但不短于 [`MIN_GRANULARITY`]
+    fn time_slice(&self, thread: &ThreadType, default: usize) -> usize {
+        let total_weight: usize = self.entities.iter().map(|entity| entity.weight).sum();
+        match self.position(thread) {
+            Some(index) => (default * self.entities.len() * self.entities[index].weight
+                / total_weight)
+                .max(MIN_GRANULARITY),
+            None => default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_smallest_vruntime() {
+        let mut scheduler = CfsScheduler::default();
+        scheduler.add_thread(1, 20);
+        scheduler.add_thread(2, 20);
+        // 虚拟运行时间相同时按加入顺序选择
+        assert_eq!(scheduler.get_next(), Some(1));
+        // 执行不足最小粒度时继续执行
+        scheduler.thread_ran(&1, MIN_GRANULARITY / 2);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.thread_ran(&1, MIN_GRANULARITY / 2);
+        assert_eq!(scheduler.get_next(), Some(2));
+        scheduler.thread_ran(&2, MIN_GRANULARITY * 2);
+        assert_eq!(scheduler.get_next(), Some(1));
+    }
+
+    #[test]
+    fn weight_scales_vruntime() {
+        let mut scheduler = CfsScheduler::default();
+        // 优先级 25 的权重约为优先级 20 的 3 倍
+        scheduler.add_thread(1, 25);
+        scheduler.add_thread(2, 20);
+        let mut runs = [0usize; 2];
+        for _ in 0..40 {
+            let thread = scheduler.get_next().unwrap();
+            runs[thread - 1] += 1;
+            scheduler.thread_ran(&thread, MIN_GRANULARITY);
+        }
+        assert!(runs[0] > runs[1] * 2, "{:?}", runs);
+    }
+
+    #[test]
+    fn woken_thread_gets_limited_credit() {
+        let mut scheduler = CfsScheduler::default();
+        scheduler.add_thread(1, 20);
+        scheduler.add_thread(2, 20);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.block_thread(&1);
+        for _ in 0..10 {
+            assert_eq!(scheduler.get_next(), Some(2));
+            scheduler.thread_ran(&2, MIN_GRANULARITY);
+        }
+        // 休眠很久的线程唤醒后先执行，但只领先 WAKEUP_CREDIT，执行一段时间后轮到线程 2
+        scheduler.add_thread(1, 20);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.thread_ran(&1, WAKEUP_CREDIT + MIN_GRANULARITY);
+        assert_eq!(scheduler.get_next(), Some(2));
+    }
+}
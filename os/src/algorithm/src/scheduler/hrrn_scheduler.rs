@@ -7,15 +7,18 @@ use alloc::collections::LinkedList;
 struct HrrnThread<ThreadType: Clone + Eq> {
     /// 进入线程池时，[`current_time`] 中的时间
     birth_time: usize,
-    /// 被分配时间片的次数
-    service_count: usize,
+    /// 进入线程池之后实际执行的时长
+    service_time: usize,
     /// 线程数据
     pub thread: ThreadType,
 }
 
 /// 采用 HRRN（最高响应比优先算法）的调度器
+///
+/// 响应比为 (等待时间 + 执行时间) / 执行时间，其中的时间都来自 [`Scheduler::thread_ran()`]
+/// 告知的实际执行时长，而不是被调度的次数
 pub struct HrrnScheduler<ThreadType: Clone + Eq> {
-    /// 当前时间，单位为 `get_next()` 调用次数
+    /// 当前时间，为线程池中所有线程已经执行的总时长
     current_time: usize,
     /// 带有调度信息的线程池
     pool: LinkedList<HrrnThread<ThreadType>>,
@@ -36,25 +39,21 @@ impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for HrrnScheduler<ThreadType>
     fn add_thread(&mut self, thread: ThreadType, _priority: Self::Priority) {
         self.pool.push_back(HrrnThread {
             birth_time: self.current_time,
-            service_count: 0,
+            service_time: 0,
             thread,
         })
     }
     fn get_next(&mut self) -> Option<ThreadType> {
-        // 计时
-        self.current_time += 1;
-
         // 遍历线程池，返回响应比最高者
+        // 时长的乘积可能超出 usize，使用 u128 比较
         let current_time = self.current_time; // borrow-check
-        if let Some(best) = self.pool.iter_mut().max_by(|x, y| {
-            ((current_time - x.birth_time) * y.service_count)
-                .cmp(&((current_time - y.birth_time) * x.service_count))
-        }) {
-            best.service_count += 1;
-            Some(best.thread.clone())
-        } else {
-            None
-        }
+        self.pool
+            .iter()
+            .max_by(|x, y| {
+                ((current_time - x.birth_time) as u128 * y.service_time as u128)
+                    .cmp(&((current_time - y.birth_time) as u128 * x.service_time as u128))
+            })
+            .map(|best| best.thread.clone())
     }
     fn remove_thread(&mut self, thread: &ThreadType) {
         // 移除相应的线程并且确认恰移除一个线程
@@ -62,4 +61,11 @@ impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for HrrnScheduler<ThreadType>
         assert!(removed.next().is_some() && removed.next().is_none());
     }
     fn set_priority(&mut self, _thread: ThreadType, _priority: Self::Priority) {}
+    fn thread_ran(&mut self, thread: &ThreadType, elapsed: usize) {
+        // 计时
+        self.current_time += elapsed;
+        if let Some(t) = self.pool.iter_mut().find(|t| t.thread == *thread) {
+            t.service_time += elapsed;
+        }
+    }
 }
@@ -0,0 +1,213 @@
+//! 彩票调度器 [`LotteryScheduler`]
+
+use super::Scheduler;
+use crate::XorShift;
+use alloc::vec::Vec;
+
+/// 默认的随机数种子，相同的种子和相同的负载会得到相同的调度顺序
+const DEFAULT_SEED: u64 = 0x5eed;
+
+/// 线程和它持有的彩票
+struct LotteryThread<ThreadType: Clone + Eq> {
+    /// 线程数据
+    thread: ThreadType,
+    /// 线程自己的彩票数量
+    tickets: usize,
+}
+
+/// 一次彩票转让
+struct Donation<ThreadType: Clone + Eq> {
+    /// 转出彩票的线程
+    from: ThreadType,
+    /// 接受彩票的线程
+    to: ThreadType,
+    /// 转让的数量
+    tickets: usize,
+}
+
+/// 采用彩票算法的调度器，`Priority` 即彩票数量
+///
+/// 每次从所有彩票中随机抽取一张，持有者获得下一个时间片，线程获得的时间片与其彩票数量成正比。
+/// 线程等待另一个线程时，可以通过 [`Scheduler::donate()`] 将彩票暂时转让给对方。
+pub struct LotteryScheduler<ThreadType: Clone + Eq> {
+    /// 可以被调度的线程
+    pool: Vec<LotteryThread<ThreadType>>,
+    /// 仍然有效的彩票转让
+    donations: Vec<Donation<ThreadType>>,
+    /// 抽奖用的伪随机数生成器
+    random: XorShift,
+}
+
+/// `Default` 使用默认种子创建一个空的调度器
+impl<ThreadType: Clone + Eq> Default for LotteryScheduler<ThreadType> {
+    fn default() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+}
+
+impl<ThreadType: Clone + Eq> LotteryScheduler<ThreadType> {
+    /// 使用给定的随机数种子创建一个空的调度器
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            pool: Vec::new(),
+            donations: Vec::new(),
+            random: XorShift::new(seed),
+        }
+    }
+
+    /// 线程当前持有的彩票数量，包括自己的和别人转让来的
+    fn tickets_of(&self, thread: &ThreadType) -> usize {
+        let own = self
+            .pool
+            .iter()
+            .find(|t| t.thread == *thread)
+            .map_or(0, |t| t.tickets);
+        own + self
+            .donations
+            .iter()
+            .filter(|donation| donation.to == *thread)
+            .map(|donation| donation.tickets)
+            .sum::<usize>()
+    }
+}
+
+/// 每个线程至少持有一张彩票，否则永远不会被调度
+fn tickets_of_priority(priority: usize) -> usize {
+    priority.max(1)
+}
+
+impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for LotteryScheduler<ThreadType> {
+    type Priority = usize;
+    /// 向线程池中添加一个线程
+    fn add_thread(&mut self, thread: ThreadType, priority: Self::Priority) {
+        self.pool.push(LotteryThread {
+            thread,
+            tickets: tickets_of_priority(priority),
+        });
+    }
+    /// 获取下一个时间段应当执行的线程
+    fn get_next(&mut self) -> Option<ThreadType> {
+        let counts: Vec<usize> = self
+            .pool
+            .iter()
+            .map(|t| self.tickets_of(&t.thread))
+            .collect();
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        // 抽取一张彩票，找到它的持有者
+        let mut winner = self.random.next_below(total);
+        for (t, count) in self.pool.iter().zip(counts) {
+            if winner < count {
+                return Some(t.thread.clone());
+            }
+            winner -= count;
+        }
+        unreachable!()
+    }
+    /// 移除一个线程，同时撤销与它有关的所有转让
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        self.pool.retain(|t| t.thread != *thread);
+        self.donations
+            .retain(|donation| donation.from != *thread && donation.to != *thread);
+    }
+    /// 设置线程的彩票数量
+    fn set_priority(&mut self, thread: ThreadType, priority: Self::Priority) {
+        if let Some(t) = self.pool.iter_mut().find(|t| t.thread == thread) {
+            t.tickets = tickets_of_priority(priority);
+        }
+    }
+    /// 线程进入休眠，它转出和收到的彩票都保持不变
+    fn block_thread(&mut self, thread: &ThreadType) {
+        self.pool.retain(|t| t.thread != *thread);
+    }
+    /// 休眠中的线程被结束，撤销与它有关的所有转让
+    fn forget_thread(&mut self, thread: &ThreadType) {
+        self.remove_thread(thread);
+    }
+    /// 将 `from` 当前持有的全部彩票转让给 `to`，直到 [`Scheduler::revoke()`]
+    fn donate(&mut self, from: &ThreadType, to: &ThreadType) {
+        if from == to {
+            return;
+        }
+        let tickets = self.tickets_of(from);
+        self.donations.push(Donation {
+            from: from.clone(),
+            to: to.clone(),
+            tickets,
+        });
+    }
+    /// 撤销 `from` 转让出的所有彩票
+    fn revoke(&mut self, from: &ThreadType) {
+        self.donations.retain(|donation| donation.from != *from);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_proportional_to_tickets() {
+        let mut scheduler = LotteryScheduler::with_seed(1);
+        scheduler.add_thread(1, 1);
+        scheduler.add_thread(2, 3);
+        let mut runs = [0usize; 2];
+        for _ in 0..4000 {
+            runs[scheduler.get_next().unwrap() - 1] += 1;
+        }
+        // 期望约为 1000 和 3000
+        assert!(runs[0] > 800 && runs[0] < 1200, "{:?}", runs);
+        // 没有彩票的线程仍然持有一张
+        scheduler.set_priority(2, 0);
+        assert_eq!(scheduler.tickets_of(&2), 1);
+    }
+
+    #[test]
+    fn revoke_restores_tickets() {
+        let mut scheduler = LotteryScheduler::with_seed(1);
+        scheduler.add_thread(1, 2);
+        scheduler.add_thread(2, 3);
+        scheduler.add_thread(3, 5);
+        // 线程 1 等待线程 2，休眠之前把彩票转让给它
+        scheduler.donate(&1, &2);
+        scheduler.block_thread(&1);
+        assert_eq!(scheduler.tickets_of(&2), 3 + 2);
+        assert_eq!(scheduler.tickets_of(&3), 5);
+        for _ in 0..100 {
+            assert_ne!(scheduler.get_next(), Some(1));
+        }
+        // 转让可以传递：线程 2 再等待线程 3 时，连同收到的彩票一起转让
+        scheduler.donate(&2, &3);
+        scheduler.block_thread(&2);
+        assert_eq!(scheduler.tickets_of(&3), 5 + 5);
+        scheduler.revoke(&2);
+        scheduler.add_thread(2, 3);
+        scheduler.revoke(&1);
+        scheduler.add_thread(1, 2);
+        assert_eq!(scheduler.tickets_of(&1), 2);
+        assert_eq!(scheduler.tickets_of(&2), 3);
+        assert_eq!(scheduler.tickets_of(&3), 5);
+    }
+
+    #[test]
+    fn forgotten_donor_drops_donation() {
+        let mut scheduler = LotteryScheduler::with_seed(1);
+        scheduler.add_thread(1, 4);
+        scheduler.add_thread(2, 1);
+        scheduler.donate(&1, &2);
+        scheduler.block_thread(&1);
+        assert_eq!(scheduler.tickets_of(&2), 5);
+        // 休眠中的转让者被结束，转让随之撤销
+        scheduler.forget_thread(&1);
+        assert_eq!(scheduler.tickets_of(&2), 1);
+        assert!(scheduler.donations.is_empty());
+        // 接受者被移除时同样撤销
+        scheduler.add_thread(3, 2);
+        scheduler.donate(&3, &2);
+        scheduler.remove_thread(&2);
+        assert!(scheduler.donations.is_empty());
+        assert_eq!(scheduler.get_next(), Some(3));
+    }
+}
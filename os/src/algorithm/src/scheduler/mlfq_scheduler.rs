@@ -0,0 +1,193 @@
+//! 多级反馈队列调度器 [`MlfqScheduler`]
+
+use super::Scheduler;
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// 队列的层数，第 0 层优先级最高
+const LEVELS: usize = 4;
+
+/// 每隔多少个时间片，将所有线程提升到最高层，防止低层的线程饥饿
+const BOOST_INTERVAL: usize = 64;
+
+/// 第 `level` 层的时间片长度，每下降一层翻倍
+fn quantum(level: usize) -> usize {
+    1 << level
+}
+
+/// 采用 MLFQ（多级反馈队列）算法的调度器
+///
+/// - 新线程进入最高层
+/// - 线程用完所在层的整个时间片后，下降一层
+/// - 线程在用完时间片之前进入休眠（如等待 I/O），被唤醒时上升一层
+/// - 每隔 [`BOOST_INTERVAL`] 个时间片，所有线程回到最高层
+///
+/// 需要 [`Scheduler::timer_preempted()`] 和 [`Scheduler::block_thread()`] 来区分这两种情况
+pub struct MlfqScheduler<ThreadType: Clone + Eq> {
+    /// 每一层的队列，正在执行的线程位于其所在队列的头部
+    queues: Vec<VecDeque<ThreadType>>,
+    /// 正在执行的线程所在的层
+    running: Option<usize>,
+    /// 正在执行的线程在当前层已经使用的时间片数量
+    used: usize,
+    /// 距离上一次提升经过的时间片数量
+    ticks: usize,
+    /// 休眠中的线程，以及它们休眠时所在的层
+    blocked: Vec<(ThreadType, usize)>,
+}
+
+/// `Default` 创建一个空的调度器
+impl<ThreadType: Clone + Eq> Default for MlfqScheduler<ThreadType> {
+    fn default() -> Self {
+        Self {
+            queues: (0..LEVELS).map(|_| VecDeque::new()).collect(),
+            running: None,
+            used: 0,
+            ticks: 0,
+            blocked: Vec::new(),
+        }
+    }
+}
+
+impl<ThreadType: Clone + Eq> MlfqScheduler<ThreadType> {
+    /// 将线程从所在的队列中移除，返回它所在的层
+    fn take(&mut self, thread: &ThreadType) -> Option<usize> {
+        for (level, queue) in self.queues.iter_mut().enumerate() {
+            if let Some(index) = queue.iter().position(|t| t == thread) {
+                queue.remove(index);
+                if index == 0 && self.running == Some(level) {
+                    self.running = None;
+                }
+                return Some(level);
+            }
+        }
+        None
+    }
+
+    /// 将所有线程提升到最高层
+    fn boost(&mut self) {
+        let (top, lower) = self.queues.split_at_mut(1);
+        for queue in lower.iter_mut() {
+            top[0].extend(queue.drain(..));
+        }
+        self.running = None;
+        self.ticks = 0;
+    }
+}
+
+impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for MlfqScheduler<ThreadType> {
+    type Priority = usize;
+    /// 向线程池中添加一个线程
+    ///
+    /// 新线程进入最高层，被唤醒的线程比休眠前上升一层
+    fn add_thread(&mut self, thread: ThreadType, _priority: Self::Priority) {
+        let level = match self.blocked.iter().position(|(t, _)| *t == thread) {
+            Some(index) => self.blocked.swap_remove(index).1.saturating_sub(1),
+            None => 0,
+        };
+        self.queues[level].push_back(thread);
+    }
+    /// 获取下一个时间段应当执行的线程
+    ///
+    /// 正在执行的线程在没有用完时间片、也没有更高层的线程时继续执行
+    fn get_next(&mut self) -> Option<ThreadType> {
+        if self.ticks >= BOOST_INTERVAL {
+            self.boost();
+        }
+        let top = self.queues.iter().position(|queue| !queue.is_empty())?;
+        if self.running != Some(top) {
+            // 切换到最高层队列头部的线程；被抢占的线程留在原队列头部
+            self.running = Some(top);
+            self.used = 0;
+        }
+        self.queues[top].front().cloned()
+    }
+    /// 移除一个线程
+    fn remove_thread(&mut self, thread: &ThreadType) {
+        self.take(thread);
+    }
+    /// 设置线程的优先级（MLFQ 根据线程的行为调整优先级，不使用这个值）
+    fn set_priority(&mut self, _thread: ThreadType, _priority: Self::Priority) {}
+    /// 线程被时钟中断打断，用完所在层的时间片后下降一层
+    fn timer_preempted(&mut self, thread: &ThreadType) {
+        self.ticks += 1;
+        let level = match self.running {
+            Some(level) if self.queues[level].front() == Some(thread) => level,
+            _ => return,
+        };
+        self.used += 1;
+        if self.used >= quantum(level) {
+            let thread = self.queues[level].pop_front().unwrap();
+            self.queues[(level + 1).min(LEVELS - 1)].push_back(thread);
+            self.running = None;
+        }
+    }
+    /// 线程进入休眠，记录它所在的层
+    fn block_thread(&mut self, thread: &ThreadType) {
+        if let Some(level) = self.take(thread) {
+            self.blocked.push((thread.clone(), level));
+        }
+    }
+    /// 休眠中的线程被结束，清除记录
+    fn forget_thread(&mut self, thread: &ThreadType) {
+        self.blocked.retain(|(t, _)| t != thread);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demote_after_quantum() {
+        let mut scheduler = MlfqScheduler::default();
+        scheduler.add_thread(1, 0);
+        scheduler.add_thread(2, 0);
+        // 第 0 层的时间片只有 1 个，用完后两个线程依次下降到第 1 层
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.timer_preempted(&1);
+        assert_eq!(scheduler.get_next(), Some(2));
+        scheduler.timer_preempted(&2);
+        // 第 1 层的时间片为 2 个
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.timer_preempted(&1);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.timer_preempted(&1);
+        assert_eq!(scheduler.get_next(), Some(2));
+    }
+
+    #[test]
+    fn new_and_woken_threads_run_first() {
+        let mut scheduler = MlfqScheduler::default();
+        scheduler.add_thread(1, 0);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.timer_preempted(&1);
+        // 新线程进入最高层，优先于已经下降的线程
+        scheduler.add_thread(2, 0);
+        assert_eq!(scheduler.get_next(), Some(2));
+        scheduler.timer_preempted(&2);
+        // 在第 1 层休眠的线程被唤醒后上升到第 0 层
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.block_thread(&1);
+        assert_eq!(scheduler.get_next(), Some(2));
+        scheduler.add_thread(1, 0);
+        assert_eq!(scheduler.get_next(), Some(1));
+    }
+
+    #[test]
+    fn boost_prevents_starvation() {
+        let mut scheduler = MlfqScheduler::default();
+        scheduler.add_thread(1, 0);
+        assert_eq!(scheduler.get_next(), Some(1));
+        scheduler.timer_preempted(&1);
+        // 线程 2 一直在用完时间片之前休眠，留在最高层，线程 1 只能在它休眠时执行
+        scheduler.add_thread(2, 0);
+        for _ in 0..BOOST_INTERVAL {
+            assert_eq!(scheduler.get_next(), Some(2));
+            scheduler.block_thread(&2);
+            scheduler.add_thread(2, 0);
+            scheduler.timer_preempted(&2);
+        }
+        // 提升之后线程 1 回到最高层，排在线程 2 之前
+        assert_eq!(scheduler.get_next(), Some(1));
+    }
+}
@@ -1,7 +1,11 @@
 //! 线程调度算法
 
+mod cfs_scheduler;
 mod fifo_scheduler;
 mod hrrn_scheduler;
+mod lottery_scheduler;
+mod mlfq_scheduler;
+mod realtime_scheduler;
 mod stride_pass_schedule;
 
 /// 线程调度器
@@ -13,6 +17,11 @@ mod stride_pass_schedule;
 ///   这个线程可能是上一个时间片所执行的线程。
 /// - 当一个线程结束时，需要调用 [`Scheduler::remove_thread()`] 来将其移除。这个方法必须在
 ///   [`Scheduler::get_next()`] 之前调用。
+/// - 时钟中断打断当前线程时，在 [`Scheduler::get_next()`] 之前调用 [`Scheduler::timer_preempted()`]。
+/// - 每次切换线程之前，调用 [`Scheduler::thread_ran()`] 告知当前线程实际执行的时间。
+/// - 线程进入休眠时，调用 [`Scheduler::block_thread()`] 将其移除，唤醒时再用
+///   [`Scheduler::add_thread()`] 加入。休眠中的线程被结束时，调用 [`Scheduler::forget_thread()`]。
+/// - 切换到 [`Scheduler::get_next()`] 返回的线程时，用 [`Scheduler::time_slice()`] 决定何时打断它。
 pub trait Scheduler<ThreadType: Clone + Eq>: Default {
     type Priority;
     /// 向线程池中添加一个线程
@@ -23,10 +32,49 @@ pub trait Scheduler<ThreadType: Clone + Eq>: Default {
     fn remove_thread(&mut self, thread: &ThreadType);
     /// 设置线程的优先级
     fn set_priority(&mut self, thread: ThreadType, priority: Self::Priority);
+    /// 线程被时钟中断打断
+    fn timer_preempted(&mut self, _thread: &ThreadType) {}
+    /// 线程刚刚实际执行了 `elapsed` 时长（内核中为 `time` 寄存器的计数）
+    fn thread_ran(&mut self, _thread: &ThreadType, _elapsed: usize) {}
+    /// 线程进入休眠（如等待 I/O），将其移除，之后被唤醒时会再次加入
+    fn block_thread(&mut self, thread: &ThreadType) {
+        self.remove_thread(thread);
+    }
+    /// 休眠中的线程被结束，不会再被唤醒
+    ///
+    /// 如果调度器在 [`Scheduler::block_thread()`] 时记录了线程的信息，需要在这里清除
+    fn forget_thread(&mut self, _thread: &ThreadType) {}
+    /// 线程 `from` 因等待 `to` 而休眠，将自己的调度份额暂时转让给 `to`
+    ///
+    /// 在 `from` 休眠之前调用，不支持转让的调度器可以忽略
+    fn donate(&mut self, _from: &ThreadType, _to: &ThreadType) {}
+    /// 撤销 `from` 之前的转让，在 `from` 被唤醒时调用
+    fn revoke(&mut self, _from: &ThreadType) {}
+    /// 线程被选中后，最多连续执行多长时间再被时钟中断打断
+    ///
+    /// `default` 为内核默认的时间片长度，单位与 [`Scheduler::thread_ran()`] 的参数相同
+    fn time_slice(&self, _thread: &ThreadType, default: usize) -> usize {
+        default
+    }
 }
 
+pub use cfs_scheduler::CfsScheduler;
 pub use fifo_scheduler::FifoScheduler;
 pub use hrrn_scheduler::HrrnScheduler;
+pub use lottery_scheduler::LotteryScheduler;
+pub use mlfq_scheduler::MlfqScheduler;
+pub use realtime_scheduler::{RealtimeParams, RealtimePolicy, RealtimeScheduler, RealtimeStats};
 pub use stride_pass_schedule::StridePassScheduler;
 
+/// 内核使用的调度器
+///
+/// 默认为 [`StridePassScheduler`]，开启 feature `lottery`、`cfs` 或 `mlfq` 可以换成对应的调度器，
+/// 从而在相同的负载下比较它们的效果
+#[cfg(not(any(feature = "lottery", feature = "cfs", feature = "mlfq")))]
 pub type SchedulerImpl<T> = StridePassScheduler<T>;
+#[cfg(feature = "lottery")]
+pub type SchedulerImpl<T> = LotteryScheduler<T>;
+#[cfg(feature = "cfs")]
+pub type SchedulerImpl<T> = CfsScheduler<T>;
+#[cfg(feature = "mlfq")]
+pub type SchedulerImpl<T> = MlfqScheduler<T>;
@@ -0,0 +1,325 @@
+//! 实时调度类 [`RealtimeScheduler`]
+//!
+//! 实时线程是周期性的：每个周期开始时获得 `budget` 的执行时间，必须在周期开始后的 `deadline`
+//! 之内用完，否则记为一次错过截止时间。用完预算的线程要等到下一个周期才会再被调度。
+//!
+//! 所有时间的单位由调用者决定，只要前后一致即可（内核中为 `time` 寄存器的计数）。
+
+use alloc::vec::Vec;
+
+/// 实时线程之间的调度策略
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RealtimePolicy {
+    /// 最早截止时间优先（EDF），总利用率不超过 100% 即可调度
+    EarliestDeadlineFirst,
+    /// 单调速率（RM），周期越短优先级越高，总利用率不超过 `n(2^(1/n) - 1)` 即可调度
+    RateMonotonic,
+}
+
+/// 实时线程的参数
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RealtimeParams {
+    /// 周期
+    pub period: usize,
+    /// 每个周期内的执行时间
+    pub budget: usize,
+    /// 相对于周期开始的截止时间，不能超过周期
+    pub deadline: usize,
+}
+
+/// 实时线程的统计信息
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RealtimeStats {
+    /// 经过的周期数
+    pub periods: usize,
+    /// 错过截止时间的次数
+    pub misses: usize,
+}
+
+/// 利用率的单位，即百万分之一
+const UTILIZATION_UNIT: usize = 1_000_000;
+
+/// RM 策略下 n 个线程可调度的利用率上界 `n(2^(1/n) - 1)`，超过 10 个时取极限 ln 2
+const RATE_MONOTONIC_BOUND: [usize; 10] = [
+    1_000_000, 828_427, 779_763, 756_828, 743_491, 734_772, 728_626, 724_062, 720_538, 717_735,
+];
+const RATE_MONOTONIC_LIMIT: usize = 693_147;
+
+impl RealtimeParams {
+    /// 利用率，即每个周期内需要执行的比例（以截止时间计）
+    fn utilization(&self) -> usize {
+        self.budget * UTILIZATION_UNIT / self.deadline
+    }
+}
+
+/// 一个实时线程
+struct RealtimeTask<ThreadType: Clone + Eq> {
+    /// 线程数据
+    thread: ThreadType,
+    /// 参数
+    params: RealtimeParams,
+    /// 当前周期开始的时间
+    release: usize,
+    /// 当前周期剩余的预算
+    remaining: usize,
+    /// 是否正在休眠
+    sleeping: bool,
+    /// 当前周期是否已经记录过错过截止时间
+    missed: bool,
+    /// 统计信息
+    stats: RealtimeStats,
+}
+
+impl<ThreadType: Clone + Eq> RealtimeTask<ThreadType> {
+    /// 当前周期的绝对截止时间
+    fn deadline(&self) -> usize {
+        self.release + self.params.deadline
+    }
+
+    /// 检查截止时间，并在进入新的周期时补充预算
+    fn update(&mut self, now: usize) {
+        if now >= self.deadline() && self.remaining > 0 && !self.sleeping && !self.missed {
+            self.missed = true;
+            self.stats.misses += 1;
+        }
+        if now >= self.release + self.params.period {
+            let periods = (now - self.release) / self.params.period;
+            self.release += periods * self.params.period;
+            self.remaining = self.params.budget;
+            self.missed = false;
+            self.stats.periods += periods;
+        }
+    }
+}
+
+/// 实时调度类，其中的线程总是优先于普通线程执行
+pub struct RealtimeScheduler<ThreadType: Clone + Eq> {
+    /// 调度策略
+    policy: RealtimePolicy,
+    /// 所有实时线程
+    tasks: Vec<RealtimeTask<ThreadType>>,
+}
+
+/// `Default` 创建一个使用 EDF 策略的空调度类
+impl<ThreadType: Clone + Eq> Default for RealtimeScheduler<ThreadType> {
+    fn default() -> Self {
+        Self::new(RealtimePolicy::EarliestDeadlineFirst)
+    }
+}
+
+impl<ThreadType: Clone + Eq> RealtimeScheduler<ThreadType> {
+    /// 创建一个使用给定策略的空调度类
+    pub fn new(policy: RealtimePolicy) -> Self {
+        Self {
+            policy,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// 线程是否属于实时调度类
+    pub fn contains(&self, thread: &ThreadType) -> bool {
+        self.tasks.iter().any(|task| task.thread == *thread)
+    }
+
+    /// 是否没有任何实时线程
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// 是否有没有休眠的线程
+    ///
+    /// 在 [`RealtimeScheduler::get_next()`] 返回 `None` 时，这些线程都在等待下一个周期
+    pub fn has_throttled(&self) -> bool {
+        self.tasks.iter().any(|task| !task.sleeping)
+    }
+
+    /// 将线程加入实时调度类，或者修改已有实时线程的参数
+    ///
+    /// 加入后总利用率超过当前策略的可调度上界时拒绝
+    pub fn admit(
+        &mut self,
+        thread: ThreadType,
+        params: RealtimeParams,
+        now: usize,
+    ) -> Result<(), &'static str> {
+        if params.budget == 0 || params.budget > params.deadline || params.deadline > params.period
+        {
+            return Err("invalid realtime parameters");
+        }
+        let others = self.tasks.iter().filter(|task| task.thread != thread);
+        let count = others.clone().count() + 1;
+        let utilization =
+            others.map(|task| task.params.utilization()).sum::<usize>() + params.utilization();
+        let bound = match self.policy {
+            RealtimePolicy::EarliestDeadlineFirst => UTILIZATION_UNIT,
+            RealtimePolicy::RateMonotonic => RATE_MONOTONIC_BOUND
+                .get(count - 1)
+                .copied()
+                .unwrap_or(RATE_MONOTONIC_LIMIT),
+        };
+        if utilization > bound {
+            return Err("realtime utilization exceeds schedulable bound");
+        }
+        let task = RealtimeTask {
+            thread,
+            params,
+            release: now,
+            remaining: params.budget,
+            sleeping: false,
+            missed: false,
+            stats: RealtimeStats::default(),
+        };
+        match self.tasks.iter_mut().find(|t| t.thread == task.thread) {
+            // 修改参数时保留统计信息和休眠状态，从现在开始新的周期
+            Some(existing) => {
+                *existing = RealtimeTask {
+                    sleeping: existing.sleeping,
+                    stats: existing.stats,
+                    ..task
+                }
+            }
+            None => self.tasks.push(task),
+        }
+        Ok(())
+    }
+
+    /// 将线程移出实时调度类，返回其统计信息
+    pub fn remove(&mut self, thread: &ThreadType) -> Option<RealtimeStats> {
+        let index = self.tasks.iter().position(|task| task.thread == *thread)?;
+        Some(self.tasks.remove(index).stats)
+    }
+
+    /// 线程进入休眠
+    pub fn block(&mut self, thread: &ThreadType) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.thread == *thread) {
+            task.sleeping = true;
+        }
+    }
+
+    /// 线程被唤醒
+    pub fn wake(&mut self, thread: &ThreadType) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.thread == *thread) {
+            task.sleeping = false;
+        }
+    }
+
+    /// 扣除线程实际执行的时间
+    pub fn thread_ran(&mut self, thread: &ThreadType, elapsed: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.thread == *thread) {
+            task.remaining = task.remaining.saturating_sub(elapsed);
+        }
+    }
+
+    /// 获取下一个应当执行的实时线程，没有可以执行的实时线程时返回 `None`
+    pub fn get_next(&mut self, now: usize) -> Option<ThreadType> {
+        for task in self.tasks.iter_mut() {
+            task.update(now);
+        }
+        let ready = self
+            .tasks
+            .iter()
+            .filter(|task| !task.sleeping && task.remaining > 0);
+        let next = match self.policy {
+            RealtimePolicy::EarliestDeadlineFirst => ready.min_by_key(|task| task.deadline()),
+            RealtimePolicy::RateMonotonic => {
+                ready.min_by_key(|task| (task.params.period, task.deadline()))
+            }
+        };
+        next.map(|task| task.thread.clone())
+    }
+
+    /// 线程在当前周期剩余的预算，用完之后应当打断它
+    pub fn time_slice(&self, thread: &ThreadType) -> Option<usize> {
+        self.tasks
+            .iter()
+            .find(|task| task.thread == *thread)
+            .map(|task| task.remaining)
+    }
+
+    /// 最近的下一个周期开始的时间，届时可能有实时线程需要执行
+    ///
+    /// 没有醒着的实时线程时返回 `None`
+    pub fn next_release(&self) -> Option<usize> {
+        self.tasks
+            .iter()
+            .filter(|task| !task.sleeping)
+            .map(|task| task.release + task.params.period)
+            .min()
+    }
+
+    /// 线程的统计信息
+    pub fn stats(&self, thread: &ThreadType) -> Option<RealtimeStats> {
+        self.tasks
+            .iter()
+            .find(|task| task.thread == *thread)
+            .map(|task| task.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(period: usize, budget: usize) -> RealtimeParams {
+        RealtimeParams {
+            period,
+            budget,
+            deadline: period,
+        }
+    }
+
+    #[test]
+    fn earliest_deadline_first() {
+        let mut scheduler = RealtimeScheduler::new(RealtimePolicy::EarliestDeadlineFirst);
+        scheduler.admit(1, params(100, 20), 0).unwrap();
+        scheduler.admit(2, params(50, 10), 0).unwrap();
+        assert_eq!(scheduler.get_next(0), Some(2));
+        // 用完预算后等待下一个周期
+        scheduler.thread_ran(&2, 10);
+        assert_eq!(scheduler.get_next(10), Some(1));
+        scheduler.thread_ran(&1, 20);
+        assert_eq!(scheduler.get_next(30), None);
+        assert_eq!(scheduler.next_release(), Some(50));
+        assert_eq!(scheduler.get_next(50), Some(2));
+    }
+
+    #[test]
+    fn rate_monotonic_prefers_short_period() {
+        let mut scheduler = RealtimeScheduler::new(RealtimePolicy::RateMonotonic);
+        scheduler.admit(1, params(100, 10), 0).unwrap();
+        scheduler.admit(2, params(40, 10), 20).unwrap();
+        // 线程 1 的截止时间更早，但线程 2 的周期更短
+        assert_eq!(scheduler.get_next(20), Some(2));
+    }
+
+    #[test]
+    fn admission_bound() {
+        let mut edf = RealtimeScheduler::new(RealtimePolicy::EarliestDeadlineFirst);
+        edf.admit(1, params(100, 50), 0).unwrap();
+        edf.admit(2, params(100, 50), 0).unwrap();
+        assert!(edf.admit(3, params(100, 1), 0).is_err());
+        // 两个线程时 RM 的上界约为 82.8%
+        let mut rm = RealtimeScheduler::new(RealtimePolicy::RateMonotonic);
+        rm.admit(1, params(100, 50), 0).unwrap();
+        assert!(rm.admit(2, params(100, 40), 0).is_err());
+        rm.admit(2, params(100, 30), 0).unwrap();
+    }
+
+    #[test]
+    fn count_deadline_misses() {
+        let mut scheduler = RealtimeScheduler::new(RealtimePolicy::EarliestDeadlineFirst);
+        scheduler.admit(1, params(100, 20), 0).unwrap();
+        scheduler.thread_ran(&1, 10);
+        // 截止时间过去时仍有预算没有用完
+        scheduler.get_next(100);
+        scheduler.get_next(150);
+        assert_eq!(
+            scheduler.stats(&1),
+            Some(RealtimeStats {
+                periods: 1,
+                misses: 1
+            })
+        );
+        assert_eq!(scheduler.get_next(150), Some(1));
+    }
+}
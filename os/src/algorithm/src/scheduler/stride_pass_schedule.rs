@@ -25,6 +25,12 @@ fn overflow_add(a: &mut UNSIGNED, b: UNSIGNED) {
     }
 }
 
+//由优先级计算每次执行后 stride 的增量，优先级越高增量越小
+fn pass_of(priority: usize) -> UNSIGNED {
+    assert!((priority as UNSIGNED) < UNSIGNED::MAX);
+    MAX_STRIDE / (priority as UNSIGNED + PRORITY_ODD) //真正的优先级应该大于0
+}
+
 //当x和y之差的绝对值小于SIGNED的最大绝对值的时候，可以比较大小
 fn overflow_cmp(x: UNSIGNED, y: UNSIGNED) -> Ordering {
     if x == y {
@@ -69,15 +75,20 @@ pub struct StridePassScheduler<ThreadType: Clone + Eq> {
 }
 
 impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for StridePassScheduler<ThreadType> {
-    type Priority = UNSIGNED;
+    type Priority = usize;
     /// 向线程池中添加一个线程
+    ///
+    /// 新加入（包括被唤醒）的线程从当前最小的 stride 开始，避免它长时间独占或者长时间得不到执行
     fn add_thread(&mut self, thread: ThreadType, priority: Self::Priority) {
-        assert!(priority < UNSIGNED::MAX);
+        let stride = match self.pool.iter().min() {
+            Some(node) => node.stride,
+            None => DEFAULT_STRIDE,
+        };
         self.pool.push_back(ThreadNode {
             thread,
             id: self.pool.len(),
-            stride: DEFAULT_STRIDE,
-            pass: MAX_STRIDE / (priority + PRORITY_ODD), //真正的优先级应该大于0
+            stride,
+            pass: pass_of(priority),
         });
     }
     /// 获取下一个时间段应当执行的线程
@@ -95,10 +106,9 @@ impl<ThreadType: Clone + Eq> Scheduler<ThreadType> for StridePassScheduler<Threa
     }
     /// 设置线程的优先级
     fn set_priority(&mut self, thread: ThreadType, priority: Self::Priority) {
-        assert!(priority < UNSIGNED::MAX);
         for node in self.pool.iter_mut() {
             if node.thread == thread {
-                node.pass = MAX_STRIDE / (priority + PRORITY_ODD);
+                node.pass = pass_of(priority);
                 break;
             }
         }
@@ -11,9 +11,13 @@
 //! [`write_str`]: core::fmt::Write::write_str
 //! [`write_fmt`]: core::fmt::Write::write_fmt
 
+use crate::process::Lock;
 use crate::sbi::*;
 use core::fmt::{self, Write};
 
+/// 多个 hart 同时输出时，保证每一次 `print` 的内容不会交错
+static PRINT_LOCK: Lock<()> = Lock::new(());
+
 /// 用0大小结构体作为名字空间
 ///
 /// ZST[Zero-Sized Type] 只可能有一个值（即为空），因此它本身就是一个单件
@@ -40,6 +44,7 @@ impl Write for Stdout {
 ///
 /// [`core::format_args!`]: https://doc.rust-lang.org/nightly/core/macro.format_args.html
 pub fn print(args: fmt::Arguments) {
+    let _guard = PRINT_LOCK.get();
     Stdout.write_fmt(args).unwrap();
 }
 
@@ -57,6 +62,9 @@ macro_rules! print{
 ///
 /// 使用实现了 [`core::fmt::Write`] trait 的 [`console::Stdout`]
 macro_rules! println {
+    () => {
+        $crate::console::print(format_args!("\n"));
+    };
     ($fmt: literal $(, $($arg: tt)+)?) => {
         $crate::console::print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
     }
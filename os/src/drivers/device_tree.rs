@@ -3,15 +3,66 @@
 //! 递归遍历设备树并初始化
 
 use super::bus::virtio_mmio::virtio_probe;
+use crate::interrupt::set_clock_frequency;
 use crate::memory::VirtualAddress;
+use alloc::string::String;
 use core::slice;
 use device_tree::{DeviceTree, Node};
+use spin::Once;
 
 /// 验证某内存段为设备树格式的 Magic Number（固定）
 const DEVICE_TREE_MAGIC: u32 = 0xd00d_feed;
 
+/// 启动参数，来自设备树中 `/chosen` 节点的 `bootargs` 属性
+static BOOTARGS: Once<String> = Once::new();
+
+/// 引导程序提供的随机数种子，来自设备树中 `/chosen` 节点的 `rng-seed` 属性
+static RNG_SEED: Once<u64> = Once::new();
+
+/// 引导程序提供的随机数种子（按字节折叠为 64 位），没有提供时返回 `None`
+pub fn rng_seed() -> Option<u64> {
+    RNG_SEED.r#try().copied()
+}
+
+/// 启动参数中是否包含某一项（以空白分隔）
+pub fn has_boot_option(option: &str) -> bool {
+    match BOOTARGS.r#try() {
+        Some(bootargs) => bootargs.split_whitespace().any(|arg| arg == option),
+        None => false,
+    }
+}
+
+/// 启动参数中形如 `key=value` 的一项的值，没有这一项时返回 `None`
+pub fn boot_option(key: &str) -> Option<&'static str> {
+    BOOTARGS.r#try()?.split_whitespace().find_map(|arg| {
+        let mut parts = arg.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if name == key => Some(value),
+            _ => None,
+        }
+    })
+}
+
 /// 递归遍历设备树
 fn walk(node: &Node) {
+    // `time` 寄存器的频率
+    if node.name == "cpus" {
+        if let Ok(frequency) = node.prop_u32("timebase-frequency") {
+            set_clock_frequency(frequency as usize);
+        }
+    }
+    // 记录启动参数
+    if node.name == "chosen" {
+        if let Ok(bootargs) = node.prop_str("bootargs") {
+            BOOTARGS.call_once(|| String::from(bootargs));
+        }
+        if let Some(seed) = node.prop_raw("rng-seed") {
+            let seed = seed.iter().enumerate().fold(0u64, |seed, (i, byte)| {
+                seed ^ ((*byte as u64) << (i % 8 * 8))
+            });
+            RNG_SEED.call_once(|| seed);
+        }
+    }
     // 检查设备的协议支持并初始化
     if let Ok(compatible) = node.prop_str("compatible") {
         if compatible == "virtio,mmio" {
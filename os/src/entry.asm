@@ -6,10 +6,19 @@
 # 关于 RISC-V 下的汇编语言，可以参考 https://github.com/riscv/riscv-asm-manual/blob/master/riscv-asm.md
 # %hi 表示取 [12,32) 位，%lo 表示取 [0,12) 位
 
+# 最多支持的 hart 数量，需要和 smp::MAX_HARTS 保持一致
+.set    MAX_HARTS, 4
+# 每个 hart 的启动栈大小
+.set    BOOT_STACK_SIZE, 4096 * 16
+
     .section .text.entry
     .globl _start
 # 目前 _start 的功能：将预留的栈空间写入 $sp，然后跳转至 rust_main
+# 每个 hart 都从这里开始执行，a0 为 hart 编号
 _start:
+    # 编号超出支持范围的 hart 不参与运行
+    li t0, MAX_HARTS
+    bgeu a0, t0, park
     # 通过线性映射关系计算 boot_page_table 的物理页号
     lui t0, %hi(boot_page_table)
     li t1, 0xffffffff00000000
@@ -22,22 +31,32 @@ _start:
     csrw satp, t0
     sfence.vma
 
-    # 加载栈的虚拟地址
+    # 加载栈的虚拟地址，每个 hart 使用各自的启动栈：boot_stack_top - hart 编号 * BOOT_STACK_SIZE
     lui sp, %hi(boot_stack_top)
     addi sp, sp, %lo(boot_stack_top)
+    li t0, BOOT_STACK_SIZE
+    mul t0, t0, a0
+    sub sp, sp, t0
+    # 内核中始终用 tp 保存 hart 编号
+    mv tp, a0
     # 跳转至 rust_main
     # 这里同时伴随 hart 和 dtb_pa 两个指针的传入（是 OpenSBI 帮我们完成的）
     lui t0, %hi(rust_main)
     addi t0, t0, %lo(rust_main)
     jr t0
 
+# 不参与运行的 hart 在这里停住
+park:
+    wfi
+    j park
+
     # 回忆：bss 段是 ELF 文件中只记录长度，而全部初始化为 0 的一段内存空间
     # 这里声明字段 .bss.stack 作为操作系统启动时的栈
     .section .bss.stack
     .global boot_stack
 boot_stack:
-    # 16K 启动栈大小
-    .space 4096 * 16
+    # 每个 hart 16K 启动栈大小
+    .space BOOT_STACK_SIZE * MAX_HARTS
     .global boot_stack_top
 boot_stack_top:
     # 栈结尾
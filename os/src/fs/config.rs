@@ -2,3 +2,18 @@
 
 /// 块设备的 Cache 块个数
 pub const BLOCK_CACHE_CAPACITY: usize = 0x10;
+
+/// 管道缓冲区的容量（字节）
+pub const PIPE_CAPACITY: usize = 0x1000;
+
+/// 数据报套接字接收队列最多容纳的数据报数量
+pub const DATAGRAM_CAPACITY: usize = 0x10;
+
+/// 数据报的最大长度（字节）
+pub const DATAGRAM_MAX_SIZE: usize = 0x400;
+
+/// 消息队列最多容纳的消息数量的上限
+pub const MQ_MAX_CAPACITY: usize = 0x40;
+
+/// 消息队列中每条消息最大长度的上限（字节）
+pub const MQ_MAX_SIZE: usize = 0x1000;
@@ -2,6 +2,23 @@
 
 use super::*;
 
+/// 从 [`INode::as_any_ref`] 转换为某个实现了 [`Pollable`] 的具体类型，类型不符时返回 `None`
+type PollableCast = fn(&dyn Any) -> Option<&dyn Pollable>;
+
+/// 所有实现了 [`Pollable`] 的文件类型，新增的类型需要加入这里
+const POLLABLE_TYPES: &[PollableCast] = &[
+    cast::<Stdin>,
+    cast::<PipeReader>,
+    cast::<PipeWriter>,
+    cast::<MessageQueueHandle>,
+    cast::<UnixSocket>,
+    cast::<TimerFd>,
+];
+
+fn cast<T: Pollable + 'static>(any: &dyn Any) -> Option<&dyn Pollable> {
+    any.downcast_ref::<T>().map(|inode| inode as &dyn Pollable)
+}
+
 /// 为 [`INode`] 类型添加的扩展功能
 pub trait INodeExt {
     /// 打印当前目录的文件
@@ -9,6 +26,11 @@ pub trait INodeExt {
 
     /// 读取文件内容
     fn readall(&self) -> Result<Vec<u8>>;
+
+    /// 如果是状态会改变的文件，转换为 [`Pollable`]
+    ///
+    /// 磁盘上的文件总是可以读写，返回 `None`
+    fn as_pollable(&self) -> Option<&dyn Pollable>;
 }
 
 impl INodeExt for dyn INode {
@@ -29,4 +51,9 @@ impl INodeExt for dyn INode {
         self.read_at(0, buffer.as_mut_slice())?;
         Ok(buffer)
     }
+
+    fn as_pollable(&self) -> Option<&dyn Pollable> {
+        let any = self.as_any_ref();
+        POLLABLE_TYPES.iter().find_map(|cast| cast(any))
+    }
 }
@@ -14,15 +14,25 @@ use spin::Mutex;
 
 mod config;
 mod inode_ext;
+mod mqueue;
+mod pipe;
+mod poll;
+mod socket;
 mod stdin;
 mod stdout;
+mod timerfd;
 
 pub use crate::kernel::Condvar;
 pub use config::*;
 pub use inode_ext::INodeExt;
+pub use mqueue::{MessageQueue, MessageQueueHandle, MessageWait};
+pub use pipe::{pipe, PipeReader, PipeWriter};
+pub use poll::{PollQueue, Pollable, Poller};
 pub use rcore_fs::{dev::block_cache::BlockCache, vfs::*};
-pub use stdin::STDIN;
+pub use socket::{unlink, SocketType, UnixSocket};
+pub use stdin::{Stdin, STDIN};
 pub use stdout::STDOUT;
+pub use timerfd::TimerFd;
 
 lazy_static! {
     /// 根文件系统的根目录的 INode
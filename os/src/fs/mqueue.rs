@@ -0,0 +1,259 @@
+//! 消息队列 [`MessageQueue`]
+//!
+//! 与管道不同，消息队列保留每条消息的边界，并按优先级取出消息。
+//! 进程通过 [`MessageQueueHandle`] 访问队列，阻塞与否是每次打开时的属性
+
+use super::*;
+use crate::interrupt::{ticks_to_us, us_to_ticks};
+use crate::process::{Lock, LockGuard};
+use alloc::{collections::BinaryHeap, vec::Vec};
+use core::cmp::{Ordering, Reverse};
+use riscv::register::time;
+
+/// 队列已满或为空时的处理方式
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MessageWait {
+    /// 立即返回 [`FsError::Again`]
+    NonBlocking,
+    /// 一直等待
+    Forever,
+    /// 最多等待给定的微秒数，超时返回 [`FsError::Again`]
+    Timeout(usize),
+}
+
+/// 队列中的一条消息
+struct Message {
+    /// 优先级，越大越先取出
+    priority: usize,
+    /// 发送的序号，同一优先级的消息先发送的先取出
+    sequence: usize,
+    data: Vec<u8>,
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Message {}
+
+impl PartialOrd for Message {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// [`BinaryHeap`] 先弹出最大的元素：优先级高的、序号小的更大
+impl Ord for Message {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, Reverse(self.sequence)).cmp(&(other.priority, Reverse(other.sequence)))
+    }
+}
+
+/// 消息队列中需要加锁的部分
+struct MessageQueueInner {
+    messages: BinaryHeap<Message>,
+    /// 下一条消息的序号
+    sequence: usize,
+}
+
+/// 消息队列
+pub struct MessageQueue {
+    /// 最多容纳的消息数量
+    pub capacity: usize,
+    /// 每条消息的最大长度（字节）
+    pub max_size: usize,
+    inner: Lock<MessageQueueInner>,
+    /// 等待消息的接收者在此休眠
+    readable: Condvar,
+    /// 等待空位的发送者在此休眠
+    writable: Condvar,
+    /// 收发消息时唤醒 poll 的线程
+    poll_queue: PollQueue,
+}
+
+impl MessageQueue {
+    /// 创建一个空的消息队列
+    ///
+    /// 消息在发送时才分配空间，`capacity` 和 `max_size` 的上限由调用者检查（见 [`MQ_MAX_CAPACITY`] 和 [`MQ_MAX_SIZE`]）
+    pub fn new(capacity: usize, max_size: usize) -> Self {
+        Self {
+            capacity,
+            max_size,
+            inner: Lock::new(MessageQueueInner {
+                messages: BinaryHeap::new(),
+                sequence: 0,
+            }),
+            readable: Condvar::default(),
+            writable: Condvar::default(),
+            poll_queue: PollQueue::default(),
+        }
+    }
+
+    /// 以 `priority` 发送一条消息，队列已满时按 `wait` 处理
+    ///
+    /// 消息超过 [`MessageQueue::max_size`] 时返回 [`FsError::InvalidParam`]
+    pub fn send(&self, data: &[u8], priority: usize, wait: MessageWait) -> Result<()> {
+        if data.len() > self.max_size {
+            return Err(FsError::InvalidParam);
+        }
+        let deadline = Deadline::from(wait);
+        let mut inner = self.inner.get();
+        while inner.messages.len() == self.capacity {
+            inner = wait_until(&self.writable, inner, deadline)?;
+        }
+        let sequence = inner.sequence;
+        inner.sequence += 1;
+        inner.messages.push(Message {
+            priority,
+            sequence,
+            data: data.to_vec(),
+        });
+        self.readable.notify_one();
+        self.poll_queue.notify();
+        Ok(())
+    }
+
+    /// 取出优先级最高的消息写入 `buffer`，队列为空时按 `wait` 处理
+    ///
+    /// 返回消息的长度和优先级。`buffer` 小于 [`MessageQueue::max_size`] 时返回 [`FsError::InvalidParam`]
+    pub fn receive(&self, buffer: &mut [u8], wait: MessageWait) -> Result<(usize, usize)> {
+        if buffer.len() < self.max_size {
+            return Err(FsError::InvalidParam);
+        }
+        let deadline = Deadline::from(wait);
+        let mut inner = self.inner.get();
+        let message = loop {
+            match inner.messages.pop() {
+                Some(message) => break message,
+                None => inner = wait_until(&self.readable, inner, deadline)?,
+            }
+        };
+        self.writable.notify_one();
+        self.poll_queue.notify();
+        buffer[..message.data.len()].copy_from_slice(&message.data);
+        Ok((message.data.len(), message.priority))
+    }
+
+    /// 队列中是否有消息，以及是否还有空位
+    pub fn status(&self) -> (bool, bool) {
+        let inner = self.inner.get();
+        (
+            !inner.messages.is_empty(),
+            inner.messages.len() < self.capacity,
+        )
+    }
+}
+
+impl Pollable for MessageQueue {
+    /// 注册到收发消息时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.poll_queue.register(poller);
+    }
+}
+
+/// 由 [`MessageWait`] 得到的截止时间
+#[derive(Copy, Clone)]
+enum Deadline {
+    /// 不等待
+    Now,
+    /// 一直等待
+    Never,
+    /// 等待到 `time` 寄存器到达这个值
+    At(usize),
+}
+
+impl From<MessageWait> for Deadline {
+    fn from(wait: MessageWait) -> Self {
+        match wait {
+            MessageWait::NonBlocking => Deadline::Now,
+            MessageWait::Forever => Deadline::Never,
+            MessageWait::Timeout(us) => Deadline::At(time::read() + us_to_ticks(us)),
+        }
+    }
+}
+
+/// 在 `condvar` 上等待一次，不等待或者已经超时返回 [`FsError::Again`]
+fn wait_until<'a, T>(
+    condvar: &Condvar,
+    guard: LockGuard<'a, T>,
+    deadline: Deadline,
+) -> Result<LockGuard<'a, T>> {
+    match deadline {
+        Deadline::Now => Err(FsError::Again),
+        Deadline::Never => Ok(condvar.wait(guard)),
+        Deadline::At(deadline) => {
+            let now = time::read();
+            if now >= deadline {
+                return Err(FsError::Again);
+            }
+            let (guard, _) = condvar.wait_timeout(guard, ticks_to_us(deadline - now));
+            Ok(guard)
+        }
+    }
+}
+
+/// 进程打开的消息队列，放在文件描述符表中
+pub struct MessageQueueHandle {
+    pub queue: Arc<MessageQueue>,
+    /// 是否以非阻塞方式打开
+    pub nonblocking: bool,
+}
+
+impl MessageQueueHandle {
+    /// 这次操作的等待方式，`timeout` 为 0 表示一直等待
+    pub fn wait(&self, timeout: usize) -> MessageWait {
+        if self.nonblocking {
+            MessageWait::NonBlocking
+        } else if timeout == 0 {
+            MessageWait::Forever
+        } else {
+            MessageWait::Timeout(timeout)
+        }
+    }
+}
+
+impl Pollable for MessageQueueHandle {
+    /// 注册到队列收发消息时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.queue.register(poller);
+    }
+}
+
+impl INode for MessageQueueHandle {
+    /// 接收一条消息，返回消息的长度
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        self.queue
+            .receive(buf, self.wait(0))
+            .map(|(length, _)| length)
+    }
+
+    /// 以优先级 0 发送一条消息
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        self.queue.send(buf, 0, self.wait(0)).map(|_| buf.len())
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let (read, write) = self.queue.status();
+        Ok(PollStatus {
+            read,
+            write,
+            error: false,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
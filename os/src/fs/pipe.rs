@@ -0,0 +1,195 @@
+//! 管道 [`PipeReader`] 和 [`PipeWriter`]
+//!
+//! 与 [`Stdin`] 相同，通过 [`Condvar`] 让读写的线程在缓冲区空或满时休眠。
+//! 读端和写端分别实现 [`INode`]，两端的计数在 drop 时减少：
+//! 所有写端关闭后读到末尾返回 0，所有读端关闭后写入会失败
+//!
+//! [`Stdin`]: super::stdin::Stdin
+
+use super::*;
+use crate::process::Lock;
+use alloc::collections::VecDeque;
+
+/// 管道两端共享的缓冲区
+struct PipeBuffer {
+    /// 从后插入，从前弹出，长度不超过 [`PIPE_CAPACITY`]
+    data: VecDeque<u8>,
+    /// 还没有关闭的读端数量
+    readers: usize,
+    /// 还没有关闭的写端数量
+    writers: usize,
+}
+
+/// 管道，由 [`PipeReader`] 和 [`PipeWriter`] 共享
+struct Pipe {
+    buffer: Lock<PipeBuffer>,
+    /// 等待数据的读者在此休眠
+    readable: Condvar,
+    /// 等待空间的写者在此休眠
+    writable: Condvar,
+    /// 任何一端的状态改变时唤醒 poll 的线程
+    poll_queue: PollQueue,
+}
+
+/// 管道的读端
+pub struct PipeReader(Arc<Pipe>);
+
+/// 管道的写端
+pub struct PipeWriter(Arc<Pipe>);
+
+/// 创建一个管道，返回其读端和写端
+pub fn pipe() -> (Arc<PipeReader>, Arc<PipeWriter>) {
+    let pipe = Arc::new(Pipe {
+        buffer: Lock::new(PipeBuffer {
+            data: VecDeque::with_capacity(PIPE_CAPACITY),
+            readers: 1,
+            writers: 1,
+        }),
+        readable: Condvar::default(),
+        writable: Condvar::default(),
+        poll_queue: PollQueue::default(),
+    });
+    (
+        Arc::new(PipeReader(pipe.clone())),
+        Arc::new(PipeWriter(pipe)),
+    )
+}
+
+impl INode for PipeReader {
+    /// 读取数据，缓冲区为空时休眠等待；所有写端都已关闭时返回 0
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        let pipe = &self.0;
+        let mut buffer = pipe.buffer.get();
+        while buffer.data.is_empty() {
+            if buffer.writers == 0 {
+                return Ok(0);
+            }
+            buffer = pipe.readable.wait(buffer);
+        }
+        let length = buf.len().min(buffer.data.len());
+        for (byte, b) in buf.iter_mut().zip(buffer.data.drain(..length)) {
+            *byte = b;
+        }
+        pipe.writable.notify_all();
+        pipe.poll_queue.notify();
+        Ok(length)
+    }
+
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let buffer = self.0.buffer.get();
+        Ok(PollStatus {
+            read: !buffer.data.is_empty() || buffer.writers == 0,
+            write: false,
+            error: false,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl INode for PipeWriter {
+    /// 写入全部数据，缓冲区已满时休眠等待
+    ///
+    /// 所有读端都已关闭时返回 [`FsError::Busy`]（见 [`PipeWriter::is_broken`]），
+    /// 如果已经写入了一部分，则返回写入的长度
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        let pipe = &self.0;
+        let mut buffer = pipe.buffer.get();
+        let mut written = 0;
+        while written < buf.len() {
+            if buffer.readers == 0 {
+                return if written == 0 {
+                    Err(FsError::Busy)
+                } else {
+                    Ok(written)
+                };
+            }
+            if buffer.data.len() == PIPE_CAPACITY {
+                buffer = pipe.writable.wait(buffer);
+                continue;
+            }
+            let length = (buf.len() - written).min(PIPE_CAPACITY - buffer.data.len());
+            buffer
+                .data
+                .extend(buf[written..written + length].iter().copied());
+            written += length;
+            pipe.readable.notify_all();
+            pipe.poll_queue.notify();
+        }
+        Ok(written)
+    }
+
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let buffer = self.0.buffer.get();
+        Ok(PollStatus {
+            read: false,
+            write: buffer.data.len() < PIPE_CAPACITY || buffer.readers == 0,
+            error: buffer.readers == 0,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Pollable for PipeReader {
+    /// 注册到管道状态改变时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.0.poll_queue.register(poller);
+    }
+}
+
+impl Pollable for PipeWriter {
+    /// 注册到管道状态改变时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.0.poll_queue.register(poller);
+    }
+}
+
+impl PipeWriter {
+    /// 所有读端是否都已关闭，此时写入的进程应当被终止（SIGPIPE）
+    pub fn is_broken(&self) -> bool {
+        self.0.buffer.get().readers == 0
+    }
+}
+
+/// 关闭读端，唤醒等待空间的写者让它们发现管道已经断开
+impl Drop for PipeReader {
+    fn drop(&mut self) {
+        self.0.buffer.get().readers -= 1;
+        self.0.writable.notify_all();
+        self.0.poll_queue.notify();
+    }
+}
+
+/// 关闭写端，唤醒等待数据的读者让它们读到末尾
+impl Drop for PipeWriter {
+    fn drop(&mut self) {
+        self.0.buffer.get().writers -= 1;
+        self.0.readable.notify_all();
+        self.0.poll_queue.notify();
+    }
+}
@@ -0,0 +1,83 @@
+//! 同时等待多个对象的 [`Poller`] 和对象的等待队列 [`PollQueue`]
+//!
+//! 线程在 poll 时创建一个 [`Poller`]，注册到所有关心的对象的 [`PollQueue`] 上，再逐个检查状态。
+//! 对象状态改变时唤醒注册在上面的所有 [`Poller`]，线程醒来后重新注册、重新检查。
+//! 先注册再检查，因此检查之后发生的改变不会被错过
+
+use super::*;
+use crate::process::Lock;
+use alloc::sync::Weak;
+
+/// 一次 poll 操作，可以注册到多个 [`PollQueue`] 上
+#[derive(Default)]
+pub struct Poller {
+    /// 注册之后是否有对象的状态改变过
+    woken: Lock<bool>,
+    condvar: Condvar,
+}
+
+impl Poller {
+    /// 创建一个 [`Poller`]
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// 休眠直到注册的对象状态改变，最多等待 `timeout` 微秒（为 `None` 时一直等待）
+    ///
+    /// 返回是否因为状态改变而醒来
+    pub fn wait(&self, timeout: Option<usize>) -> bool {
+        let mut woken = self.woken.get();
+        if !*woken {
+            woken = match timeout {
+                Some(timeout) => self.condvar.wait_timeout(woken, timeout).0,
+                None => self.condvar.wait(woken),
+            };
+        }
+        core::mem::replace(&mut *woken, false)
+    }
+
+    /// 注册的对象状态改变
+    fn wake(&self) {
+        *self.woken.get() = true;
+        self.condvar.notify_all();
+    }
+}
+
+/// 状态会改变、可以被 `poll` 和 `select` 等待的文件
+///
+/// [`INode`] 来自 rcore-fs，不能为它增加方法，文件描述符表中的文件通过 [`INodeExt::as_pollable`] 转换。
+/// 磁盘上的文件总是可以读写，不需要实现
+///
+/// [`INodeExt::as_pollable`]: super::INodeExt::as_pollable
+pub trait Pollable {
+    /// 将 `poller` 注册到状态改变时会唤醒它的队列上
+    fn register(&self, poller: &Arc<Poller>);
+}
+
+/// 对象的等待队列，状态改变时唤醒注册在上面的 [`Poller`]
+#[derive(Default)]
+pub struct PollQueue {
+    /// 注册的 [`Poller`]，唤醒后即移除，需要继续等待的线程会重新注册
+    pollers: Lock<Vec<Weak<Poller>>>,
+}
+
+impl PollQueue {
+    /// 注册一个 [`Poller`]，重复注册只记录一次
+    pub fn register(&self, poller: &Arc<Poller>) {
+        let mut pollers = self.pollers.get();
+        // 顺便清理已经结束的 poll
+        pollers.retain(|registered| registered.strong_count() > 0);
+        let poller = Arc::downgrade(poller);
+        if !pollers.iter().any(|registered| registered.ptr_eq(&poller)) {
+            pollers.push(poller);
+        }
+    }
+
+    /// 对象的状态改变，唤醒所有注册的 [`Poller`]
+    pub fn notify(&self) {
+        let pollers = core::mem::take(&mut *self.pollers.get());
+        for poller in pollers.iter().filter_map(Weak::upgrade) {
+            poller.wake();
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! 本地（Unix 域）套接字 [`UnixSocket`]
+//!
+//! 套接字绑定到路径上，路径由内核中的名字空间 [`static@NAMESPACE`] 记录。根文件系统（SFS）不支持套接字类型的文件，
+//! 因此绑定不会在磁盘上创建文件，也不能在文件系统中找到，名字空间与文件系统中的路径互不冲突。
+//!
+//! 与 Linux 相同，套接字关闭后路径仍然被占用，直到用 [`unlink`] 删除；此时连接到该路径会失败。
+//! 名字空间只持有监听队列和接收队列的弱引用，它们随套接字一起释放
+//!
+//! - 流式套接字：[`UnixSocket::listen`] 之后，其他套接字 [`UnixSocket::connect`] 时创建一对管道
+//!   作为连接的两个方向，服务端的一侧放入等待队列，由 [`UnixSocket::accept`] 取出
+//! - 数据报套接字：绑定时创建一个 [`MessageQueue`] 作为接收队列，
+//!   [`UnixSocket::connect`] 只是记下对端的接收队列，之后 [`UnixSocket::send`] 发往那里
+
+use super::*;
+use crate::process::Lock;
+use alloc::{collections::BTreeMap, collections::VecDeque, string::String, sync::Weak, vec};
+
+/// 套接字的类型
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SocketType {
+    /// 面向连接的字节流
+    Stream,
+    /// 保留边界的数据报
+    Datagram,
+}
+
+/// 绑定在路径上的对象，套接字关闭后失效
+enum Bound {
+    /// 流式套接字的监听队列
+    Stream(Weak<Listener>),
+    /// 数据报套接字的接收队列
+    Datagram(Weak<MessageQueue>),
+}
+
+lazy_static! {
+    /// 所有绑定的路径
+    static ref NAMESPACE: Mutex<BTreeMap<String, Bound>> = Mutex::new(BTreeMap::new());
+}
+
+/// 流式套接字的监听队列
+#[derive(Default)]
+struct Listener {
+    inner: Lock<ListenerInner>,
+    /// 等待连接的 [`UnixSocket::accept`] 在此休眠
+    condvar: Condvar,
+    /// 有新的连接时唤醒 poll 的线程
+    poll_queue: PollQueue,
+}
+
+#[derive(Default)]
+struct ListenerInner {
+    /// 调用 [`UnixSocket::listen`] 之后才接受连接
+    listening: bool,
+    /// 最多排队的连接数量
+    backlog: usize,
+    /// 已经建立、等待被取出的连接（服务端的一侧）
+    pending: VecDeque<Arc<UnixSocket>>,
+}
+
+/// 本地套接字
+pub struct UnixSocket {
+    pub socket_type: SocketType,
+    inner: Lock<SocketInner>,
+}
+
+/// 套接字中需要加锁的部分
+#[derive(Default)]
+struct SocketInner {
+    /// 绑定的路径
+    path: Option<String>,
+    /// 流式：绑定后的监听队列
+    listener: Option<Arc<Listener>>,
+    /// 流式：连接之后读写的两个方向
+    connection: Option<(Arc<PipeReader>, Arc<PipeWriter>)>,
+    /// 数据报：绑定后的接收队列
+    inbox: Option<Arc<MessageQueue>>,
+    /// 数据报：连接的对端的接收队列，对端关闭后失效
+    peer: Option<Weak<MessageQueue>>,
+}
+
+impl UnixSocket {
+    /// 创建一个未绑定的套接字
+    pub fn new(socket_type: SocketType) -> Self {
+        Self {
+            socket_type,
+            inner: Lock::new(SocketInner::default()),
+        }
+    }
+
+    /// 将套接字绑定到 `path`，路径已经被占用时返回 [`FsError::EntryExist`]
+    ///
+    /// 绑定的套接字关闭之后，路径在 [`unlink`] 之前仍然被占用
+    pub fn bind(&self, path: &str) -> Result<()> {
+        let mut inner = self.inner.get();
+        if inner.path.is_some() || inner.connection.is_some() {
+            return Err(FsError::InvalidParam);
+        }
+        let mut namespace = NAMESPACE.lock();
+        if namespace.contains_key(path) {
+            return Err(FsError::EntryExist);
+        }
+        let bound = match self.socket_type {
+            SocketType::Stream => {
+                let listener = Arc::new(Listener::default());
+                let bound = Bound::Stream(Arc::downgrade(&listener));
+                inner.listener = Some(listener);
+                bound
+            }
+            SocketType::Datagram => {
+                let inbox = Arc::new(MessageQueue::new(DATAGRAM_CAPACITY, DATAGRAM_MAX_SIZE));
+                let bound = Bound::Datagram(Arc::downgrade(&inbox));
+                inner.inbox = Some(inbox);
+                bound
+            }
+        };
+        namespace.insert(String::from(path), bound);
+        inner.path = Some(String::from(path));
+        Ok(())
+    }
+
+    /// 开始接受连接，最多排队 `backlog` 个，只适用于已经绑定的流式套接字
+    pub fn listen(&self, backlog: usize) -> Result<()> {
+        let listener = self.inner.get().listener.clone();
+        let listener = listener.ok_or(FsError::InvalidParam)?;
+        let mut inner = listener.inner.get();
+        inner.listening = true;
+        inner.backlog = backlog.max(1);
+        Ok(())
+    }
+
+    /// 取出一个已经建立的连接，没有时休眠等待
+    pub fn accept(&self) -> Result<Arc<UnixSocket>> {
+        let listener = self.inner.get().listener.clone();
+        let listener = listener.ok_or(FsError::InvalidParam)?;
+        let mut inner = listener.inner.get();
+        if !inner.listening {
+            return Err(FsError::InvalidParam);
+        }
+        loop {
+            if let Some(socket) = inner.pending.pop_front() {
+                return Ok(socket);
+            }
+            inner = listener.condvar.wait(inner);
+        }
+    }
+
+    /// 连接到绑定在 `path` 上的套接字
+    ///
+    /// 流式套接字立即建立连接并放入对方的等待队列，队列已满时返回 [`FsError::Busy`]；
+    /// 数据报套接字只记下对端，之后的 [`UnixSocket::send`] 都发往那里。
+    /// 路径没有绑定或者绑定的套接字已经关闭时返回 [`FsError::EntryNotFound`]
+    pub fn connect(&self, path: &str) -> Result<()> {
+        let bound = match NAMESPACE.lock().get(path) {
+            Some(Bound::Stream(listener)) => Bound::Stream(listener.clone()),
+            Some(Bound::Datagram(inbox)) => Bound::Datagram(inbox.clone()),
+            None => return Err(FsError::EntryNotFound),
+        };
+        let mut inner = self.inner.get();
+        match (self.socket_type, bound) {
+            (SocketType::Stream, Bound::Stream(listener)) => {
+                let listener = listener.upgrade().ok_or(FsError::EntryNotFound)?;
+                if inner.listener.is_some() || inner.connection.is_some() {
+                    return Err(FsError::InvalidParam);
+                }
+                let (client_reader, server_writer) = pipe();
+                let (server_reader, client_writer) = pipe();
+                let server = UnixSocket::new(SocketType::Stream);
+                server.inner.get().connection = Some((server_reader, server_writer));
+                let mut listener_inner = listener.inner.get();
+                if !listener_inner.listening {
+                    return Err(FsError::EntryNotFound);
+                }
+                if listener_inner.pending.len() >= listener_inner.backlog {
+                    return Err(FsError::Busy);
+                }
+                listener_inner.pending.push_back(Arc::new(server));
+                listener.condvar.notify_one();
+                listener.poll_queue.notify();
+                inner.connection = Some((client_reader, client_writer));
+                Ok(())
+            }
+            (SocketType::Datagram, Bound::Datagram(inbox)) => {
+                if inbox.strong_count() == 0 {
+                    return Err(FsError::EntryNotFound);
+                }
+                inner.peer = Some(inbox);
+                Ok(())
+            }
+            _ => Err(FsError::InvalidParam),
+        }
+    }
+
+    /// 发送数据，返回发送的长度
+    ///
+    /// 流式套接字在缓冲区满时休眠，对方关闭后返回 [`FsError::Busy`]；
+    /// 数据报套接字发往连接的对端，对端接收队列满时休眠，对端已经关闭时返回 [`FsError::EntryNotFound`]
+    pub fn send(&self, data: &[u8]) -> Result<usize> {
+        // 发送时可能休眠，不能持有套接字的锁
+        let (writer, peer) = {
+            let inner = self.inner.get();
+            (
+                inner.connection.as_ref().map(|(_, writer)| writer.clone()),
+                inner.peer.clone(),
+            )
+        };
+        match self.socket_type {
+            SocketType::Stream => writer.ok_or(FsError::InvalidParam)?.write_at(0, data),
+            SocketType::Datagram => {
+                let peer = peer.ok_or(FsError::InvalidParam)?;
+                let peer = peer.upgrade().ok_or(FsError::EntryNotFound)?;
+                peer.send(data, 0, MessageWait::Forever)?;
+                Ok(data.len())
+            }
+        }
+    }
+
+    /// 接收数据，没有数据时休眠等待，返回接收的长度
+    ///
+    /// 流式套接字在对方关闭后返回 0；数据报套接字每次接收一个数据报，超出 `buffer` 的部分被丢弃
+    pub fn recv(&self, buffer: &mut [u8]) -> Result<usize> {
+        let (reader, inbox) = {
+            let inner = self.inner.get();
+            (
+                inner.connection.as_ref().map(|(reader, _)| reader.clone()),
+                inner.inbox.clone(),
+            )
+        };
+        match self.socket_type {
+            SocketType::Stream => reader.ok_or(FsError::InvalidParam)?.read_at(0, buffer),
+            SocketType::Datagram => {
+                let inbox = inbox.ok_or(FsError::InvalidParam)?;
+                let mut datagram = vec![0u8; inbox.max_size];
+                let (length, _) = inbox.receive(&mut datagram, MessageWait::Forever)?;
+                let length = length.min(buffer.len());
+                buffer[..length].copy_from_slice(&datagram[..length]);
+                Ok(length)
+            }
+        }
+    }
+}
+
+impl Pollable for UnixSocket {
+    /// 注册到所有可能改变套接字状态的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        let inner = self.inner.get();
+        if let Some((reader, writer)) = inner.connection.as_ref() {
+            reader.register(poller);
+            writer.register(poller);
+        }
+        if let Some(listener) = inner.listener.as_ref() {
+            listener.poll_queue.register(poller);
+        }
+        if let Some(inbox) = inner.inbox.as_ref() {
+            inbox.register(poller);
+        }
+        if let Some(peer) = inner.peer.as_ref().and_then(Weak::upgrade) {
+            peer.register(poller);
+        }
+    }
+}
+
+impl INode for UnixSocket {
+    /// 见 [`UnixSocket::recv`]
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        self.recv(buf)
+    }
+
+    /// 见 [`UnixSocket::send`]
+    fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        self.send(buf)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        let inner = self.inner.get();
+        if let Some((reader, writer)) = inner.connection.as_ref() {
+            let read = reader.poll()?;
+            let write = writer.poll()?;
+            return Ok(PollStatus {
+                read: read.read,
+                write: write.write,
+                error: write.error,
+            });
+        }
+        if let Some(listener) = inner.listener.as_ref() {
+            // 有等待取出的连接时可读
+            return Ok(PollStatus {
+                read: !listener.inner.get().pending.is_empty(),
+                write: false,
+                error: false,
+            });
+        }
+        // 对端已经关闭时报告错误
+        let peer = inner.peer.as_ref().map(Weak::upgrade);
+        Ok(PollStatus {
+            read: inner.inbox.as_ref().map_or(false, |inbox| inbox.status().0),
+            write: peer
+                .as_ref()
+                .and_then(Option::as_ref)
+                .map_or(false, |peer| peer.status().1),
+            error: peer.map_or(false, |peer| peer.is_none()),
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// 删除绑定在 `path` 上的路径，之后可以再次绑定
+///
+/// 已经建立的连接不受影响。路径没有绑定时返回 [`FsError::EntryNotFound`]
+pub fn unlink(path: &str) -> Result<()> {
+    match NAMESPACE.lock().remove(path) {
+        Some(_) => Ok(()),
+        None => Err(FsError::EntryNotFound),
+    }
+}
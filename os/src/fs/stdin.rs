@@ -1,6 +1,7 @@
 //! 键盘输入 [`Stdin`]
 
 use super::*;
+use crate::process::Lock;
 use alloc::collections::VecDeque;
 
 lazy_static! {
@@ -11,9 +12,11 @@ lazy_static! {
 #[derive(Default)]
 pub struct Stdin {
     /// 从后插入，前段弹出
-    buffer: Mutex<VecDeque<u8>>,
+    buffer: Lock<VecDeque<u8>>,
     /// 条件变量用于使等待输入的线程休眠
     condvar: Condvar,
+    /// 有输入时唤醒 poll 的线程
+    poll_queue: PollQueue,
 }
 
 impl INode for Stdin {
@@ -21,22 +24,21 @@ impl INode for Stdin {
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
         if offset != 0 {
             // 不支持 offset
-            Err(FsError::NotSupported)
-        } else if self.buffer.lock().len() == 0 {
-            // 缓冲区没有数据，将当前线程休眠
-            self.condvar.wait();
-            Ok(0)
-        } else {
-            let mut stdin_buffer = self.buffer.lock();
-            for (i, byte) in buf.iter_mut().enumerate() {
-                if let Some(b) = stdin_buffer.pop_front() {
-                    *byte = b;
-                } else {
-                    return Ok(i);
-                }
+            return Err(FsError::NotSupported);
+        }
+        let mut stdin_buffer = self.buffer.get();
+        while stdin_buffer.is_empty() {
+            // 缓冲区没有数据，将当前线程休眠，被唤醒后重新检查
+            stdin_buffer = self.condvar.wait(stdin_buffer);
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            if let Some(b) = stdin_buffer.pop_front() {
+                *byte = b;
+            } else {
+                return Ok(i);
             }
-            Ok(buf.len())
         }
+        Ok(buf.len())
     }
 
     /// Write bytes at `offset` from `buf`, return the number of bytes written.
@@ -45,7 +47,11 @@ impl INode for Stdin {
     }
 
     fn poll(&self) -> Result<PollStatus> {
-        Err(FsError::NotSupported)
+        Ok(PollStatus {
+            read: !self.buffer.get().is_empty(),
+            write: false,
+            error: false,
+        })
     }
 
     /// This is used to implement dynamics cast.
@@ -58,7 +64,15 @@ impl INode for Stdin {
 impl Stdin {
     /// 向缓冲区插入一个字符，然后唤起一个线程
     pub fn push(&self, c: u8) {
-        self.buffer.lock().push_back(c);
+        self.buffer.get().push_back(c);
         self.condvar.notify_one();
+        self.poll_queue.notify();
+    }
+}
+
+impl Pollable for Stdin {
+    /// 注册到有输入时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.poll_queue.register(poller);
     }
 }
@@ -27,8 +27,13 @@ impl INode for Stdout {
         Err(FsError::NotSupported)
     }
 
+    /// 控制台总是可以写入
     fn poll(&self) -> Result<PollStatus> {
-        Err(FsError::NotSupported)
+        Ok(PollStatus {
+            read: false,
+            write: true,
+            error: false,
+        })
     }
 
     /// This is used to implement dynamics cast.
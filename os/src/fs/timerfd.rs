@@ -0,0 +1,158 @@
+//! 定时器文件 [`TimerFd`]
+//!
+//! 启动后通过 [`add_timer`] 放入定时器队列，在时钟中断中到期时累计到期次数，并唤醒读取或 poll 的线程。
+//! 读取时得到自上次读取以来的到期次数（8 字节），还没有到期时休眠等待
+
+use super::*;
+use crate::interrupt::{add_timer, cancel_timer, ticks_to_us, us_to_ticks, TimerID};
+use crate::process::Lock;
+use alloc::sync::Weak;
+use riscv::register::time;
+
+/// 定时器文件
+///
+/// 定时器队列中的事件只持有 [`Timer`] 的弱引用，文件关闭之后不再生效
+#[derive(Default)]
+pub struct TimerFd(Arc<Timer>);
+
+/// 定时器的状态
+#[derive(Default)]
+struct Timer {
+    inner: Lock<TimerInner>,
+    /// 等待到期的读者在此休眠
+    condvar: Condvar,
+    /// 到期时唤醒 poll 的线程
+    poll_queue: PollQueue,
+}
+
+/// 定时器文件中需要加锁的部分，时间的单位为 `time` 寄存器的计数
+#[derive(Default)]
+struct TimerInner {
+    /// 上次读取以来到期的次数
+    expirations: usize,
+    /// 下一次到期的时间，为 `None` 表示没有启动
+    deadline: Option<usize>,
+    /// 到期后重新开始计时的间隔，为 0 表示只触发一次
+    interval: usize,
+    /// 定时器队列中的事件
+    timer: Option<TimerID>,
+}
+
+impl TimerFd {
+    /// 创建一个没有启动的定时器文件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在 `value` 微秒后到期，之后每隔 `interval` 微秒到期一次（为 0 时只触发一次）
+    ///
+    /// `value` 为 0 时停止计时。返回之前距离到期的时间和间隔（微秒）
+    pub fn set(&self, value: usize, interval: usize) -> (usize, usize) {
+        let mut inner = self.0.inner.get();
+        let now = time::read();
+        let old = (
+            inner
+                .deadline
+                .map_or(0, |deadline| ticks_to_us(deadline.saturating_sub(now))),
+            ticks_to_us(inner.interval),
+        );
+        if let Some(timer) = inner.timer.take() {
+            cancel_timer(timer);
+        }
+        inner.deadline = None;
+        inner.interval = us_to_ticks(interval);
+        if value > 0 {
+            self.0.arm(&mut inner, now + us_to_ticks(value));
+        }
+        old
+    }
+}
+
+impl Pollable for TimerFd {
+    /// 注册到到期时唤醒的队列
+    fn register(&self, poller: &Arc<Poller>) {
+        self.0.poll_queue.register(poller);
+    }
+}
+
+impl Timer {
+    /// 在 `deadline` 时到期
+    fn arm(self: &Arc<Self>, inner: &mut TimerInner, deadline: usize) {
+        let timer = Arc::downgrade(self);
+        inner.deadline = Some(deadline);
+        inner.timer = Some(add_timer(deadline, move || {
+            if let Some(timer) = Weak::upgrade(&timer) {
+                timer.fire(deadline);
+            }
+        }));
+    }
+
+    /// 在时钟中断中到期，周期性的定时器再次放入队列
+    fn fire(self: &Arc<Self>, deadline: usize) {
+        let mut inner = self.inner.get();
+        // 已经被重新设置
+        if inner.deadline != Some(deadline) {
+            return;
+        }
+        inner.expirations += 1;
+        inner.timer = None;
+        inner.deadline = None;
+        if inner.interval > 0 {
+            let interval = inner.interval;
+            self.arm(&mut inner, deadline + interval);
+        }
+        self.condvar.notify_all();
+        self.poll_queue.notify();
+    }
+}
+
+impl INode for TimerFd {
+    /// 读取到期次数（8 字节）并清零，还没有到期时休眠等待
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        if offset != 0 {
+            // 不支持 offset
+            return Err(FsError::NotSupported);
+        }
+        if buf.len() < 8 {
+            return Err(FsError::InvalidParam);
+        }
+        let timer = &self.0;
+        let mut inner = timer.inner.get();
+        loop {
+            if inner.expirations > 0 {
+                buf[..8].copy_from_slice(&(inner.expirations as u64).to_le_bytes());
+                inner.expirations = 0;
+                return Ok(8);
+            }
+            inner = timer.condvar.wait(inner);
+        }
+    }
+
+    /// 不支持写入
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn poll(&self) -> Result<PollStatus> {
+        Ok(PollStatus {
+            read: self.0.inner.get().expirations > 0,
+            write: false,
+            error: false,
+        })
+    }
+
+    /// This is used to implement dynamics cast.
+    /// Simply return self in the implement of the function.
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// 关闭时从定时器队列中移除
+impl Drop for TimerFd {
+    fn drop(&mut self) {
+        if let Some(timer) = self.0.inner.get().timer.take() {
+            cancel_timer(timer);
+        }
+    }
+}
@@ -1,22 +1,41 @@
 use super::context::Context;
 use super::timer;
 use crate::fs::STDIN;
-use crate::kernel::syscall_handler;
+use crate::kernel::{charge_cpu_time, debug_console, syscall_handler};
 use crate::memory::*;
 use crate::process::PROCESSOR;
-use crate::sbi::console_getchar;
+use crate::sbi::{clear_ipi, console_getchar};
 use alloc::{format, string::String};
 use riscv::register::{
     scause::{Exception, Interrupt, Scause, Trap},
-    sie, stvec,
+    sie,
+    sstatus::SPP,
+    stvec,
 };
 
 global_asm!(include_str!("./interrupt.asm"));
 
 /// 初始化中断处理
 ///
-/// 把中断入口 `__interrupt` 写入 `stvec` 中，并且开启中断使能
+/// 设置当前 hart 的中断处理，并在 PLIC 中开启外部中断（只发往负责初始化的 hart）
 pub fn init() {
+    init_hart();
+    unsafe {
+        // 在 OpenSBI 中开启外部中断
+        *PhysicalAddress(0x0c00_2080).deref_kernel() = 1u32 << 10;
+        // 在 OpenSBI 中开启串口
+        *PhysicalAddress(0x1000_0004).deref_kernel() = 0x0bu8;
+        *PhysicalAddress(0x1000_0001).deref_kernel() = 0x01u8;
+        // 其他一些外部中断相关魔数
+        *PhysicalAddress(0x0C00_0028).deref_kernel() = 0x07u32;
+        *PhysicalAddress(0x0C20_1000).deref_kernel() = 0u32;
+    }
+}
+
+/// 设置当前 hart 的中断处理
+///
+/// 把中断入口 `__interrupt` 写入 `stvec` 中，并且开启外部中断和核间中断使能
+pub fn init_hart() {
     unsafe {
         extern "C" {
             /// `interrupt.asm` 中的中断入口
@@ -27,15 +46,8 @@ pub fn init() {
 
         // 开启外部中断使能
         sie::set_sext();
-
-        // 在 OpenSBI 中开启外部中断
-        *PhysicalAddress(0x0c00_2080).deref_kernel() = 1u32 << 10;
-        // 在 OpenSBI 中开启串口
-        *PhysicalAddress(0x1000_0004).deref_kernel() = 0x0bu8;
-        *PhysicalAddress(0x1000_0001).deref_kernel() = 0x01u8;
-        // 其他一些外部中断相关魔数
-        *PhysicalAddress(0x0C00_0028).deref_kernel() = 0x07u32;
-        *PhysicalAddress(0x0C20_1000).deref_kernel() = 0u32;
+        // 开启核间中断使能
+        sie::set_ssoft();
     }
 }
 
@@ -45,18 +57,15 @@ pub fn init() {
 /// 具体的中断类型需要根据 scause 来推断，然后分别处理
 #[no_mangle]
 pub fn handle_interrupt(context: &mut Context, scause: Scause, stval: usize) -> *mut Context {
-    // 首先检查线程是否已经结束（内核线程会自己设置标记来结束自己）
-    /*{
-        let mut processor = PROCESSOR.get();
-        let current_thread = processor.current_thread();
-        if current_thread.as_ref().inner().dead {
-            println!("thread {} exit", current_thread.id);
-            processor.kill_current_thread();
-            return processor.prepare_next_thread();
-        }
-    }*/
+    // 从用户态进入中断，此前的时间计为线程在用户态执行的时间
+    let from_user = context.sstatus.spp() == SPP::User;
+    let user_time = if from_user {
+        PROCESSOR.get().account_current_thread(true)
+    } else {
+        0
+    };
     // 根据中断类型来处理，返回的 Context 必须位于放在内核栈顶
-    match scause.cause() {
+    let context = match scause.cause() {
         // 断点中断（ebreak）
         Trap::Exception(Exception::Breakpoint) => breakpoint(context),
         // 系统调用
@@ -65,29 +74,55 @@ pub fn handle_interrupt(context: &mut Context, scause: Scause, stval: usize) ->
         Trap::Interrupt(Interrupt::SupervisorTimer) => supervisor_timer(context),
         // 外部中断（键盘输入）
         Trap::Interrupt(Interrupt::SupervisorExternal) => supervisor_external(context),
+        // 核间中断
+        Trap::Interrupt(Interrupt::SupervisorSoft) => supervisor_soft(context),
         // 其他情况，无法处理
         _ => Err(format!(
             "unimplemented interrupt type: {:x?}",
             scause.cause()
         )),
     }
-    .unwrap_or_else(|msg| fault(msg, scause, stval))
+    .unwrap_or_else(|msg| fault(msg, scause, stval));
+    // 返回用户态之前，中断处理的时间计为内核态执行的时间，与用户态的时间一起计入进程的定时器。
+    // 中断处理中即使切换过线程，返回时仍然在同一个线程的内核栈上，两段时间属于同一个进程
+    if from_user {
+        let (system_time, process) = {
+            let mut processor = PROCESSOR.get();
+            let elapsed = processor.account_current_thread(false);
+            (elapsed, processor.current_process())
+        };
+        if let Some(process) = process {
+            charge_cpu_time(&process, user_time, system_time);
+        }
+    }
+    context
 }
 
 /// 处理 ebreak 断点
 ///
-/// 继续执行，其中 `sepc` 增加 2 字节，以跳过当前这条 `ebreak` 指令
+/// 内核线程结束时会设置标记并执行 `ebreak`（见 `process::kthread`），此时结束线程，不再返回。
+/// 否则继续执行，其中 `sepc` 增加 2 字节，以跳过当前这条 `ebreak` 指令
 fn breakpoint(context: &mut Context) -> Result<*mut Context, String> {
+    let current_thread = PROCESSOR.get().current_thread();
+    if current_thread.inner().dead {
+        println!("thread {} exit", current_thread.id);
+        drop(current_thread);
+        PROCESSOR.exit_current_thread();
+    }
     println!("Breakpoint at 0x{:x}", context.sepc);
     context.sepc += 2;
     Ok(context)
 }
 
 /// 处理时钟中断
+///
+/// 线程的时间片用完，切换到下一个线程；hart 空闲时则只是唤醒调度循环
 fn supervisor_timer(context: &mut Context) -> Result<*mut Context, String> {
     timer::tick();
-    PROCESSOR.get().park_current_thread(context);
-    Ok(PROCESSOR.get().prepare_next_thread())
+    if PROCESSOR.get().preempt_current_thread() {
+        PROCESSOR.schedule();
+    }
+    Ok(context)
 }
 
 /// 处理外部中断，只实现了键盘输入
@@ -97,11 +132,22 @@ fn supervisor_external(context: &mut Context) -> Result<*mut Context, String> {
         if c == '\r' as usize {
             c = '\n' as usize;
         }
-        STDIN.push(c as u8);
+        // 调试控制台会拦截它所使用的按键
+        if !debug_console(c as u8) {
+            STDIN.push(c as u8);
+        }
     }
     Ok(context)
 }
 
+/// 处理核间中断
+///
+/// 其他 hart 用它通知有线程被唤醒或者加入，空闲的 hart 会从 `wait_for_interrupt` 返回并重新调度
+fn supervisor_soft(context: &mut Context) -> Result<*mut Context, String> {
+    clear_ipi();
+    Ok(context)
+}
+
 /// 出现未能解决的异常，终止当前线程
 fn fault(msg: String, scause: Scause, stval: usize) -> *mut Context {
     println!(
@@ -111,7 +157,6 @@ fn fault(msg: String, scause: Scause, stval: usize) -> *mut Context {
     );
     println!("cause: {:?}, stval: {:x}", scause.cause(), stval);
 
-    PROCESSOR.get().kill_current_thread();
-    // 跳转到 PROCESSOR 调度的下一个线程
-    PROCESSOR.get().prepare_next_thread()
+    // 结束线程，不再返回
+    PROCESSOR.exit_current_thread()
 }
@@ -56,6 +56,13 @@ __interrupt:
     csrr    t1, sepc
     SAVE    t0, 32
     SAVE    t1, 33
+
+    # 从用户态进入中断时，tp 是用户程序的值，需要从内核栈顶之上取回 hart 编号
+    # 内核态（SPP 为 1）时 tp 本来就是 hart 编号
+    andi    t0, t0, 1 << 8
+    bnez    t0, 1f
+    ld      tp, CONTEXT_SIZE * REG_SIZE(sp)
+1:
     # 调用 handle_interrupt，传入参数
     # context: &mut Context
     mv      a0, sp
@@ -79,6 +86,12 @@ __restore:
     LOAD    t1, 33
     csrw    sstatus, t0
     csrw    sepc, t1
+    # 回到内核态（SPP 为 1）时，tp 保持为当前 hart 的编号，
+    # 因为内核线程可能已经换到了其他 hart 上
+    andi    t1, t0, 1 << 8
+    beqz    t1, 1f
+    SAVE    tp, 4
+1:
     # 将内核栈地址写入 sscratch
     addi    t0, sp, CONTEXT_SIZE * REG_SIZE
     csrw    sscratch, t0
@@ -6,9 +6,13 @@ mod context;
 mod handler;
 mod timer;
 
-use riscv::register::{sie, sstatus};
+use riscv::register::sstatus;
 
 pub use context::Context;
+pub use timer::{
+    add_timer, cancel_timeout, cancel_timer, clock_frequency, set_clock_frequency, set_timeout,
+    ticks_to_us, us_to_ticks, TimerID,
+};
 
 /// 初始化中断相关的子模块
 ///
@@ -20,17 +24,32 @@ pub fn init() {
     println!("mod interrupt initialized");
 }
 
-/// 等待一个外部中断
+/// 其他 hart 启动时的初始化
 ///
-/// 暂时开启中断（不包括时钟中断）并执行 `wfi` 指令
+/// - [`handler::init_hart`]
+/// - [`timer::init`]
+pub fn init_hart() {
+    handler::init_hart();
+    timer::init();
+}
+
+/// 等待一个中断
+///
+/// 在 `time` 寄存器到达 `timeout` 时预约时钟中断，为 `None` 则只等待外部中断或核间中断。
+/// 然后暂时开启中断并执行 `wfi` 指令
 ///
-/// 会在所有线程都在等待外部信号时调用
-pub fn wait_for_interrupt() {
+/// 会在当前 hart 没有可以执行的线程时调用
+pub fn wait_for_interrupt(timeout: Option<usize>) {
+    match timeout {
+        Some(timeout) => set_timeout(timeout),
+        None => cancel_timeout(),
+    }
     unsafe {
-        sie::clear_stimer();
+        // 等待期间发生的中断直接在当前栈上处理：
+        // 调度循环运行在 hart 的启动栈上，sscratch 中是上一个线程的内核栈
+        llvm_asm!("csrw sscratch, sp" :::: "volatile");
         sstatus::set_sie();
         llvm_asm!("wfi" :::: "volatile");
         sstatus::clear_sie();
-        sie::set_stimer();
     }
 }
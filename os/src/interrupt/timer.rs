@@ -1,37 +1,142 @@
 //! 预约和处理时钟中断
+//!
+//! 时钟中断不是周期性的：每次切换线程时，按照线程的时间片预约下一次中断（见 [`set_timeout`]）；
+//! hart 空闲时只为最近需要醒来的线程预约，没有则不预约（tickless）
+//!
+//! 内核中其他需要定时执行的事件（间隔定时器、timerfd 等）通过 [`add_timer`] 放入按时间排序的定时器队列，
+//! 每次预约时钟中断时都不会晚于队列中最早的事件，事件在时钟中断中执行。
+//! 加入的事件比当前 hart 预约的时钟中断更早时，立即重新预约
 
+use crate::process::Lock;
 use crate::sbi::set_timer;
+use crate::smp::{hart_id, MAX_HARTS};
+use alloc::{boxed::Box, collections::BTreeMap};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
 use riscv::register::{sie, sstatus, time};
 
-/// 时钟中断的间隔，单位是 CPU 指令
-static INTERVAL: usize = 10000;
+/// `time` 寄存器的频率，启动时从设备树的 `timebase-frequency` 读取，默认为 QEMU virt 平台的 10MHz
+static CLOCK_FREQUENCY: AtomicUsize = AtomicUsize::new(10_000_000);
 
-/// 设置下一次时钟中断
+/// `time` 寄存器的频率
+pub fn clock_frequency() -> usize {
+    CLOCK_FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// 设置 `time` 寄存器的频率，由设备树给出
+pub fn set_clock_frequency(frequency: usize) {
+    CLOCK_FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+/// 将微秒转换为 `time` 寄存器的计数
+pub fn us_to_ticks(us: usize) -> usize {
+    (us as u128 * clock_frequency() as u128 / 1_000_000) as usize
+}
+
+/// 将 `time` 寄存器的计数转换为微秒
+pub fn ticks_to_us(ticks: usize) -> usize {
+    (ticks as u128 * 1_000_000 / clock_frequency() as u128) as usize
+}
+
+/// 定时器队列中事件的编号
+pub type TimerID = usize;
+
+static TIMER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 定时器队列的类型，以到期时间和编号排序
+type TimerQueue = BTreeMap<(usize, TimerID), Box<dyn FnOnce() + Send>>;
+
+lazy_static! {
+    /// 定时器队列，以到期时间和编号排序
+    ///
+    /// 使用关闭中断的 [`Lock`]，避免持有锁时被时钟中断打断而在中断处理中再次上锁
+    static ref TIMER_QUEUE: Lock<TimerQueue> = Lock::new(BTreeMap::new());
+}
+
+/// 每个 hart 通过 [`set_timeout`] 预约的时间（不考虑定时器队列），没有预约时为 `usize::MAX`
 ///
-/// 获取当前时间，加上中断间隔，通过 SBI 调用预约下一次中断
-fn set_next_timeout() {
-    set_timer(time::read() + INTERVAL);
+/// 只有对应的 hart 自己会访问，并且只在持有 [`TIMER_QUEUE`] 的锁（关闭中断）时访问
+static mut TIMEOUTS: [usize; MAX_HARTS] = [usize::MAX; MAX_HARTS];
+
+/// 为当前 hart 预约 `timeout` 和定时器队列中最早的事件二者中较早的时钟中断
+fn program(queue: &TimerQueue, timeout: usize) {
+    let next = queue.keys().next().map(|(deadline, _)| *deadline);
+    set_timer(next.map_or(timeout, |next| next.min(timeout)));
 }
 
-/// 触发时钟中断计数
-pub static mut TICKS: usize = 0;
+/// 在 `time` 寄存器到达 `deadline` 时，于时钟中断中执行 `callback`，返回事件的编号
+///
+/// `callback` 执行时关闭了中断，不能休眠；周期性的事件需要在其中再次调用 [`add_timer`]。
+/// 如果它是队列中最早的事件，当前 hart 预约的时钟中断可能太晚，因此重新预约
+pub fn add_timer(deadline: usize, callback: impl FnOnce() + Send + 'static) -> TimerID {
+    let id = TIMER_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut queue = TIMER_QUEUE.get();
+    queue.insert((deadline, id), Box::new(callback));
+    if queue.keys().next() == Some(&(deadline, id)) {
+        program(&queue, unsafe { TIMEOUTS[hart_id()] });
+    }
+    id
+}
+
+/// 取消一个还没有执行的事件，返回它是否仍在队列中
+pub fn cancel_timer(id: TimerID) -> bool {
+    let mut queue = TIMER_QUEUE.get();
+    let key = queue.keys().find(|(_, timer)| *timer == id).copied();
+    key.and_then(|key| queue.remove(&key)).is_some()
+}
+
+/// 依次执行已经到期的事件
+///
+/// 每次只在持有锁时取出一个事件，执行时不持有锁，这样事件中可以再次调用 [`add_timer`]
+fn fire_expired() {
+    let now = time::read();
+    loop {
+        let callback = {
+            let mut queue = TIMER_QUEUE.get();
+            match queue.keys().next().copied() {
+                Some(key) if key.0 <= now => queue.remove(&key).unwrap(),
+                _ => break,
+            }
+        };
+        callback();
+    }
+}
+
+/// 预约在 `time` 寄存器到达 `deadline` 时触发时钟中断，会取代之前的预约
+///
+/// 定时器队列中有更早的事件时，提前到该事件的时间
+pub fn set_timeout(deadline: usize) {
+    let queue = TIMER_QUEUE.get();
+    unsafe { TIMEOUTS[hart_id()] = deadline };
+    program(&queue, deadline);
+}
+
+/// 取消预约的时钟中断
+///
+/// 定时器队列不为空时，仍然为其中最早的事件预约
+pub fn cancel_timeout() {
+    set_timeout(usize::MAX);
+}
+
+/// 触发时钟中断计数（所有 hart 合计）
+pub static TICKS: AtomicUsize = AtomicUsize::new(0);
 
 /// 每一次时钟中断时调用
 ///
-/// 设置下一次时钟中断，同时计数 +1
+/// 执行到期的定时事件，计数 +1，下一次时钟中断在切换线程时预约
 pub fn tick() {
-    set_next_timeout();
-    unsafe {
-        TICKS += 1;
-        if TICKS % 5000 == 0 {
-            println!("{} tick", TICKS);
-        }
+    fire_expired();
+    // 在重新预约之前，避免同一个时钟中断再次触发（到期的事件已经取出，不会再为它们预约）
+    cancel_timeout();
+    let ticks = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    if ticks % 5000 == 0 {
+        println!("{} tick", ticks);
     }
 }
 
 /// 初始化时钟中断
 ///
-/// 开启时钟中断使能，并且预约第一次时钟中断
+/// 开启时钟中断使能，之后由调度预约时钟中断
 pub fn init() {
     unsafe {
         // 开启 STIE，允许时钟中断
@@ -41,6 +146,5 @@ pub fn init() {
         //实验4代码：我们会在线程开始运行时开启中断，而在操作系统初始化的过程中是不应该有中断的
         //sstatus::set_sie();
     }
-    // 设置下一次时钟中断
-    set_next_timeout();
+    cancel_timeout();
 }
@@ -2,7 +2,12 @@
 
 use super::*;
 use alloc::collections::VecDeque;
+use riscv::register::time;
 
+/// 条件变量
+///
+/// 与保护条件所依赖数据的 [`Lock`] 配合使用：持有锁检查条件，不满足时调用 [`Condvar::wait`]，
+/// 它在线程开始休眠之后才释放锁，被唤醒后重新获得锁再返回
 #[derive(Default)]
 pub struct Condvar {
     /// 所有等待此条件变量的线程
@@ -10,18 +15,88 @@ pub struct Condvar {
 }
 
 impl Condvar {
-    /// 令当前线程休眠，等待此条件变量
-    pub fn wait(&self) {
+    /// 令当前线程休眠，等待此条件变量，被唤醒后重新获得锁并返回
+    ///
+    /// `guard` 是条件所依赖数据的锁，在线程开始休眠之后才释放。
+    /// 这样在检查条件和开始休眠之间，其他线程不能修改数据，也就不会错过唤醒。
+    /// [`Lock`] 同时关闭了中断，开始休眠的过程也不会被时钟中断打断
+    pub fn wait<'a, T>(&self, mut guard: LockGuard<'a, T>) -> LockGuard<'a, T> {
         self.watchers
             .lock()
             .push_back(PROCESSOR.get().current_thread());
         PROCESSOR.get().sleep_current_thread();
+        guard.unlocked(|| PROCESSOR.schedule());
+        guard
     }
 
-    /// 唤起一个等待此条件变量的线程
-    pub fn notify_one(&self) {
-        if let Some(thread) = self.watchers.lock().pop_front() {
-            PROCESSOR.get().wake_thread(thread);
+    /// 令当前线程休眠，等待此条件变量，被唤醒后重新获得锁并返回
+    ///
+    /// 等待期间将当前线程的调度份额转让给 `holder`（通常是正在准备所等待资源的线程），让它尽快完成。
+    /// `guard` 的作用与 [`Condvar::wait`] 相同
+    pub fn wait_for<'a, T>(
+        &self,
+        holder: &Arc<Thread>,
+        mut guard: LockGuard<'a, T>,
+    ) -> LockGuard<'a, T> {
+        self.watchers
+            .lock()
+            .push_back(PROCESSOR.get().current_thread());
+        PROCESSOR.get().donate_current_thread(holder);
+        PROCESSOR.get().sleep_current_thread();
+        guard.unlocked(|| PROCESSOR.schedule());
+        guard
+    }
+
+    /// 与 [`Condvar::wait`] 相同，但最多等待 `timeout` 微秒
+    ///
+    /// 返回重新获得的锁，以及是否因为超时而被唤醒
+    pub fn wait_timeout<'a, T>(
+        &self,
+        mut guard: LockGuard<'a, T>,
+        timeout: usize,
+    ) -> (LockGuard<'a, T>, bool) {
+        let current_thread = PROCESSOR.get().current_thread();
+        self.watchers.lock().push_back(current_thread.clone());
+        let deadline = time::read() + us_to_ticks(timeout);
+        PROCESSOR.get().sleep_current_thread_until(deadline);
+        guard.unlocked(|| PROCESSOR.schedule());
+        // 被通知的线程已经从队列中取出，仍然在队列中说明是超时被唤醒
+        let mut watchers = self.watchers.lock();
+        let timed_out = match watchers.iter().position(|thread| *thread == current_thread) {
+            Some(index) => {
+                watchers.remove(index);
+                true
+            }
+            None => false,
+        };
+        (guard, timed_out)
+    }
+
+    /// 唤起一个等待此条件变量的线程，返回是否唤起了线程
+    ///
+    /// 队列中的线程可能已经随进程被强制结束，或者已经超时醒来，跳过它们继续唤起下一个
+    pub fn notify_one(&self) -> bool {
+        loop {
+            let thread = match self.watchers.lock().pop_front() {
+                Some(thread) => thread,
+                None => return false,
+            };
+            if PROCESSOR.wake_thread(thread) {
+                return true;
+            }
+        }
+    }
+
+    /// 是否有线程在等待此条件变量
+    pub fn has_watchers(&self) -> bool {
+        !self.watchers.lock().is_empty()
+    }
+
+    /// 唤起所有等待此条件变量的线程
+    pub fn notify_all(&self) {
+        let watchers = core::mem::take(&mut *self.watchers.lock());
+        for thread in watchers {
+            PROCESSOR.wake_thread(thread);
         }
     }
 }
@@ -0,0 +1,109 @@
+//! 内核调试控制台
+//!
+//! 在控制台按下 `Ctrl-P` 后输入线程 ID 并回车，会打印该线程所属进程的全部映射，
+//! 并检查页表与 [`Segment`] 是否一致。不输入 ID 直接回车则选择当前线程。
+//!
+//! 按下 `Ctrl-T` 会打印所有 hart 的调度统计。
+//!
+//! [`Segment`]: crate::memory::Segment
+
+use super::*;
+use alloc::string::String;
+use lazy_static::*;
+
+/// 进入调试命令的按键（Ctrl-P）
+const DEBUG_KEY: u8 = 0x10;
+
+/// 打印调度统计的按键（Ctrl-T）
+const SCHED_KEY: u8 = 0x14;
+
+lazy_static! {
+    /// 正在输入的调试命令，`None` 表示不在调试命令中
+    static ref COMMAND: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// 处理一个控制台输入的字符
+///
+/// 返回 `true` 表示字符已经被调试控制台处理，不应再交给 [`STDIN`](crate::fs::STDIN)
+pub fn debug_console(c: u8) -> bool {
+    let mut command = COMMAND.lock();
+    match (command.as_mut(), c) {
+        (None, DEBUG_KEY) => {
+            print!("\n[debug] dump thread: ");
+            *command = Some(String::new());
+        }
+        (None, SCHED_KEY) => {
+            drop(command);
+            println!();
+            PROCESSOR.report();
+        }
+        (None, _) => return false,
+        (Some(buffer), b'0'..=b'9') => {
+            buffer.push(c as char);
+            print!("{}", c as char);
+        }
+        (Some(buffer), b'\n') => {
+            let id = buffer.parse::<ThreadID>().ok();
+            *command = None;
+            // 打印过程中不需要持有锁
+            drop(command);
+            println!();
+            dump_thread(id);
+        }
+        (Some(_), _) => {
+            *command = None;
+            println!(" cancelled");
+        }
+    }
+    true
+}
+
+/// 打印线程的调度统计和所属进程的映射，并进行一致性检查
+fn dump_thread(id: Option<ThreadID>) {
+    let thread = match id {
+        Some(id) => Thread::find(id),
+        None => PROCESSOR.get().try_current_thread(),
+    };
+    let thread = match thread {
+        Some(thread) => thread,
+        None => {
+            println!("[debug] no such thread");
+            return;
+        }
+    };
+    let stats = thread.inner().stats;
+    println!(
+        "[debug] thread {}: user {} system {} wait {} (time counts), {} voluntary / {} involuntary switches, last on hart {}",
+        thread.id,
+        stats.user_time,
+        stats.system_time,
+        stats.wait_time,
+        stats.voluntary_switches,
+        stats.involuntary_switches,
+        stats.last_hart
+    );
+    let process = thread.process.clone();
+    // 中断时进程可能正在被修改，此时不能等待锁
+    let process = match process.try_read() {
+        Some(process) => process,
+        None => {
+            println!("[debug] process is busy, try again later");
+            return;
+        }
+    };
+    println!(
+        "[debug] process {}: {} resident frames, {} page table frames",
+        process.id,
+        process.memory_set.resident_frames(),
+        process.memory_set.page_table_frames()
+    );
+    process.memory_set.mapping.dump();
+    match process.memory_set.check() {
+        Ok(()) => println!("[debug] mapping is consistent with segments"),
+        Err(problems) => {
+            for problem in problems.iter() {
+                println!("[debug] {}", problem);
+            }
+        }
+    }
+}
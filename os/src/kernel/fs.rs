@@ -6,39 +6,112 @@ use core::slice::from_raw_parts_mut;
 
 /// 从指定的文件中读取字符
 ///
-/// 如果缓冲区暂无数据，返回 0；出现错误返回 -1
-// todo: inode 放到 process 中去
+/// 如果缓冲区暂无数据，线程会在内核中休眠直到有数据；出现错误返回 -1
 pub(super) fn sys_read(fd: usize, buffer: *mut u8, size: usize) -> SyscallResult {
-    // 从线程中获取 inode，注意避免锁
-    let inode: Arc<dyn INode> =
-        if let Some(inode) = PROCESSOR.get().current_thread().inner().descriptors.get(fd) {
-            inode.clone()
-        } else {
-            return SyscallResult::Proceed(-1);
-        };
+    // 从进程中获取 inode，注意避免锁
+    let inode: Arc<dyn INode> = if let Some(inode) = PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+    {
+        inode
+    } else {
+        return SyscallResult::Proceed(-1);
+    };
     let buffer = unsafe { from_raw_parts_mut(buffer, size) };
-    if let Ok(ret) = inode.read_at(0, buffer) {
-        let ret = ret as isize;
-        if ret > 0 {
-            return SyscallResult::Proceed(ret);
-        }
-        if ret == 0 {
-            return SyscallResult::Park(ret);
-        }
+    match inode.read_at(0, buffer) {
+        Ok(ret) => SyscallResult::Proceed(ret as isize),
+        Err(_) => SyscallResult::Proceed(-1),
     }
-    SyscallResult::Proceed(-1)
 }
 
 /// 将字符写入指定的文件
+///
+/// 如果写入的是所有读端都已关闭的管道，则终止当前进程（SIGPIPE）
 pub(super) fn sys_write(fd: usize, buffer: *mut u8, size: usize) -> SyscallResult {
-    if let Some(inode) = PROCESSOR.get().current_thread().inner().descriptors.get(fd) {
-        let buffer = unsafe { from_raw_parts_mut(buffer, size) };
-        if let Ok(ret) = inode.write_at(0, buffer) {
-            let ret = ret as isize;
-            if ret >= 0 {
-                return SyscallResult::Proceed(ret);
+    // 写入管道时可能休眠，不能持有进程的锁
+    let inode = match PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+    {
+        Some(inode) => inode,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let buffer = unsafe { from_raw_parts_mut(buffer, size) };
+    match inode.write_at(0, buffer) {
+        Ok(ret) => SyscallResult::Proceed(ret as isize),
+        Err(_) => {
+            let broken = inode
+                .as_any_ref()
+                .downcast_ref::<PipeWriter>()
+                .map_or(false, PipeWriter::is_broken);
+            if broken {
+                broken_pipe()
+            } else {
+                SyscallResult::Proceed(-1)
             }
         }
     }
-    SyscallResult::Proceed(-1)
+}
+
+/// 写入已断开的管道，终止当前进程
+fn broken_pipe() -> SyscallResult {
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let id = {
+        let mut process = process.write();
+        process.killed = true;
+        process.id
+    };
+    // 当前线程由 `SyscallResult::Kill` 终止，这里结束其余线程
+    PROCESSOR.kill_process(&process);
+    println!("broken pipe: killed process {}", id);
+    SyscallResult::Kill
+}
+
+/// 创建一个管道，将读端和写端的文件描述符依次写入 `fds`
+///
+/// `fds` 不可写时返回 -1
+pub(super) fn sys_pipe(fds: *mut usize) -> SyscallResult {
+    if !user_accessible(fds, 2, true) {
+        return SyscallResult::Proceed(-1);
+    }
+    let (reader, writer) = pipe();
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let mut process = process.write();
+    let read_fd = process.add_descriptor(reader);
+    let write_fd = process.add_descriptor(writer);
+    unsafe {
+        *fds = read_fd;
+        *fds.add(1) = write_fd;
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 复制文件描述符，新的文件描述符是当前未使用的最小的一个
+///
+/// 出现错误返回 -1
+pub(super) fn sys_dup(fd: usize) -> SyscallResult {
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let mut process = process.write();
+    match process.descriptor(fd) {
+        Some(inode) => SyscallResult::Proceed(process.add_descriptor(inode) as isize),
+        None => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 关闭文件描述符
+///
+/// 出现错误返回 -1
+pub(super) fn sys_close(fd: usize) -> SyscallResult {
+    // 关闭管道的一端时会唤醒等待的线程，不在持有进程的锁时 drop
+    let inode = PROCESSOR.get().current_thread().process.write().remove_descriptor(fd);
+    match inode {
+        Some(_) => SyscallResult::Proceed(0),
+        None => SyscallResult::Proceed(-1),
+    }
 }
@@ -0,0 +1,79 @@
+//! 用户程序使用的 futex
+//!
+//! 等待队列以用户虚拟地址背后的物理地址为键，
+//! 因此不同进程通过共享内存映射同一个变量时，等待和唤醒的是同一个队列
+
+use super::*;
+use crate::memory::*;
+use alloc::collections::BTreeMap;
+use core::sync::atomic::{AtomicU32, Ordering};
+use lazy_static::*;
+
+/// 值与预期相同时休眠等待
+pub const FUTEX_WAIT: usize = 0;
+/// 唤醒最多给定数量的等待者
+pub const FUTEX_WAKE: usize = 1;
+
+lazy_static! {
+    /// 所有有线程在等待的 futex，以物理地址索引
+    ///
+    /// 使用关闭中断的 [`Lock`]，以便交给 [`Condvar::wait`] 在线程开始休眠之后才释放
+    static ref FUTEXES: Lock<BTreeMap<usize, Arc<Condvar>>> = Lock::new(BTreeMap::new());
+}
+
+/// 对 `address` 处的 32 位整数进行 futex 操作
+///
+/// - [`FUTEX_WAIT`]：如果值仍然等于 `value` 则休眠，直到被唤醒；值已经改变时返回 -1
+/// - [`FUTEX_WAKE`]：唤醒最多 `value` 个等待者，返回实际唤醒的数量
+///
+/// 地址没有对齐、不能被用户访问或者操作不存在时返回 -1
+pub(super) fn sys_futex(address: usize, op: usize, value: usize) -> SyscallResult {
+    if address % 4 != 0 {
+        return SyscallResult::Proceed(-1);
+    }
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let key = match process
+        .read()
+        .memory_set
+        .translate_user(VirtualAddress(address))
+    {
+        Some(physical_address) => physical_address,
+        None => return SyscallResult::Proceed(-1),
+    };
+    match op {
+        FUTEX_WAIT => futex_wait(key, value as u32),
+        FUTEX_WAKE => futex_wake(key, value),
+        _ => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 值仍然为 `value` 时在 `key` 对应的队列中休眠
+fn futex_wait(key: PhysicalAddress, value: u32) -> SyscallResult {
+    let mut futexes = FUTEXES.get();
+    // 持有表的锁检查值：唤醒者修改值之后一定要获得同一把锁，因此不会错过唤醒
+    let current = key.deref_kernel::<AtomicU32>().load(Ordering::SeqCst);
+    if current != value {
+        return SyscallResult::Proceed(-1);
+    }
+    let condvar = futexes.entry(key.0).or_default().clone();
+    drop(condvar.wait(futexes));
+    SyscallResult::Proceed(0)
+}
+
+/// 唤醒 `key` 对应队列中最多 `count` 个线程，返回唤醒的数量
+fn futex_wake(key: PhysicalAddress, count: usize) -> SyscallResult {
+    let mut futexes = FUTEXES.get();
+    let condvar = match futexes.get(&key.0) {
+        Some(condvar) => condvar.clone(),
+        None => return SyscallResult::Proceed(0),
+    };
+    let mut woken = 0;
+    while woken < count && condvar.notify_one() {
+        woken += 1;
+    }
+    // 没有等待者的队列不再保留
+    if !condvar.has_watchers() {
+        futexes.remove(&key.0);
+    }
+    SyscallResult::Proceed(woken as isize)
+}
@@ -0,0 +1,62 @@
+//! 内存相关的内核功能
+
+use super::*;
+use crate::memory::*;
+use core::mem::size_of;
+
+/// 当前进程中从 `pointer` 开始的 `count` 个 `T` 是否全部允许用户读取，`writable` 时还需要可写
+///
+/// 在系统调用中访问用户传入的指针之前调用，见 [`MemorySet::is_user_accessible`]
+pub(super) fn user_accessible<T>(pointer: *const T, count: usize, writable: bool) -> bool {
+    let size = match size_of::<T>().checked_mul(count) {
+        Some(size) => size,
+        None => return false,
+    };
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let accessible = process.read().memory_set.is_user_accessible(
+        VirtualAddress(pointer as usize),
+        size,
+        writable,
+    );
+    accessible
+}
+
+/// 按键值打开一段共享内存，不存在则创建
+///
+/// 返回共享内存的标识（即键值），出现错误返回 -1
+pub(super) fn sys_shmget(key: usize, size: usize) -> SyscallResult {
+    match SharedMemory::open(key, size) {
+        Ok(shared_memory) => SyscallResult::Proceed(shared_memory.key as isize),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 将共享内存映射到当前进程，返回映射的起始地址
+///
+/// 出现错误返回 -1
+pub(super) fn sys_shmat(id: usize) -> SyscallResult {
+    let shared_memory = match SharedMemory::get(id) {
+        Some(shared_memory) => shared_memory,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let result = process.write().attach_shared_memory(shared_memory);
+    match result {
+        Ok(address) => SyscallResult::Proceed(address.0 as isize),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 解除当前进程在 `address` 处的共享内存映射
+///
+/// 最后一个进程解除映射后，共享内存的物理页面会被释放。出现错误返回 -1
+pub(super) fn sys_shmdt(address: usize) -> SyscallResult {
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let result = process
+        .write()
+        .detach_shared_memory(VirtualAddress(address));
+    match result {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
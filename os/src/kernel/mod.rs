@@ -1,17 +1,38 @@
 //! 为进程提供系统调用等内核功能
 
 mod condvar;
+mod debug;
 mod fs;
+mod futex;
+mod memory;
+mod mqueue;
+mod poll;
 mod process;
+mod semaphore;
+mod signal;
+mod socket;
+pub mod sync;
 mod syscall;
+mod timer;
 
 use crate::interrupt::*;
+use crate::lockdep::Mutex;
 use crate::process::*;
 use alloc::sync::Arc;
 pub(self) use fs::*;
+pub(self) use futex::*;
+pub(self) use memory::*;
+pub(self) use mqueue::*;
+pub(self) use poll::*;
 pub(self) use process::*;
-use spin::Mutex;
+pub(self) use semaphore::*;
+pub(self) use signal::*;
+pub(self) use socket::*;
 pub(self) use syscall::*;
+pub(self) use timer::*;
 
 pub use condvar::Condvar;
+pub use debug::debug_console;
+pub use process::process_exited;
 pub use syscall::syscall_handler;
+pub use timer::charge_cpu_time;
@@ -0,0 +1,154 @@
+//! 用户进程使用的消息队列
+//!
+//! 消息队列以名字索引，不同进程用同一个名字打开同一个队列。
+//! 打开的队列放在文件描述符表中，也可以用 `sys_read` 和 `sys_write` 收发优先级为 0 的消息
+
+use super::sync;
+use super::*;
+use crate::fs::*;
+use alloc::{collections::BTreeMap, string::String};
+use core::slice::{from_raw_parts, from_raw_parts_mut};
+use core::str::from_utf8;
+use lazy_static::*;
+
+/// 打开时如果不存在则创建
+pub const MQ_CREATE: usize = 1;
+/// 以非阻塞方式打开
+pub const MQ_NONBLOCK: usize = 2;
+
+lazy_static! {
+    /// 所有消息队列，以名字索引
+    static ref MESSAGE_QUEUES: sync::Mutex<BTreeMap<String, Arc<MessageQueue>>> =
+        sync::Mutex::new(BTreeMap::new());
+}
+
+/// 按名字打开一个消息队列，返回文件描述符
+///
+/// `flags` 包含 [`MQ_CREATE`] 时，队列不存在则以 `capacity` 和 `max_size` 创建，否则忽略这两个参数。
+/// 两者都不能为 0，也不能超过 [`MQ_MAX_CAPACITY`] 和 [`MQ_MAX_SIZE`]。出现错误返回 -1
+pub(super) fn sys_mq_open(
+    name: *const u8,
+    length: usize,
+    flags: usize,
+    capacity: usize,
+    max_size: usize,
+) -> SyscallResult {
+    let name = match from_utf8(unsafe { from_raw_parts(name, length) }) {
+        Ok(name) => name,
+        Err(_) => return SyscallResult::Proceed(-1),
+    };
+    let queue = {
+        let mut queues = MESSAGE_QUEUES.lock();
+        match queues.get(name) {
+            Some(queue) => queue.clone(),
+            None if flags & MQ_CREATE != 0 && valid_attributes(capacity, max_size) => queues
+                .entry(String::from(name))
+                .or_insert_with(|| Arc::new(MessageQueue::new(capacity, max_size)))
+                .clone(),
+            None => return SyscallResult::Proceed(-1),
+        }
+    };
+    let handle = Arc::new(MessageQueueHandle {
+        queue,
+        nonblocking: flags & MQ_NONBLOCK != 0,
+    });
+    let fd = PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .write()
+        .add_descriptor(handle);
+    SyscallResult::Proceed(fd as isize)
+}
+
+/// 创建队列时的容量和消息长度是否合法
+fn valid_attributes(capacity: usize, max_size: usize) -> bool {
+    (1..=MQ_MAX_CAPACITY).contains(&capacity) && (1..=MQ_MAX_SIZE).contains(&max_size)
+}
+
+/// 删除名为 `name` 的消息队列
+///
+/// 已经打开的描述符仍然可以继续使用队列，直到全部关闭；之后以同一个名字打开的是新的队列。
+/// 队列不存在时返回 -1
+pub(super) fn sys_mq_unlink(name: *const u8, length: usize) -> SyscallResult {
+    let name = match from_utf8(unsafe { from_raw_parts(name, length) }) {
+        Ok(name) => name,
+        Err(_) => return SyscallResult::Proceed(-1),
+    };
+    match MESSAGE_QUEUES.lock().remove(name) {
+        Some(_) => SyscallResult::Proceed(0),
+        None => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 取出文件描述符对应的消息队列
+fn find_queue(fd: usize) -> Option<Arc<dyn INode>> {
+    PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+        .filter(|inode| inode.as_any_ref().is::<MessageQueueHandle>())
+}
+
+/// 以 `priority` 发送一条消息，队列已满时最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 以非阻塞方式打开时不等待。出现错误或超时返回 -1
+pub(super) fn sys_mq_send(
+    fd: usize,
+    buffer: *const u8,
+    size: usize,
+    priority: usize,
+    timeout: usize,
+) -> SyscallResult {
+    // 发送时可能休眠，不能持有进程的锁
+    let inode = match find_queue(fd) {
+        Some(inode) => inode,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let handle = inode
+        .as_any_ref()
+        .downcast_ref::<MessageQueueHandle>()
+        .unwrap();
+    let buffer = unsafe { from_raw_parts(buffer, size) };
+    match handle.queue.send(buffer, priority, handle.wait(timeout)) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 取出优先级最高的消息，队列为空时最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 返回消息的长度，`priority` 不为空时写入消息的优先级。
+/// 缓冲区不能小于队列的最大消息长度。以非阻塞方式打开时不等待。
+/// `priority` 不可写时不取出消息；出现错误或超时返回 -1
+pub(super) fn sys_mq_receive(
+    fd: usize,
+    buffer: *mut u8,
+    size: usize,
+    priority: *mut usize,
+    timeout: usize,
+) -> SyscallResult {
+    if !(priority.is_null() || user_accessible(priority, 1, true)) {
+        return SyscallResult::Proceed(-1);
+    }
+    let inode = match find_queue(fd) {
+        Some(inode) => inode,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let handle = inode
+        .as_any_ref()
+        .downcast_ref::<MessageQueueHandle>()
+        .unwrap();
+    let buffer = unsafe { from_raw_parts_mut(buffer, size) };
+    match handle.queue.receive(buffer, handle.wait(timeout)) {
+        Ok((length, message_priority)) => {
+            if !priority.is_null() {
+                unsafe { *priority = message_priority };
+            }
+            SyscallResult::Proceed(length as isize)
+        }
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
@@ -0,0 +1,195 @@
+//! 同时等待多个文件描述符的 `poll` 和 `select`
+
+use super::*;
+use crate::fs::*;
+use alloc::vec::Vec;
+use core::slice::from_raw_parts_mut;
+use riscv::register::time;
+
+/// 可以读取
+pub const POLLIN: usize = 0x1;
+/// 可以写入
+pub const POLLOUT: usize = 0x4;
+/// 出现错误（例如管道的读端都已关闭）
+pub const POLLERR: usize = 0x8;
+/// 文件描述符无效
+pub const POLLNVAL: usize = 0x20;
+
+/// `select` 的文件描述符集合是一个 `usize` 位图，最多支持的文件描述符数量
+const SELECT_MAX_FDS: usize = 64;
+
+/// 传给 `poll` 的一项
+#[repr(C)]
+pub struct PollFd {
+    /// 文件描述符
+    pub fd: usize,
+    /// 关心的事件
+    pub events: usize,
+    /// 发生的事件，由内核填写
+    pub revents: usize,
+}
+
+/// 将 `poller` 注册到 `inodes` 上，反复调用 `check` 直到它返回非 0 或者超时
+///
+/// `timeout` 的单位为微秒，为负数时一直等待。返回 `check` 最后一次的结果
+fn wait_ready(
+    inodes: &[Arc<dyn INode>],
+    timeout: isize,
+    mut check: impl FnMut() -> usize,
+) -> usize {
+    let deadline = if timeout < 0 {
+        None
+    } else {
+        Some(time::read() + us_to_ticks(timeout as usize))
+    };
+    let poller = Poller::new();
+    loop {
+        // 先注册再检查，检查之后的改变会唤醒 poller
+        for inode in inodes.iter() {
+            if let Some(pollable) = inode.as_pollable() {
+                pollable.register(&poller);
+            }
+        }
+        let ready = check();
+        if ready > 0 {
+            return ready;
+        }
+        let remaining = match deadline {
+            Some(deadline) => {
+                let now = time::read();
+                if now >= deadline {
+                    return 0;
+                }
+                Some(ticks_to_us(deadline - now))
+            }
+            None => None,
+        };
+        poller.wait(remaining);
+    }
+}
+
+/// 文件当前的状态
+///
+/// 磁盘上的普通文件不支持 poll，但读写它们不会阻塞，视为总是就绪
+fn poll_status(inode: &Arc<dyn INode>) -> Result<PollStatus> {
+    match inode.poll() {
+        Err(FsError::NotSupported) => Ok(PollStatus {
+            read: true,
+            write: true,
+            error: false,
+        }),
+        result => result,
+    }
+}
+
+/// 取得文件描述符对应的文件
+fn find_descriptor(fd: usize) -> Option<Arc<dyn INode>> {
+    PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+}
+
+/// 等待 `fds` 中任何一项关心的事件发生，最多等待 `timeout` 微秒（为负数时一直等待）
+///
+/// 填写每一项的 `revents`，返回有事件发生的项数，超时返回 0，`fds` 不可读写时返回 -1
+///
+/// 使用 Linux 中 `ppoll` 的编号，但参数不同：`timeout` 直接以微秒给出，也没有信号掩码
+pub(super) fn sys_poll(fds: *mut PollFd, count: usize, timeout: isize) -> SyscallResult {
+    if !user_accessible(fds, count, true) {
+        return SyscallResult::Proceed(-1);
+    }
+    let fds = unsafe { from_raw_parts_mut(fds, count) };
+    let inodes: Vec<Option<Arc<dyn INode>>> = fds.iter().map(|fd| find_descriptor(fd.fd)).collect();
+    let registered: Vec<Arc<dyn INode>> = inodes.iter().flatten().cloned().collect();
+    let ready = wait_ready(&registered, timeout, || {
+        let mut ready = 0;
+        for (fd, inode) in fds.iter_mut().zip(inodes.iter()) {
+            fd.revents = match inode {
+                Some(inode) => match poll_status(inode) {
+                    Ok(status) => {
+                        let mut revents = 0;
+                        if status.read {
+                            revents |= POLLIN;
+                        }
+                        if status.write {
+                            revents |= POLLOUT;
+                        }
+                        // 错误总是报告，不需要关心
+                        (revents & fd.events) | if status.error { POLLERR } else { 0 }
+                    }
+                    Err(_) => POLLERR,
+                },
+                None => POLLNVAL,
+            };
+            if fd.revents != 0 {
+                ready += 1;
+            }
+        }
+        ready
+    });
+    SyscallResult::Proceed(ready as isize)
+}
+
+/// 等待 `read` 中的任何文件描述符可以读取，或者 `write` 中的任何文件描述符可以写入
+///
+/// `read` 和 `write` 是文件描述符的位图，可以为空，返回时只保留就绪的文件描述符。
+/// 最多等待 `timeout` 微秒（为负数时一直等待）。返回就绪的文件描述符数量，超时返回 0，
+/// 位图不可读写或者出现其他错误时返回 -1
+///
+/// 使用 Linux 中 `pselect6` 的编号，但参数不同：没有 `nfds`、异常集合和信号掩码，位图只有一个 `usize`
+pub(super) fn sys_select(read: *mut usize, write: *mut usize, timeout: isize) -> SyscallResult {
+    for &set in &[read, write] {
+        if !(set.is_null() || user_accessible(set, 1, true)) {
+            return SyscallResult::Proceed(-1);
+        }
+    }
+    let read_set = if read.is_null() { 0 } else { unsafe { *read } };
+    let write_set = if write.is_null() {
+        0
+    } else {
+        unsafe { *write }
+    };
+    let mut inodes = Vec::new();
+    for fd in (0..SELECT_MAX_FDS).filter(|fd| (read_set | write_set) & (1 << fd) != 0) {
+        match find_descriptor(fd) {
+            Some(inode) => inodes.push((fd, inode)),
+            None => return SyscallResult::Proceed(-1),
+        }
+    }
+    let registered: Vec<Arc<dyn INode>> = inodes.iter().map(|(_, inode)| inode.clone()).collect();
+    let (mut read_ready, mut write_ready) = (0, 0);
+    let ready = wait_ready(&registered, timeout, || {
+        read_ready = 0;
+        write_ready = 0;
+        for (fd, inode) in inodes.iter() {
+            let status = match poll_status(inode) {
+                Ok(status) => status,
+                Err(_) => PollStatus {
+                    read: false,
+                    write: false,
+                    error: true,
+                },
+            };
+            // 出现错误时读写都会立即返回，视为就绪
+            if (status.read || status.error) && read_set & (1 << fd) != 0 {
+                read_ready |= 1 << fd;
+            }
+            if (status.write || status.error) && write_set & (1 << fd) != 0 {
+                write_ready |= 1 << fd;
+            }
+        }
+        (read_ready.count_ones() + write_ready.count_ones()) as usize
+    });
+    unsafe {
+        if !read.is_null() {
+            *read = read_ready;
+        }
+        if !write.is_null() {
+            *write = write_ready;
+        }
+    }
+    SyscallResult::Proceed(ready as isize)
+}
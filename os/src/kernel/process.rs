@@ -1,6 +1,20 @@
 //! 进程相关的内核功能
 
 use super::*;
+use crate::memory::VirtualAddress;
+use algorithm::RealtimeParams;
+use core::mem::size_of;
+use core::slice::from_raw_parts;
+use core::str::from_utf8;
+
+/// 进程结束或被强制结束时，回收它在内核中登记的资源
+///
+/// 被强制结束时先调用一次，进程被丢弃时再调用一次，因此需要允许重复调用
+pub fn process_exited(id: ProcessID) {
+    release_banker_allocation(id);
+    cancel_real_timer(id);
+    clear_signals(id);
+}
 
 pub(super) fn sys_exit(code: usize) -> SyscallResult {
     println!(
@@ -10,3 +24,173 @@ pub(super) fn sys_exit(code: usize) -> SyscallResult {
     );
     SyscallResult::Kill
 }
+
+/// 从文件系统中加载名为 `name` 的程序，在新的进程中执行，返回新进程的 ID
+///
+/// `memory_limit` 为新进程最多可以占用的内存（字节），为 0 时使用默认的 [`USER_MEMORY_LIMIT`]。
+/// 限制在加载程序之前就生效，程序本身超出限制时同样失败。出现错误返回 -1
+///
+/// 新进程继承当前进程打开的所有文件，文件描述符保持不变
+pub(super) fn sys_spawn(name: *const u8, length: usize, memory_limit: usize) -> SyscallResult {
+    let name = match from_utf8(unsafe { from_raw_parts(name, length) }) {
+        Ok(name) => name,
+        Err(_) => return SyscallResult::Proceed(-1),
+    };
+    let memory_limit = match memory_limit {
+        0 => USER_MEMORY_LIMIT,
+        limit => Some(limit),
+    };
+    match Process::spawn(name, memory_limit) {
+        Ok(thread) => {
+            let descriptors = PROCESSOR
+                .get()
+                .current_thread()
+                .process
+                .read()
+                .descriptors
+                .clone();
+            thread.process.write().descriptors = descriptors;
+            let id = thread.process.read().id;
+            PROCESSOR.add_thread(thread);
+            SyscallResult::Proceed(id as isize)
+        }
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 找到 `id` 对应的线程，`id` 为 0 时表示当前线程
+///
+/// 只能找到与当前线程属于同一个进程的线程
+fn find_thread(id: ThreadID) -> Option<Arc<Thread>> {
+    let current_thread = PROCESSOR.get().current_thread();
+    if id == 0 {
+        return Some(current_thread);
+    }
+    Thread::find(id).filter(|thread| Arc::ptr_eq(&thread.process, &current_thread.process))
+}
+
+/// 设置线程的优先级，`id` 为 0 时表示当前线程
+///
+/// 优先级不能超过 [`MAX_PRIORITY`]，出现错误返回 -1
+pub(super) fn sys_set_priority(id: usize, priority: usize) -> SyscallResult {
+    if priority > MAX_PRIORITY {
+        return SyscallResult::Proceed(-1);
+    }
+    match find_thread(id as ThreadID) {
+        Some(thread) => {
+            PROCESSOR.set_priority(&thread, priority);
+            SyscallResult::Proceed(0)
+        }
+        None => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 获取线程的优先级，`id` 为 0 时表示当前线程
+///
+/// 出现错误返回 -1
+pub(super) fn sys_get_priority(id: usize) -> SyscallResult {
+    match find_thread(id as ThreadID) {
+        Some(thread) => SyscallResult::Proceed(thread.inner().priority as isize),
+        None => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 用户程序传入的实时调度参数，单位为微秒
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub(super) struct RealtimeAttr {
+    /// 周期
+    period: usize,
+    /// 每个周期内的执行时间
+    budget: usize,
+    /// 相对于周期开始的截止时间
+    deadline: usize,
+}
+
+/// 设置线程的实时调度参数，`id` 为 0 时表示当前线程
+///
+/// `attr` 为空指针时将线程移回普通调度器。
+/// `attr` 不可读、参数不合法或者无法通过可调度性检查时返回 -1
+pub(super) fn sys_sched_setattr(id: usize, attr: *const RealtimeAttr) -> SyscallResult {
+    let thread = match find_thread(id as ThreadID) {
+        Some(thread) => thread,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let params = if attr.is_null() {
+        None
+    } else {
+        let process = PROCESSOR.get().current_thread().process.clone();
+        let readable = process.read().memory_set.is_user_accessible(
+            VirtualAddress(attr as usize),
+            size_of::<RealtimeAttr>(),
+            false,
+        );
+        if !readable {
+            return SyscallResult::Proceed(-1);
+        }
+        // 将微秒转换为 time 寄存器的计数
+        let attr = unsafe { attr.read_unaligned() };
+        let to_ticks = |us: usize| {
+            us.checked_mul(clock_frequency())
+                .map(|ticks| ticks / 1_000_000)
+        };
+        match (
+            to_ticks(attr.period),
+            to_ticks(attr.budget),
+            to_ticks(attr.deadline),
+        ) {
+            (Some(period), Some(budget), Some(deadline)) => Some(RealtimeParams {
+                period,
+                budget,
+                deadline,
+            }),
+            _ => return SyscallResult::Proceed(-1),
+        }
+    };
+    match PROCESSOR.set_realtime(&thread, params) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 返回给用户程序的线程调度统计，时间的单位为微秒
+#[repr(C)]
+pub(super) struct Rusage {
+    /// 在用户态执行的时间
+    user_time: usize,
+    /// 在内核态执行的时间
+    system_time: usize,
+    /// 可以执行但在等待调度的时间
+    wait_time: usize,
+    /// 主动让出 hart 的次数
+    voluntary_switches: usize,
+    /// 被抢占的次数
+    involuntary_switches: usize,
+    /// 上一次在哪个 hart 上执行
+    last_hart: usize,
+}
+
+/// 获取线程的调度统计，`id` 为 0 时表示当前线程
+///
+/// `usage` 不可写或者出现其他错误时返回 -1
+pub(super) fn sys_getrusage(id: usize, usage: *mut Rusage) -> SyscallResult {
+    if !user_accessible(usage, 1, true) {
+        return SyscallResult::Proceed(-1);
+    }
+    let thread = match find_thread(id as ThreadID) {
+        Some(thread) => thread,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let stats = thread.inner().stats;
+    unsafe {
+        *usage = Rusage {
+            user_time: ticks_to_us(stats.user_time),
+            system_time: ticks_to_us(stats.system_time),
+            wait_time: ticks_to_us(stats.wait_time),
+            voluntary_switches: stats.voluntary_switches,
+            involuntary_switches: stats.involuntary_switches,
+            last_hart: stats.last_hart,
+        };
+    }
+    SyscallResult::Proceed(0)
+}
@@ -0,0 +1,203 @@
+//! 用户进程使用的信号量
+//!
+//! 与共享内存相同，信号量以键值索引，不同进程用同一个键值打开同一个信号量
+//!
+//! 进程可以通过 [`sys_semclaim`] 声明对某个信号量的最大需求，之后它对该信号量的 P 操作使用银行家算法：
+//! 只有分配之后系统仍处于安全状态时才会分配，否则等待其他进程释放，从而避免死锁。
+//! 进程结束时，它经银行家算法获得的计数会被归还（见 [`release_banker_allocation`]）
+
+use super::sync::{self, Semaphore};
+use super::*;
+use algorithm::is_safe;
+use alloc::collections::BTreeMap;
+use lazy_static::*;
+
+lazy_static! {
+    /// 所有用户信号量，以键值索引
+    static ref SEMAPHORES: sync::RwLock<BTreeMap<usize, Arc<Semaphore>>> =
+        sync::RwLock::new(BTreeMap::new());
+    /// 银行家算法的状态
+    static ref BANKER: Lock<Banker> = Lock::new(Banker::default());
+    /// 银行家算法暂不分配的线程在此等待
+    static ref BANKER_CONDVAR: Condvar = Condvar::default();
+}
+
+/// 银行家算法的状态，只包括声明了最大需求的进程
+#[derive(Default)]
+struct Banker {
+    /// 每个进程对每个信号量声明的最大需求
+    claims: BTreeMap<ProcessID, BTreeMap<usize, usize>>,
+    /// 每个进程从每个信号量已经获得的数量
+    allocation: BTreeMap<ProcessID, BTreeMap<usize, usize>>,
+    /// 被声明过的信号量
+    ///
+    /// 在这里另外持有，计算剩余计数时不需要在持有 [`static@BANKER`] 的同时访问会休眠的全局表
+    semaphores: BTreeMap<usize, Arc<Semaphore>>,
+}
+
+impl Banker {
+    /// 进程对信号量声明的最大需求，没有声明时为 `None`
+    fn claim(&self, process: ProcessID, id: usize) -> Option<usize> {
+        self.claims.get(&process)?.get(&id).copied()
+    }
+
+    /// 进程从信号量已经获得的数量
+    fn allocated(&self, process: ProcessID, id: usize) -> usize {
+        self.allocation
+            .get(&process)
+            .and_then(|allocation| allocation.get(&id).copied())
+            .unwrap_or(0)
+    }
+
+    /// 所有被声明的信号量当前的剩余计数
+    fn available(&self) -> BTreeMap<usize, usize> {
+        self.claims
+            .values()
+            .flat_map(|claims| claims.keys())
+            .map(|id| (*id, self.semaphores.get(id).map_or(0, |s| s.count())))
+            .collect()
+    }
+
+    /// 在剩余计数为 `available` 时，是否存在让所有进程依次满足最大需求并结束的顺序
+    fn is_safe(&self, available: BTreeMap<usize, usize>) -> bool {
+        is_safe(&self.claims, &self.allocation, available)
+    }
+}
+
+/// 按键值打开一个信号量，不存在则以 `value` 为初始计数创建
+///
+/// 返回信号量的标识（即键值）
+pub(super) fn sys_semget(key: usize, value: usize) -> SyscallResult {
+    SEMAPHORES
+        .write()
+        .entry(key)
+        .or_insert_with(|| Arc::new(Semaphore::new(value)));
+    SyscallResult::Proceed(key as isize)
+}
+
+/// 声明当前进程对信号量最多同时需要 `max` 个计数，为 0 时取消声明
+///
+/// 不能小于已经获得的数量，出现错误返回 -1
+pub(super) fn sys_semclaim(id: usize, max: usize) -> SyscallResult {
+    let semaphore = match SEMAPHORES.read().get(&id) {
+        Some(semaphore) => semaphore.clone(),
+        None => return SyscallResult::Proceed(-1),
+    };
+    let process = PROCESSOR.get().current_thread().process.read().id;
+    let mut banker = BANKER.get();
+    banker.semaphores.insert(id, semaphore);
+    if max < banker.allocated(process, id) {
+        return SyscallResult::Proceed(-1);
+    }
+    let claims = banker.claims.entry(process).or_default();
+    if max == 0 {
+        claims.remove(&id);
+        if claims.is_empty() {
+            banker.claims.remove(&process);
+        }
+    } else {
+        claims.insert(id, max);
+    }
+    // 取消声明可能使等待的线程可以被分配
+    BANKER_CONDVAR.notify_all();
+    SyscallResult::Proceed(0)
+}
+
+/// 对信号量进行操作：`op` 为正数时将计数增加 `op`，为负数时减少 `-op`
+///
+/// 减少时计数不足 `-op` 则休眠等待，直到可以一次性减少，等待期间不会占用任何计数。
+/// `op` 的绝对值不能超过 [`SEMAPHORE_OP_LIMIT`]。
+/// 声明过最大需求的进程按银行家算法分配，超过声明时返回 -1。出现错误返回 -1
+pub(super) fn sys_semop(id: usize, op: isize) -> SyscallResult {
+    // isize::MIN 取绝对值后仍为负数，转换后超过限制
+    let count = op.wrapping_abs() as usize;
+    if count > SEMAPHORE_OP_LIMIT {
+        return SyscallResult::Proceed(-1);
+    }
+    // 等待时不持有全局表的锁
+    let semaphore = match SEMAPHORES.read().get(&id) {
+        Some(semaphore) => semaphore.clone(),
+        None => return SyscallResult::Proceed(-1),
+    };
+    let process = PROCESSOR.get().current_thread().process.read().id;
+    if op >= 0 {
+        release(process, id, &semaphore, count);
+    } else if !acquire(process, id, &semaphore, count) {
+        return SyscallResult::Proceed(-1);
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 为进程一次性获得 `count` 个计数，返回是否成功
+///
+/// 声明过最大需求的进程按银行家算法分配，加上已经获得的数量超过声明时不分配并返回 `false`；
+/// 没有声明的进程直接在信号量上等待
+fn acquire(process: ProcessID, id: usize, semaphore: &Semaphore, count: usize) -> bool {
+    let mut banker = BANKER.get();
+    loop {
+        // 等待期间声明可能被修改或取消，每次都重新检查
+        let claim = match banker.claim(process, id) {
+            Some(claim) => claim,
+            None => {
+                drop(banker);
+                semaphore.acquire_n(count);
+                return true;
+            }
+        };
+        if banker.allocated(process, id) + count > claim {
+            return false;
+        }
+        let mut available = banker.available();
+        if available[&id] >= count {
+            // 假设分配，检查之后是否仍然安全
+            *available.get_mut(&id).unwrap() -= count;
+            *banker
+                .allocation
+                .entry(process)
+                .or_default()
+                .entry(id)
+                .or_default() += count;
+            // 没有声明的进程不经过银行家算法，可能已经取走了剩余的计数
+            if banker.is_safe(available) && semaphore.try_acquire_n(count) {
+                return true;
+            }
+            *banker
+                .allocation
+                .get_mut(&process)
+                .unwrap()
+                .get_mut(&id)
+                .unwrap() -= count;
+        }
+        banker = BANKER_CONDVAR.wait(banker);
+    }
+}
+
+/// 为进程释放 `count` 个计数，并唤醒等待银行家算法分配的线程
+fn release(process: ProcessID, id: usize, semaphore: &Semaphore, count: usize) {
+    let mut banker = BANKER.get();
+    semaphore.release_n(count);
+    if let Some(allocated) = banker
+        .allocation
+        .get_mut(&process)
+        .and_then(|allocation| allocation.get_mut(&id))
+    {
+        *allocated = allocated.saturating_sub(count);
+    }
+    BANKER_CONDVAR.notify_all();
+}
+
+/// 进程结束或被强制结束时，归还它经银行家算法获得的计数并撤销它的声明，唤醒等待分配的线程
+///
+/// 可能对同一个进程调用多次
+pub(super) fn release_banker_allocation(process: ProcessID) {
+    let mut banker = BANKER.get();
+    banker.claims.remove(&process);
+    if let Some(allocation) = banker.allocation.remove(&process) {
+        for (id, count) in allocation {
+            if let Some(semaphore) = banker.semaphores.get(&id) {
+                semaphore.reclaim(count);
+            }
+        }
+    }
+    BANKER_CONDVAR.notify_all();
+}
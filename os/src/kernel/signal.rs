@@ -0,0 +1,86 @@
+//! 发给进程的信号
+//!
+//! 目前只用于定时器到期的通知：信号记为进程的待处理信号，不会打断进程的执行，
+//! 进程通过 [`sys_sigtimedwait`] 等待并取走信号，或者通过 [`sys_sigpending`] 查看
+
+use super::*;
+use alloc::collections::BTreeMap;
+use lazy_static::*;
+use riscv::register::time;
+
+/// [`ITIMER_REAL`] 到期
+pub const SIGALRM: usize = 14;
+/// [`ITIMER_VIRTUAL`] 到期
+pub const SIGVTALRM: usize = 26;
+/// [`ITIMER_PROF`] 到期
+pub const SIGPROF: usize = 27;
+
+/// 一个进程的信号
+///
+/// 信号在时钟中断中发送，使用关闭中断的 [`Lock`]
+#[derive(Default)]
+struct Signals {
+    /// 待处理信号，第 n 位表示信号 n
+    pending: Lock<usize>,
+    /// 该进程中等待信号的线程在此休眠
+    condvar: Condvar,
+}
+
+lazy_static! {
+    /// 每个进程的信号，在第一次发送或等待时创建，进程结束时移除（见 [`clear_signals`]）
+    static ref SIGNALS: Lock<BTreeMap<ProcessID, Arc<Signals>>> = Lock::new(BTreeMap::new());
+}
+
+/// 取得进程的信号，没有时创建
+fn signals_of(process: ProcessID) -> Arc<Signals> {
+    SIGNALS.get().entry(process).or_default().clone()
+}
+
+/// 向进程发送信号，同一个信号在被取走之前多次发送只记一次
+pub fn send_signal(process: ProcessID, signal: usize) {
+    let signals = signals_of(process);
+    *signals.pending.get() |= 1 << signal;
+    signals.condvar.notify_all();
+}
+
+/// 进程结束，丢弃它的待处理信号
+pub(super) fn clear_signals(process: ProcessID) {
+    SIGNALS.get().remove(&process);
+}
+
+/// 返回当前进程的待处理信号，第 n 位表示信号 n
+pub(super) fn sys_sigpending() -> SyscallResult {
+    let process = PROCESSOR.get().current_thread().process.read().id;
+    let signals = SIGNALS.get().get(&process).cloned();
+    let pending = signals.map_or(0, |signals| *signals.pending.get());
+    SyscallResult::Proceed(pending as isize)
+}
+
+/// 等待 `mask` 中的任何一个信号，最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 取走编号最小的待处理信号并返回其编号，超时返回 -1
+pub(super) fn sys_sigtimedwait(mask: usize, timeout: usize) -> SyscallResult {
+    let process = PROCESSOR.get().current_thread().process.read().id;
+    let deadline = time::read() + us_to_ticks(timeout);
+    let signals = signals_of(process);
+    let mut pending = signals.pending.get();
+    loop {
+        if *pending & mask != 0 {
+            let signal = (*pending & mask).trailing_zeros() as usize;
+            *pending &= !(1 << signal);
+            return SyscallResult::Proceed(signal as isize);
+        }
+        if timeout == 0 {
+            pending = signals.condvar.wait(pending);
+        } else {
+            let now = time::read();
+            if now >= deadline {
+                return SyscallResult::Proceed(-1);
+            }
+            pending = signals
+                .condvar
+                .wait_timeout(pending, ticks_to_us(deadline - now))
+                .0;
+        }
+    }
+}
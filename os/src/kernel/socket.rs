@@ -0,0 +1,133 @@
+//! 本地套接字相关的内核功能
+
+use super::*;
+use crate::fs::*;
+use core::slice::{from_raw_parts, from_raw_parts_mut};
+use core::str::from_utf8;
+
+/// 本地（Unix 域）套接字，目前唯一支持的协议族
+pub const AF_UNIX: usize = 1;
+/// 流式套接字
+pub const SOCK_STREAM: usize = 1;
+/// 数据报套接字
+pub const SOCK_DGRAM: usize = 2;
+
+/// 创建一个套接字，返回文件描述符
+///
+/// 只支持 [`AF_UNIX`]，出现错误返回 -1
+pub(super) fn sys_socket(domain: usize, socket_type: usize) -> SyscallResult {
+    let socket_type = match (domain, socket_type) {
+        (AF_UNIX, SOCK_STREAM) => SocketType::Stream,
+        (AF_UNIX, SOCK_DGRAM) => SocketType::Datagram,
+        _ => return SyscallResult::Proceed(-1),
+    };
+    let fd = PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .write()
+        .add_descriptor(Arc::new(UnixSocket::new(socket_type)));
+    SyscallResult::Proceed(fd as isize)
+}
+
+/// 取出文件描述符对应的套接字
+fn find_socket(fd: usize) -> Option<Arc<dyn INode>> {
+    PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+        .filter(|inode| inode.as_any_ref().is::<UnixSocket>())
+}
+
+/// 对 `fd` 对应的套接字执行 `f`，并将结果转换为返回值，出现错误返回 -1
+///
+/// `f` 可能休眠，执行时不持有进程的锁
+fn with_socket(fd: usize, f: impl FnOnce(&UnixSocket) -> Result<usize>) -> SyscallResult {
+    let inode = match find_socket(fd) {
+        Some(inode) => inode,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let socket = inode.as_any_ref().downcast_ref::<UnixSocket>().unwrap();
+    match f(socket) {
+        Ok(ret) => SyscallResult::Proceed(ret as isize),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 从用户空间读取路径
+fn read_path<'a>(path: *const u8, length: usize) -> Result<&'a str> {
+    from_utf8(unsafe { from_raw_parts(path, length) }).map_err(|_| FsError::InvalidParam)
+}
+
+/// 将套接字绑定到路径上
+///
+/// 出现错误返回 -1
+pub(super) fn sys_bind(fd: usize, path: *const u8, length: usize) -> SyscallResult {
+    with_socket(fd, |socket| {
+        socket.bind(read_path(path, length)?)?;
+        Ok(0)
+    })
+}
+
+/// 开始接受连接，最多排队 `backlog` 个
+///
+/// 出现错误返回 -1
+pub(super) fn sys_listen(fd: usize, backlog: usize) -> SyscallResult {
+    with_socket(fd, |socket| {
+        socket.listen(backlog)?;
+        Ok(0)
+    })
+}
+
+/// 取出一个连接，没有时休眠等待，返回新连接的文件描述符
+///
+/// 出现错误返回 -1
+pub(super) fn sys_accept(fd: usize) -> SyscallResult {
+    with_socket(fd, |socket| {
+        let connection = socket.accept()?;
+        Ok(PROCESSOR
+            .get()
+            .current_thread()
+            .process
+            .write()
+            .add_descriptor(connection))
+    })
+}
+
+/// 连接到绑定在路径上的套接字
+///
+/// 出现错误返回 -1
+pub(super) fn sys_connect(fd: usize, path: *const u8, length: usize) -> SyscallResult {
+    with_socket(fd, |socket| {
+        socket.connect(read_path(path, length)?)?;
+        Ok(0)
+    })
+}
+
+/// 通过套接字发送数据，返回发送的长度
+///
+/// 出现错误返回 -1
+pub(super) fn sys_send(fd: usize, buffer: *const u8, size: usize) -> SyscallResult {
+    let buffer = unsafe { from_raw_parts(buffer, size) };
+    with_socket(fd, |socket| socket.send(buffer))
+}
+
+/// 通过套接字接收数据，返回接收的长度，流式套接字的对方关闭后返回 0
+///
+/// 出现错误返回 -1
+pub(super) fn sys_recv(fd: usize, buffer: *mut u8, size: usize) -> SyscallResult {
+    let buffer = unsafe { from_raw_parts_mut(buffer, size) };
+    with_socket(fd, |socket| socket.recv(buffer))
+}
+
+/// 删除套接字绑定的路径，之后可以再次绑定，已经建立的连接不受影响
+///
+/// 目前只有套接字绑定的路径可以删除，见 [`unlink`]。路径没有绑定时返回 -1
+pub(super) fn sys_unlink(path: *const u8, length: usize) -> SyscallResult {
+    match read_path(path, length).and_then(unlink) {
+        Ok(()) => SyscallResult::Proceed(0),
+        Err(_) => SyscallResult::Proceed(-1),
+    }
+}
@@ -0,0 +1,84 @@
+//! 死锁检测
+//!
+//! 会休眠的原语在获得、释放和等待资源时维护一张等待图（wait-for graph）：
+//! 线程指向它正在等待的资源，资源指向持有它的线程。
+//! 线程开始等待时，从它出发沿着等待图查找，如果能回到它自己，说明出现了死锁，打印环上的线程和资源。
+//!
+//! 信号量可能由没有获得它的线程释放（例如生产者-消费者），这时从最早的持有者中扣除，
+//! 等待图只是近似，报告仅供调试参考。
+
+use super::*;
+use algorithm::WaitForGraph;
+use lazy_static::*;
+
+/// 等待图中的资源，以同步原语的地址区分
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Resource {
+    /// 原语的地址
+    pub address: usize,
+    /// 原语的类型，用于报告
+    pub kind: &'static str,
+}
+
+impl Resource {
+    /// 以 `primitive` 的地址作为资源
+    pub fn of<T>(primitive: &T, kind: &'static str) -> Self {
+        Self {
+            address: primitive as *const T as usize,
+            kind,
+        }
+    }
+}
+
+lazy_static! {
+    /// 全局的等待图
+    static ref GRAPH: Lock<WaitForGraph<ThreadID, Resource>> = Lock::new(WaitForGraph::default());
+}
+
+/// 当前线程获得了 `resource`，同时不再等待
+pub fn acquired(resource: Resource) {
+    let id = PROCESSOR.get().current_thread().id;
+    GRAPH.get().acquire(id, resource);
+}
+
+/// 当前线程释放了 `resource`
+///
+/// 当前线程没有持有时，视为替最早的持有者释放
+pub fn released(resource: Resource) {
+    let id = PROCESSOR.get().current_thread().id;
+    GRAPH.get().release(Some(id), resource);
+}
+
+/// 当前线程即将休眠等待 `resource`，如果因此形成环则打印报告
+///
+/// 返回是否检测到了死锁
+pub fn waiting(resource: Resource) -> bool {
+    let id = PROCESSOR.get().current_thread().id;
+    let mut graph = GRAPH.get();
+    match graph.wait(id, resource) {
+        Some(path) => {
+            report(&graph, &path);
+            true
+        }
+        None => false,
+    }
+}
+
+/// 线程结束时移除它在等待图中的记录，之后它不会再出现在报告中
+pub fn forget(thread: ThreadID) {
+    GRAPH.get().forget(thread);
+}
+
+/// 打印环上的线程和资源
+fn report(graph: &WaitForGraph<ThreadID, Resource>, path: &[(ThreadID, Resource)]) {
+    println!("deadlock detected:");
+    for (thread, resource) in path.iter() {
+        println!(
+            "  thread {} waits for {} {:#x} held by threads {:?}",
+            thread,
+            resource.kind,
+            resource.address,
+            graph.holders(resource)
+        );
+    }
+}
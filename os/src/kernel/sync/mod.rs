@@ -0,0 +1,26 @@
+//! 会休眠的同步原语
+//!
+//! `spin::Mutex` 和关闭中断的 [`Lock`] 在竞争时都会忙等，只适合很短的临界区。
+//! 这里的原语基于 [`Condvar`]：得不到资源的线程会休眠，由释放资源的线程唤醒，
+//! 因此可以在内核线程和系统调用中长时间持有，但不能在没有当前线程的中断处理中使用。
+//!
+//! - [`Mutex`]：互斥锁，记录持有者，等待者将调度份额转让给它
+//! - [`Semaphore`]：计数信号量
+//! - [`RwLock`]：写者优先的读写锁
+//!
+//! 这些原语都会维护 [`deadlock`] 中的等待图，线程开始等待时检测死锁
+//!
+//! [`Lock`]: crate::process::Lock
+
+pub mod deadlock;
+mod mutex;
+mod rwlock;
+mod semaphore;
+
+use super::*;
+
+use deadlock::Resource;
+
+pub use mutex::{Mutex, MutexGuard};
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+pub use semaphore::Semaphore;
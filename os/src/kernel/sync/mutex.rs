@@ -0,0 +1,90 @@
+//! 会休眠的互斥锁 [`Mutex`]
+
+use super::*;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// 会休眠的互斥锁
+///
+/// 锁被占用时，当前线程休眠等待，并将调度份额转让给持有者（见 [`Condvar::wait_for`]）
+pub struct Mutex<T> {
+    /// 持有锁的线程，空闲时为 `None`
+    owner: Lock<Option<Arc<Thread>>>,
+    /// 等待锁的线程
+    condvar: Condvar,
+    /// 被保护的数据
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// [`Mutex`] 的守卫，drop 时释放锁
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+#[allow(unused)]
+impl<T> Mutex<T> {
+    /// 创建一个新的互斥锁
+    pub fn new(data: T) -> Self {
+        Self {
+            owner: Lock::new(None),
+            condvar: Condvar::default(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 获得锁，锁被占用时休眠等待
+    pub fn lock(&self) -> MutexGuard<T> {
+        let current_thread = PROCESSOR.get().current_thread();
+        let mut owner = self.owner.get();
+        while let Some(holder) = owner.clone() {
+            deadlock::waiting(Resource::of(self, "mutex"));
+            owner = self.condvar.wait_for(&holder, owner);
+        }
+        *owner = Some(current_thread);
+        deadlock::acquired(Resource::of(self, "mutex"));
+        MutexGuard { mutex: self }
+    }
+
+    /// 尝试获得锁，锁被占用时返回 `None`
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let current_thread = PROCESSOR.get().current_thread();
+        let mut owner = self.owner.get();
+        if owner.is_some() {
+            return None;
+        }
+        *owner = Some(current_thread);
+        deadlock::acquired(Resource::of(self, "mutex"));
+        Some(MutexGuard { mutex: self })
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// 释放锁，并唤醒一个等待者
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        *self.mutex.owner.get() = None;
+        deadlock::released(Resource::of(self.mutex, "mutex"));
+        self.mutex.condvar.notify_one();
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
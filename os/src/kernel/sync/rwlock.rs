@@ -0,0 +1,136 @@
+//! 写者优先的读写锁 [`RwLock`]
+
+use super::*;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// 读写锁的状态
+#[derive(Default)]
+struct State {
+    /// 持有读锁的线程数量
+    readers: usize,
+    /// 是否有线程持有写锁
+    writer: bool,
+    /// 正在等待写锁的线程数量
+    waiting_writers: usize,
+}
+
+/// 写者优先的读写锁
+///
+/// 可以有多个读者，或者一个写者。只要有写者在等待，新的读者就需要等待，避免写者饥饿
+pub struct RwLock<T> {
+    /// 读者和写者的状态
+    state: Lock<State>,
+    /// 等待读锁的线程
+    readers: Condvar,
+    /// 等待写锁的线程
+    writers: Condvar,
+    /// 被保护的数据
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+/// [`RwLock`] 的读锁守卫，drop 时释放读锁
+pub struct RwLockReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/// [`RwLock`] 的写锁守卫，drop 时释放写锁
+pub struct RwLockWriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+impl<T> RwLock<T> {
+    /// 创建一个新的读写锁
+    pub fn new(data: T) -> Self {
+        Self {
+            state: Lock::new(State::default()),
+            readers: Condvar::default(),
+            writers: Condvar::default(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 获得读锁，有写者持有或者等待时休眠等待
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        let mut state = self.state.get();
+        while state.writer || state.waiting_writers > 0 {
+            deadlock::waiting(Resource::of(self, "rwlock"));
+            state = self.readers.wait(state);
+        }
+        state.readers += 1;
+        deadlock::acquired(Resource::of(self, "rwlock"));
+        RwLockReadGuard { lock: self }
+    }
+
+    /// 获得写锁，有其他读者或者写者持有时休眠等待
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        let mut state = self.state.get();
+        state.waiting_writers += 1;
+        while state.writer || state.readers > 0 {
+            deadlock::waiting(Resource::of(self, "rwlock"));
+            state = self.writers.wait(state);
+        }
+        state.waiting_writers -= 1;
+        state.writer = true;
+        deadlock::acquired(Resource::of(self, "rwlock"));
+        RwLockWriteGuard { lock: self }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// 最后一个读者离开时，唤醒一个写者
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.get();
+        state.readers -= 1;
+        deadlock::released(Resource::of(self.lock, "rwlock"));
+        if state.readers == 0 {
+            drop(state);
+            self.lock.writers.notify_one();
+        }
+    }
+}
+
+/// 写者离开时，优先唤醒下一个写者，没有写者在等待时唤醒所有读者
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.get();
+        state.writer = false;
+        deadlock::released(Resource::of(self.lock, "rwlock"));
+        let writer_waiting = state.waiting_writers > 0;
+        drop(state);
+        if writer_waiting {
+            self.lock.writers.notify_one();
+        } else {
+            self.lock.readers.notify_all();
+        }
+    }
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
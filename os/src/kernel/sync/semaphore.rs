@@ -0,0 +1,90 @@
+//! 计数信号量 [`Semaphore`]
+
+use super::*;
+
+/// 计数信号量
+///
+/// 计数为 0 时，[`Semaphore::acquire`] 休眠等待，直到其他线程调用 [`Semaphore::release`]
+#[derive(Default)]
+pub struct Semaphore {
+    /// 剩余的计数
+    count: Lock<usize>,
+    /// 等待计数的线程
+    condvar: Condvar,
+}
+
+#[allow(unused)]
+impl Semaphore {
+    /// 创建一个计数为 `count` 的信号量
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: Lock::new(count),
+            condvar: Condvar::default(),
+        }
+    }
+
+    /// 计数减 1，计数为 0 时休眠等待（P 操作）
+    pub fn acquire(&self) {
+        self.acquire_n(1);
+    }
+
+    /// 计数一次性减 `n`，计数不足 `n` 时休眠等待，期间不会取走任何计数
+    pub fn acquire_n(&self, n: usize) {
+        let mut count = self.count.get();
+        while *count < n {
+            deadlock::waiting(Resource::of(self, "semaphore"));
+            count = self.condvar.wait(count);
+        }
+        *count -= n;
+        for _ in 0..n {
+            deadlock::acquired(Resource::of(self, "semaphore"));
+        }
+    }
+
+    /// 尝试将计数减 1，计数为 0 时返回 `false`
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_n(1)
+    }
+
+    /// 尝试将计数一次性减 `n`，计数不足 `n` 时不做修改并返回 `false`
+    pub fn try_acquire_n(&self, n: usize) -> bool {
+        let mut count = self.count.get();
+        if *count < n {
+            return false;
+        }
+        *count -= n;
+        for _ in 0..n {
+            deadlock::acquired(Resource::of(self, "semaphore"));
+        }
+        true
+    }
+
+    /// 计数加 1，并唤醒等待者（V 操作）
+    pub fn release(&self) {
+        self.release_n(1);
+    }
+
+    /// 计数加 `n`，并唤醒等待者
+    ///
+    /// 等待者需要的数量可能各不相同，因此唤醒所有等待者，由它们各自重新检查
+    pub fn release_n(&self, n: usize) {
+        *self.count.get() += n;
+        for _ in 0..n {
+            deadlock::released(Resource::of(self, "semaphore"));
+        }
+        self.condvar.notify_all();
+    }
+
+    /// 计数加 `n`，并唤醒等待者，不修改等待图
+    ///
+    /// 用于收回已经结束的进程获得的计数，它们在等待图中的记录随线程结束移除（见 [`deadlock::forget`]）
+    pub fn reclaim(&self, n: usize) {
+        *self.count.get() += n;
+        self.condvar.notify_all();
+    }
+
+    /// 当前的计数
+    pub fn count(&self) -> usize {
+        *self.count.get()
+    }
+}
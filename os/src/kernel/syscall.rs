@@ -3,16 +3,52 @@
 use super::*;
 use alloc::{format, string::String};
 
+pub const SYS_DUP: usize = 23;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE: usize = 59;
 pub const SYS_READ: usize = 63;
 pub const SYS_WRITE: usize = 64;
+/// 编号与 Linux 的 `pselect6` 相同，参数不同，见 [`sys_select`]
+pub const SYS_PSELECT: usize = 72;
+/// 编号与 Linux 的 `ppoll` 相同，参数不同，见 [`sys_poll`]
+pub const SYS_PPOLL: usize = 73;
+pub const SYS_TIMERFD_CREATE: usize = 85;
+pub const SYS_TIMERFD_SETTIME: usize = 86;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_FUTEX: usize = 98;
+pub const SYS_GETITIMER: usize = 102;
+pub const SYS_SETITIMER: usize = 103;
+pub const SYS_RT_SIGPENDING: usize = 136;
+pub const SYS_RT_SIGTIMEDWAIT: usize = 137;
+pub const SYS_SET_PRIORITY: usize = 140;
+pub const SYS_GET_PRIORITY: usize = 141;
+pub const SYS_GETRUSAGE: usize = 165;
+pub const SYS_MQ_OPEN: usize = 180;
+pub const SYS_MQ_UNLINK: usize = 181;
+pub const SYS_MQ_TIMEDSEND: usize = 182;
+pub const SYS_MQ_TIMEDRECEIVE: usize = 183;
+pub const SYS_SEMGET: usize = 190;
+pub const SYS_SEMCLAIM: usize = 191;
+pub const SYS_SEMOP: usize = 193;
+pub const SYS_SHMGET: usize = 194;
+pub const SYS_SHMAT: usize = 196;
+pub const SYS_SHMDT: usize = 197;
+pub const SYS_SOCKET: usize = 198;
+pub const SYS_BIND: usize = 200;
+pub const SYS_LISTEN: usize = 201;
+pub const SYS_ACCEPT: usize = 202;
+pub const SYS_CONNECT: usize = 203;
+pub const SYS_SENDTO: usize = 206;
+pub const SYS_RECVFROM: usize = 207;
+pub const SYS_SCHED_SETATTR: usize = 274;
+/// 以下是本内核自己的系统调用，编号不与 Linux 重叠
+pub const SYS_SPAWN: usize = 400;
+pub const SYS_UNLINK: usize = 401;
 
 /// 系统调用在内核之内的返回值
 pub(super) enum SyscallResult {
     /// 继续执行，带返回值
     Proceed(isize),
-    /// 记录返回值，但暂存当前线程
-    Park(isize),
     /// 丢弃当前 context，调度下一个线程继续执行
     Kill,
 }
@@ -23,12 +59,71 @@ pub fn syscall_handler(context: &mut Context) -> Result<*mut Context, String> {
     context.sepc += 4;
 
     let syscall_id = context.x[17];
-    let args = [context.x[10], context.x[11], context.x[12]];
+    let args = [
+        context.x[10],
+        context.x[11],
+        context.x[12],
+        context.x[13],
+        context.x[14],
+    ];
 
     let result = match syscall_id {
+        SYS_DUP => sys_dup(args[0]),
+        SYS_CLOSE => sys_close(args[0]),
+        SYS_PIPE => sys_pipe(args[0] as *mut usize),
         SYS_READ => sys_read(args[0], args[1] as *mut u8, args[2]),
         SYS_WRITE => sys_write(args[0], args[1] as *mut u8, args[2]),
+        SYS_PSELECT => sys_select(
+            args[0] as *mut usize,
+            args[1] as *mut usize,
+            args[2] as isize,
+        ),
+        SYS_PPOLL => sys_poll(args[0] as *mut PollFd, args[1], args[2] as isize),
+        SYS_TIMERFD_CREATE => sys_timerfd_create(),
+        SYS_TIMERFD_SETTIME => sys_timerfd_settime(
+            args[0],
+            args[1] as *const ITimerVal,
+            args[2] as *mut ITimerVal,
+        ),
         SYS_EXIT => sys_exit(args[0]),
+        SYS_FUTEX => sys_futex(args[0], args[1], args[2]),
+        SYS_GETITIMER => sys_getitimer(args[0], args[1] as *mut ITimerVal),
+        SYS_SETITIMER => sys_setitimer(
+            args[0],
+            args[1] as *const ITimerVal,
+            args[2] as *mut ITimerVal,
+        ),
+        SYS_RT_SIGPENDING => sys_sigpending(),
+        SYS_RT_SIGTIMEDWAIT => sys_sigtimedwait(args[0], args[1]),
+        SYS_SET_PRIORITY => sys_set_priority(args[0], args[1]),
+        SYS_GET_PRIORITY => sys_get_priority(args[0]),
+        SYS_GETRUSAGE => sys_getrusage(args[0], args[1] as *mut Rusage),
+        SYS_MQ_OPEN => sys_mq_open(args[0] as *const u8, args[1], args[2], args[3], args[4]),
+        SYS_MQ_UNLINK => sys_mq_unlink(args[0] as *const u8, args[1]),
+        SYS_MQ_TIMEDSEND => sys_mq_send(args[0], args[1] as *const u8, args[2], args[3], args[4]),
+        SYS_MQ_TIMEDRECEIVE => sys_mq_receive(
+            args[0],
+            args[1] as *mut u8,
+            args[2],
+            args[3] as *mut usize,
+            args[4],
+        ),
+        SYS_SEMGET => sys_semget(args[0], args[1]),
+        SYS_SEMCLAIM => sys_semclaim(args[0], args[1]),
+        SYS_SEMOP => sys_semop(args[0], args[1] as isize),
+        SYS_SHMGET => sys_shmget(args[0], args[1]),
+        SYS_SHMAT => sys_shmat(args[0]),
+        SYS_SHMDT => sys_shmdt(args[0]),
+        SYS_SOCKET => sys_socket(args[0], args[1]),
+        SYS_BIND => sys_bind(args[0], args[1] as *const u8, args[2]),
+        SYS_LISTEN => sys_listen(args[0], args[1]),
+        SYS_ACCEPT => sys_accept(args[0]),
+        SYS_CONNECT => sys_connect(args[0], args[1] as *const u8, args[2]),
+        SYS_SENDTO => sys_send(args[0], args[1] as *const u8, args[2]),
+        SYS_RECVFROM => sys_recv(args[0], args[1] as *mut u8, args[2]),
+        SYS_SCHED_SETATTR => sys_sched_setattr(args[0], args[1] as *const RealtimeAttr),
+        SYS_SPAWN => sys_spawn(args[0] as *const u8, args[1], args[2]),
+        SYS_UNLINK => sys_unlink(args[0] as *const u8, args[1]),
         _ => return Err(format!("unimplemented syscall: {}", syscall_id)),
     };
 
@@ -38,17 +133,9 @@ pub fn syscall_handler(context: &mut Context) -> Result<*mut Context, String> {
             context.x[10] = ret as usize;
             context
         }
-        SyscallResult::Park(ret) => {
-            // 将返回值放入 context 中
-            context.x[10] = ret as usize;
-            // 保存 context，准备下一个线程
-            PROCESSOR.get().park_current_thread(context);
-            PROCESSOR.get().prepare_next_thread()
-        }
         SyscallResult::Kill => {
             // 终止，跳转到 PROCESSOR 调度的下一个线程
-            PROCESSOR.get().kill_current_thread();
-            PROCESSOR.get().prepare_next_thread()
+            PROCESSOR.exit_current_thread()
         }
     })
 }
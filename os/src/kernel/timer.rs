@@ -0,0 +1,265 @@
+//! 间隔定时器和定时器文件
+//!
+//! - [`ITIMER_REAL`]：按实际时间计时，放入 [`interrupt`] 的定时器队列，到期时发送 [`SIGALRM`]
+//! - [`ITIMER_VIRTUAL`] 和 [`ITIMER_PROF`]：按进程的执行时间计时，在每次统计执行时间时扣除
+//!   （见 [`charge_cpu_time`]），到期时发送 [`SIGVTALRM`] 或 [`SIGPROF`]
+//! - 定时器文件 [`TimerFd`]：到期时可读，读取或 poll 的线程被唤醒
+//!
+//! 时间的单位都是微秒
+//!
+//! [`interrupt`]: crate::interrupt
+
+use super::*;
+use crate::fs::*;
+use crate::lockdep::RwLock;
+use alloc::{collections::BTreeMap, sync::Weak};
+use lazy_static::*;
+use riscv::register::time;
+
+/// 按实际时间计时
+pub const ITIMER_REAL: usize = 0;
+/// 按进程在用户态执行的时间计时
+pub const ITIMER_VIRTUAL: usize = 1;
+/// 按进程在用户态和内核态执行的时间计时
+pub const ITIMER_PROF: usize = 2;
+
+/// 间隔定时器的设置
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct ITimerVal {
+    /// 到期后重新开始计时的间隔，为 0 表示只触发一次
+    interval: usize,
+    /// 距离到期的时间，为 0 表示没有启动
+    value: usize,
+}
+
+/// 启动的 [`ITIMER_REAL`]，时间的单位为 `time` 寄存器的计数
+struct RealTimer {
+    /// 所属的进程，结束之后不再触发
+    process: Weak<RwLock<Process>>,
+    /// 到期时间
+    deadline: usize,
+    /// 到期后重新开始计时的间隔
+    interval: usize,
+    /// 定时器队列中的事件
+    timer: TimerID,
+}
+
+lazy_static! {
+    /// 所有启动的 [`ITIMER_REAL`]，以进程索引
+    ///
+    /// 在时钟中断中访问，使用关闭中断的 [`Lock`]
+    static ref REAL_TIMERS: Lock<BTreeMap<ProcessID, RealTimer>> = Lock::new(BTreeMap::new());
+}
+
+/// 将 [`ITIMER_REAL`] 放入定时器队列，在 `deadline` 到期
+fn arm_real_timer(
+    timers: &mut BTreeMap<ProcessID, RealTimer>,
+    id: ProcessID,
+    process: Weak<RwLock<Process>>,
+    deadline: usize,
+    interval: usize,
+) {
+    let timer = add_timer(deadline, move || fire_real_timer(id, deadline));
+    timers.insert(
+        id,
+        RealTimer {
+            process,
+            deadline,
+            interval,
+            timer,
+        },
+    );
+}
+
+/// 进程结束，停止它的 [`ITIMER_REAL`]
+pub(super) fn cancel_real_timer(id: ProcessID) {
+    if let Some(timer) = REAL_TIMERS.get().remove(&id) {
+        cancel_timer(timer.timer);
+    }
+}
+
+/// [`ITIMER_REAL`] 在时钟中断中到期，发送 [`SIGALRM`]，周期性的定时器再次放入队列
+fn fire_real_timer(id: ProcessID, deadline: usize) {
+    let mut timers = REAL_TIMERS.get();
+    let timer = match timers.remove(&id) {
+        // 已经被重新设置
+        Some(timer) if timer.deadline != deadline => {
+            timers.insert(id, timer);
+            return;
+        }
+        Some(timer) => timer,
+        None => return,
+    };
+    if timer.process.strong_count() == 0 {
+        return;
+    }
+    if timer.interval > 0 {
+        let next = deadline + timer.interval;
+        arm_real_timer(&mut timers, id, timer.process, next, timer.interval);
+    }
+    drop(timers);
+    send_signal(id, SIGALRM);
+}
+
+/// 进程在用户态执行了 `user_time`、在内核态执行了 `system_time`（`time` 寄存器的计数），
+/// 从 [`ITIMER_VIRTUAL`] 和 [`ITIMER_PROF`] 中扣除
+///
+/// 由中断处理在返回用户态之前调用，每次中断只为进程上一次锁，
+/// 因此内核态的时间只包括处理中断和系统调用的时间
+pub fn charge_cpu_time(process: &Arc<RwLock<Process>>, user_time: usize, system_time: usize) {
+    let (id, virtual_expired, profiling_expired) = {
+        let mut process = process.write();
+        let virtual_expired = process.virtual_timer.charge(user_time);
+        let profiling_expired = process.profiling_timer.charge(user_time + system_time);
+        (process.id, virtual_expired, profiling_expired)
+    };
+    if virtual_expired {
+        send_signal(id, SIGVTALRM);
+    }
+    if profiling_expired {
+        send_signal(id, SIGPROF);
+    }
+}
+
+/// 读取当前进程的间隔定时器 `which`
+fn get_itimer(process: &Arc<RwLock<Process>>, which: usize) -> Option<ITimerVal> {
+    let cpu_timer = |timer: &CpuTimer| ITimerVal {
+        interval: ticks_to_us(timer.interval),
+        value: ticks_to_us(timer.remaining),
+    };
+    match which {
+        ITIMER_REAL => {
+            let id = process.read().id;
+            let now = time::read();
+            Some(
+                REAL_TIMERS
+                    .get()
+                    .get(&id)
+                    .map_or(ITimerVal::default(), |timer| ITimerVal {
+                        interval: ticks_to_us(timer.interval),
+                        value: ticks_to_us(timer.deadline.saturating_sub(now)),
+                    }),
+            )
+        }
+        ITIMER_VIRTUAL => Some(cpu_timer(&process.read().virtual_timer)),
+        ITIMER_PROF => Some(cpu_timer(&process.read().profiling_timer)),
+        _ => None,
+    }
+}
+
+/// 将 `old` 指向的位置写入间隔定时器 `which` 的设置
+///
+/// `old` 不可写或者出现其他错误时返回 -1
+pub(super) fn sys_getitimer(which: usize, old: *mut ITimerVal) -> SyscallResult {
+    if !user_accessible(old, 1, true) {
+        return SyscallResult::Proceed(-1);
+    }
+    let process = PROCESSOR.get().current_thread().process.clone();
+    match get_itimer(&process, which) {
+        Some(value) => {
+            unsafe { *old = value };
+            SyscallResult::Proceed(0)
+        }
+        None => SyscallResult::Proceed(-1),
+    }
+}
+
+/// 按 `new` 设置间隔定时器 `which`，`value` 为 0 时停止；`old` 不为空时写入之前的设置
+///
+/// `new` 不可读、`old` 不可写或者出现其他错误时返回 -1
+pub(super) fn sys_setitimer(
+    which: usize,
+    new: *const ITimerVal,
+    old: *mut ITimerVal,
+) -> SyscallResult {
+    if !user_accessible(new, 1, false) || !(old.is_null() || user_accessible(old, 1, true)) {
+        return SyscallResult::Proceed(-1);
+    }
+    let process = PROCESSOR.get().current_thread().process.clone();
+    let previous = match get_itimer(&process, which) {
+        Some(previous) => previous,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let new = unsafe { *new };
+    let (value, interval) = (us_to_ticks(new.value), us_to_ticks(new.interval));
+    match which {
+        ITIMER_REAL => {
+            let id = process.read().id;
+            let mut timers = REAL_TIMERS.get();
+            if let Some(timer) = timers.remove(&id) {
+                cancel_timer(timer.timer);
+            }
+            if value > 0 {
+                let deadline = time::read() + value;
+                arm_real_timer(
+                    &mut timers,
+                    id,
+                    Arc::downgrade(&process),
+                    deadline,
+                    interval,
+                );
+            }
+        }
+        _ => {
+            let mut process = process.write();
+            let timer = if which == ITIMER_VIRTUAL {
+                &mut process.virtual_timer
+            } else {
+                &mut process.profiling_timer
+            };
+            *timer = CpuTimer {
+                remaining: value,
+                interval,
+            };
+        }
+    }
+    if !old.is_null() {
+        unsafe { *old = previous };
+    }
+    SyscallResult::Proceed(0)
+}
+
+/// 创建一个定时器文件，返回文件描述符
+pub(super) fn sys_timerfd_create() -> SyscallResult {
+    let fd = PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .write()
+        .add_descriptor(Arc::new(TimerFd::new()));
+    SyscallResult::Proceed(fd as isize)
+}
+
+/// 按 `new` 设置定时器文件，`value` 为 0 时停止；`old` 不为空时写入之前的设置
+///
+/// `new` 不可读、`old` 不可写或者出现其他错误时返回 -1
+pub(super) fn sys_timerfd_settime(
+    fd: usize,
+    new: *const ITimerVal,
+    old: *mut ITimerVal,
+) -> SyscallResult {
+    if !user_accessible(new, 1, false) || !(old.is_null() || user_accessible(old, 1, true)) {
+        return SyscallResult::Proceed(-1);
+    }
+    let inode = match PROCESSOR
+        .get()
+        .current_thread()
+        .process
+        .read()
+        .descriptor(fd)
+    {
+        Some(inode) => inode,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let timer_fd = match inode.as_any_ref().downcast_ref::<TimerFd>() {
+        Some(timer_fd) => timer_fd,
+        None => return SyscallResult::Proceed(-1),
+    };
+    let new = unsafe { *new };
+    let (value, interval) = timer_fd.set(new.value, new.interval);
+    if !old.is_null() {
+        unsafe { *old = ITimerVal { interval, value } };
+    }
+    SyscallResult::Proceed(0)
+}
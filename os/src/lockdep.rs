@@ -0,0 +1,398 @@
+//! 锁顺序检查（lockdep）
+//!
+//! 开启 `lockdep` feature 后（`make run FEATURES=lockdep`），[`Lock`] 以及这里包装 `spin` 的
+//! [`Mutex`] 和 [`RwLock`] 在上锁和释放时都会记录到本模块：
+//!
+//! - 每种锁按其类型（包括被保护数据的类型）归为一个锁类，例如所有线程的 `inner` 属于同一类
+//! - 每个 hart 记录当前持有的锁，以及上锁时的调用栈
+//! - 持有 A 类锁时获得 B 类锁，记录一条 A → B 的边。如果图中已经有从 B 到 A 的路径，
+//!   说明另一处代码以相反的顺序上锁，两个 hart 各走一边就会死锁
+//! - 持有某个锁时再次获得同一个锁（读锁除外），自旋锁一定会死锁
+//!
+//! 发现问题时打印两处上锁的调用栈后继续执行，同一对锁类只报告一次；递归获得自旋锁之后仍会真的死锁。
+//! 调用栈通过帧指针回溯（需要保留帧指针，`make` 在开启 feature 时自动加上编译参数），
+//! 只记录返回地址，可以用 `addr2line` 还原。
+//! 被抢占的内核线程持有的锁会算在 hart 上，因此报告仅供调试参考。
+//!
+//! 不开启 feature 时，记录函数直接返回，包装的锁与 `spin` 中的锁行为相同
+//!
+//! [`Lock`]: crate::process::Lock
+
+use crate::memory::KERNEL_MAP_OFFSET;
+use crate::smp::{hart_id, MAX_HARTS};
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    vec::Vec,
+};
+use core::any::type_name;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+
+/// 是否开启检查
+const ENABLED: bool = cfg!(feature = "lockdep");
+
+/// 调用栈最多记录的层数
+const TRACE_DEPTH: usize = 8;
+
+/// 锁类，使用锁的类型名
+pub type LockClass = &'static str;
+
+/// 上锁的方式
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Acquire {
+    /// 互斥地获得，会自旋等待
+    Exclusive,
+    /// 获得读锁，可以在持有时再次获得
+    Shared,
+    /// 通过 `try_lock` 等获得，不会等待，因此只记录持有而不检查顺序
+    Try,
+}
+
+/// 调用栈中的返回地址，未使用的部分为 0
+#[derive(Copy, Clone, Default)]
+struct Trace([usize; TRACE_DEPTH]);
+
+/// 一个被持有的锁
+struct HeldLock {
+    class: LockClass,
+    /// 锁的地址
+    instance: usize,
+    /// 是否为读锁
+    shared: bool,
+    /// 上锁时的调用栈
+    trace: Trace,
+}
+
+/// 检查发现的问题
+struct Violation {
+    message: &'static str,
+    /// 已经持有的锁
+    held: (LockClass, Trace),
+    /// 正在获得的锁
+    acquiring: (LockClass, Trace),
+    /// 以相反顺序上锁的调用栈
+    reversed: Option<Trace>,
+}
+
+lazy_static! {
+    /// 每个 hart 持有的锁
+    static ref HELD: Vec<spin::Mutex<Vec<HeldLock>>> =
+        (0..MAX_HARTS).map(|_| spin::Mutex::new(Vec::new())).collect();
+    /// 观察到的上锁顺序：(先获得的锁类, 后获得的锁类) → 第一次观察到时获得后者的调用栈
+    static ref ORDER: spin::Mutex<BTreeMap<(LockClass, LockClass), Trace>> =
+        spin::Mutex::new(BTreeMap::new());
+    /// 已经报告过的问题：(已经持有的锁类, 正在获得的锁类)
+    static ref REPORTED: spin::Mutex<BTreeSet<(LockClass, LockClass)>> =
+        spin::Mutex::new(BTreeSet::new());
+}
+
+/// 正在打印报告的 hart，打印时上锁不再记录，避免再次触发；没有时为 `usize::MAX`
+static REPORTING_HART: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// 是否记录当前 hart 上的上锁和释放
+fn recording() -> bool {
+    ENABLED && REPORTING_HART.load(Ordering::Relaxed) != hart_id()
+}
+
+/// 即将获得 `instance` 处的 `class` 类锁，在自旋等待之前调用，这样真的死锁时也能给出报告
+pub fn acquire(class: LockClass, instance: usize, acquire: Acquire) {
+    if !recording() {
+        return;
+    }
+    let trace = Trace::capture();
+    let violation = without_interrupts(|| {
+        let mut held = HELD[hart_id()].lock();
+        let violation = match acquire {
+            Acquire::Try => None,
+            _ => check(&held, class, instance, acquire == Acquire::Shared, trace),
+        };
+        // 发现问题时同样记录持有，继续执行之后的释放才能对应上
+        held.push(HeldLock {
+            class,
+            instance,
+            shared: acquire != Acquire::Exclusive,
+            trace,
+        });
+        violation
+    });
+    if let Some(violation) = violation {
+        report(violation);
+    }
+}
+
+/// 释放了 `instance` 处的锁
+pub fn release(instance: usize) {
+    if !recording() {
+        return;
+    }
+    without_interrupts(|| {
+        let mut held = HELD[hart_id()].lock();
+        // 锁不一定按获得的相反顺序释放
+        if let Some(index) = held.iter().rposition(|lock| lock.instance == instance) {
+            held.remove(index);
+        }
+    });
+}
+
+/// 检查在持有 `held` 时获得锁是否会出现问题，没有问题时记录新的上锁顺序
+fn check(
+    held: &[HeldLock],
+    class: LockClass,
+    instance: usize,
+    shared: bool,
+    trace: Trace,
+) -> Option<Violation> {
+    if let Some(lock) = held
+        .iter()
+        .find(|lock| lock.instance == instance && !(lock.shared && shared))
+    {
+        return Some(Violation {
+            message: "recursive acquisition",
+            held: (lock.class, lock.trace),
+            acquiring: (class, trace),
+            reversed: None,
+        });
+    }
+    let mut order = ORDER.lock();
+    for lock in held.iter().filter(|lock| lock.class != class) {
+        if let Some(reversed) = find_path(&order, class, lock.class) {
+            return Some(Violation {
+                message: "lock order inversion",
+                held: (lock.class, lock.trace),
+                acquiring: (class, trace),
+                reversed: Some(reversed),
+            });
+        }
+        order.entry((lock.class, class)).or_insert(trace);
+    }
+    None
+}
+
+/// 在上锁顺序图中寻找从 `from` 到 `to` 的路径，返回路径上最后一条边记录的调用栈
+fn find_path(
+    order: &BTreeMap<(LockClass, LockClass), Trace>,
+    from: LockClass,
+    to: LockClass,
+) -> Option<Trace> {
+    let mut visited = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    while let Some(class) = queue.pop_front() {
+        let edges = order
+            .range((class, "")..)
+            .take_while(|((first, _), _)| *first == class);
+        for ((_, next), trace) in edges {
+            if *next == to {
+                return Some(*trace);
+            }
+            if visited.insert(*next) {
+                queue.push_back(*next);
+            }
+        }
+    }
+    None
+}
+
+/// 打印两处上锁的调用栈，同一对锁类只打印一次
+fn report(violation: Violation) {
+    let first = without_interrupts(|| {
+        REPORTED
+            .lock()
+            .insert((violation.held.0, violation.acquiring.0))
+    });
+    if !first {
+        return;
+    }
+    REPORTING_HART.store(hart_id(), Ordering::Relaxed);
+    println!("lockdep: {} on hart {}", violation.message, hart_id());
+    println!("  holding {}", violation.held.0);
+    println!("    acquired at {}", violation.held.1);
+    println!("  acquiring {}", violation.acquiring.0);
+    println!("    at {}", violation.acquiring.1);
+    if let Some(reversed) = violation.reversed {
+        println!(
+            "  but {} was acquired after {} at {}",
+            violation.held.0, violation.acquiring.0, reversed
+        );
+    }
+    REPORTING_HART.store(usize::MAX, Ordering::Relaxed);
+}
+
+impl Trace {
+    /// 沿着帧指针回溯当前的调用栈，需要编译时保留帧指针
+    fn capture() -> Self {
+        let mut trace = Self::default();
+        let mut fp: usize;
+        unsafe { llvm_asm!("mv $0, s0" : "=r"(fp) ::: "volatile") };
+        for slot in trace.0.iter_mut() {
+            // 离开内核栈（例如到达 trap 入口保存的用户态 s0）时停止
+            if fp < KERNEL_MAP_OFFSET || fp % 8 != 0 {
+                break;
+            }
+            // RISC-V 的栈帧中，fp - 8 处为返回地址，fp - 16 处为上一个帧指针
+            let (ra, prev) = unsafe { (*((fp - 8) as *const usize), *((fp - 16) as *const usize)) };
+            *slot = ra;
+            if prev <= fp {
+                break;
+            }
+            fp = prev;
+        }
+        trace
+    }
+}
+
+impl core::fmt::Display for Trace {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for address in self.0.iter().take_while(|address| **address != 0) {
+            write!(f, "{:#x} ", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// 关闭中断执行 `f`，避免中断处理中上锁时在同一个 hart 上重入
+fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let sstatus: usize;
+    unsafe { llvm_asm!("csrrci $0, sstatus, 1 << 1" : "=r"(sstatus) ::: "volatile") };
+    let result = f();
+    unsafe { llvm_asm!("csrs sstatus, $0" :: "r"(sstatus & 2) :: "volatile") };
+    result
+}
+
+/// 记录到 lockdep 的 `spin::Mutex`
+#[derive(Default)]
+pub struct Mutex<T>(spin::Mutex<T>);
+
+/// [`Mutex`] 的守卫，drop 时记录释放
+pub struct MutexGuard<'a, T> {
+    guard: spin::MutexGuard<'a, T>,
+    instance: usize,
+}
+
+impl<T> Mutex<T> {
+    /// 创建一个新的锁
+    pub const fn new(data: T) -> Self {
+        Self(spin::Mutex::new(data))
+    }
+
+    /// 获得锁
+    pub fn lock(&self) -> MutexGuard<T> {
+        let instance = self as *const Self as usize;
+        acquire(type_name::<Self>(), instance, Acquire::Exclusive);
+        MutexGuard {
+            guard: self.0.lock(),
+            instance,
+        }
+    }
+
+    /// 尝试获得锁，已经被持有时返回 `None`
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let instance = self as *const Self as usize;
+        let guard = self.0.try_lock()?;
+        acquire(type_name::<Self>(), instance, Acquire::Try);
+        Some(MutexGuard { guard, instance })
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        release(self.instance);
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+/// 记录到 lockdep 的 `spin::RwLock`
+#[derive(Default)]
+pub struct RwLock<T>(spin::RwLock<T>);
+
+/// [`RwLock`] 的读锁守卫，drop 时记录释放
+pub struct RwLockReadGuard<'a, T> {
+    guard: spin::RwLockReadGuard<'a, T>,
+    instance: usize,
+}
+
+/// [`RwLock`] 的写锁守卫，drop 时记录释放
+pub struct RwLockWriteGuard<'a, T> {
+    guard: spin::RwLockWriteGuard<'a, T>,
+    instance: usize,
+}
+
+impl<T> RwLock<T> {
+    /// 创建一个新的锁
+    pub const fn new(data: T) -> Self {
+        Self(spin::RwLock::new(data))
+    }
+
+    /// 获得读锁
+    pub fn read(&self) -> RwLockReadGuard<T> {
+        let instance = self as *const Self as usize;
+        acquire(type_name::<Self>(), instance, Acquire::Shared);
+        RwLockReadGuard {
+            guard: self.0.read(),
+            instance,
+        }
+    }
+
+    /// 获得写锁
+    pub fn write(&self) -> RwLockWriteGuard<T> {
+        let instance = self as *const Self as usize;
+        acquire(type_name::<Self>(), instance, Acquire::Exclusive);
+        RwLockWriteGuard {
+            guard: self.0.write(),
+            instance,
+        }
+    }
+
+    /// 尝试获得读锁，已经被写者持有时返回 `None`
+    pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        let instance = self as *const Self as usize;
+        let guard = self.0.try_read()?;
+        acquire(type_name::<Self>(), instance, Acquire::Try);
+        Some(RwLockReadGuard { guard, instance })
+    }
+}
+
+impl<'a, T> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        release(self.instance);
+    }
+}
+
+impl<'a, T> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        release(self.instance);
+    }
+}
+
+impl<'a, T> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<'a, T> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<'a, T> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
@@ -42,19 +42,20 @@ mod drivers;
 mod fs;
 mod interrupt;
 mod kernel;
+mod lockdep;
 mod memory;
 mod panic;
 mod process;
 mod sbi;
+mod smp;
 
 extern crate alloc;
 use memory::*;
 use process::*;
 use spin::RwLock;
 
-use alloc::sync::Arc;
-use fs::{INodeExt, ROOT_INODE};
-use xmas_elf::ElfFile;
+use alloc::{sync::Arc, vec::Vec};
+use riscv::register::time;
 
 // 汇编编写的程序入口，具体见该文件
 global_asm!(include_str!("entry.asm"));
@@ -62,8 +63,13 @@ global_asm!(include_str!("entry.asm"));
 /// Rust 的入口函数
 ///
 /// 在 `_start` 为我们进行了一系列准备之后，这是第一个被调用的 Rust 函数
+///
+/// 每个 hart 都会进入这里，第一个进入的 hart 负责初始化，其他 hart 进入 [`secondary_main`]
 #[no_mangle] //禁用编译期间的名称重整（Name Mangling），保证生成命为_start的函数
-pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
+pub extern "C" fn rust_main(hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
+    if !smp::claim_boot_hart() {
+        secondary_main(hart_id);
+    }
     println!("Hello rCore-Tutorial.");
     println!("Hello, GuiYi.");
     //初始化各模块
@@ -71,6 +77,7 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
     interrupt::init();
     drivers::init(dtb_pa);
     fs::init();
+    process::init();
     println!(
         "kernel end:{:x}, dtb:{}",
         PhysicalAddress::from(*memory::KERNEL_END_ADDRESS).0,
@@ -92,46 +99,81 @@ pub extern "C" fn rust_main(_hart_id: usize, dtb_pa: PhysicalAddress) -> ! {
         processor.add_thread(create_user_process("notebook"));
     }
     */
-    start_kernel_thread(sample_process as usize, Some(&[0usize]));
-    start_user_thread("hello_world");
-    start_user_thread("notebook");
+    // 一个内核线程计算结果，另一个等待它结束并打印
+    let worker = kthread_spawn(|| (1..=100).sum::<usize>()).unwrap();
+    kthread_spawn(move || println!("kernel thread returned {}", kthread_join(worker))).unwrap();
+    if drivers::device_tree::has_boot_option("fairness") {
+        fairness_test();
+    }
+    // 启动参数 `init=a,b` 可以指定开机时运行的用户程序
+    let programs = drivers::device_tree::boot_option("init").unwrap_or("hello_world,notebook");
+    for name in programs.split(',') {
+        start_user_thread(name);
+    }
 
-    PROCESSOR.get().run();
+    // 启动其他 hart，它们会从这个 hart 取走线程
+    smp::start_secondary_harts(dtb_pa);
+    PROCESSOR.run();
 }
 
-fn start_kernel_thread(entry_point: usize, arguments: Option<&[usize]>) {
-    let process = Process::new_kernel().unwrap();
-    let thread = Thread::new(process, entry_point, arguments).unwrap();
-    // 设置线程的返回地址为 kernel_thread_exit
-    thread.as_ref().inner().context.as_mut().unwrap();
-    //.set_ra(kernel_thread_exit as usize);
-    PROCESSOR.get().add_thread(thread);
+/// 其他 hart 的入口
+///
+/// 等待初始化完成后，设置自己的中断和内存，然后开始调度
+fn secondary_main(hart_id: usize) -> ! {
+    smp::wait_for_boot();
+    memory::init_hart();
+    interrupt::init_hart();
+    smp::set_online();
+    println!("hart {} started", hart_id);
+    PROCESSOR.run();
 }
 
-fn start_user_thread(name: &str) {
-    // 从文件系统中找到程序
-    let app = fs::ROOT_INODE.find(name).unwrap();
-    // 读取数据
-    let data = app.readall().unwrap();
-    // 解析 ELF 文件
-    let elf = ElfFile::new(data.as_slice()).unwrap();
-    // 利用 ELF 文件创建线程，映射空间并加载数据
-    let process = Process::from_elf(&elf, true).unwrap();
-    // 再从 ELF 中读出程序入口地址
-    let thread = Thread::new(process, elf.header.pt2.entry_point() as usize, None).unwrap();
-    // 添加线程
-    PROCESSOR.get().add_thread(thread);
-}
+/// 比较调度器的公平性：几个优先级不同的内核线程忙等同样长的时间，打印各自分到的执行时间
+///
+/// 启动参数包含 `fairness` 时运行。所有线程需要在同一个 hart 上竞争才有意义，应当配合 `SMP=1`，
+/// 例如 `make run SMP=1 BOOTARGS=fairness FEATURES=sched-lottery`
+fn fairness_test() {
+    /// 各个线程的优先级（对彩票调度器即彩票数量）
+    const PRIORITIES: [usize; 3] = [4, 8, 16];
+    /// 忙等的时间 2 秒（微秒）
+    const DURATION: usize = 2_000_000;
 
-fn sample_process(message: usize) {
-    println!("hello world from kernel thread.");
+    let end = time::read() + interrupt::us_to_ticks(DURATION);
+    let handles: Vec<_> = PRIORITIES
+        .iter()
+        .map(|&priority| {
+            let handle = kthread_spawn(move || {
+                while time::read() < end {}
+                PROCESSOR.get().current_thread().inner().stats
+            })
+            .unwrap();
+            PROCESSOR.set_priority(handle.thread(), priority);
+            handle
+        })
+        .collect();
+    kthread_spawn(move || {
+        let stats: Vec<ThreadStats> = handles.into_iter().map(kthread_join).collect();
+        let total: usize = stats.iter().map(|stats| stats.system_time).sum();
+        let total_priority: usize = PRIORITIES.iter().sum();
+        for (priority, stats) in PRIORITIES.iter().zip(stats.iter()) {
+            println!(
+                "[fairness] priority {:>2}: ran {:>3}%, expected {:>3}%, preempted {} times",
+                priority,
+                stats.system_time * 100 / total.max(1),
+                priority * 100 / total_priority,
+                stats.involuntary_switches
+            );
+        }
+    })
+    .unwrap();
 }
 
-/// 内核线程需要调用这个函数来退出
-fn kernel_thread_exit() {
-    use process::*;
-    // 当前线程标记为结束
-    PROCESSOR.get().current_thread().as_ref().inner().dead = true;
-    // 制造一个中断来交给操作系统处理
-    unsafe { llvm_asm!("ebreak" :::: "volatile") };
+fn start_user_thread(name: &str) {
+    // 从文件系统中找到程序，利用 ELF 文件创建进程，映射空间并加载数据，再从 ELF 中读出程序入口地址
+    // 文件不合法或内存不足时只放弃这个程序，而不是让整个内核 panic
+    match Process::spawn(name, USER_MEMORY_LIMIT) {
+        // 添加线程
+        Ok(thread) => PROCESSOR.add_thread(thread),
+        Err(message) => println!("failed to start {}: {}", name, message),
+    }
 }
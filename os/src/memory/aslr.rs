@@ -0,0 +1,80 @@
+//! 地址空间布局随机化
+//!
+//! 用户进程的线程栈、堆和匿名映射（包括共享内存）会被放在随机的位置上。
+//!
+//! 随机数种子在启动时由 `time`、`cycle` 寄存器和 hart 编号混合而成，如果引导程序在设备树中
+//! 提供了 `/chosen/rng-seed` 也会一并混入。注意前三者只取决于启动所用的时间，
+//! 熵很少，可以被猜到；没有 `rng-seed` 时，随机化只能防止依赖固定地址的攻击，
+//! 而不能抵抗有意的猜测。
+//!
+//! 启动参数中包含 `noaslr` 时关闭随机化，所有映射都从低地址开始依次放置，便于调试时复现问题。
+
+use super::{address::*, range::Range};
+use crate::drivers::device_tree::{has_boot_option, rng_seed};
+use crate::smp::hart_id;
+use algorithm::XorShift;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::*;
+use riscv::register::{cycle, time};
+use spin::Mutex;
+
+lazy_static! {
+    /// 用于选择位置的伪随机数生成器
+    static ref RANDOM: Mutex<XorShift> = Mutex::new(XorShift::new(0));
+}
+
+/// 是否启用随机化
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 读取启动参数并设置随机数种子
+///
+/// 需要在解析设备树之后调用
+pub fn init() {
+    if has_boot_option("noaslr") {
+        ENABLED.store(false, Ordering::Relaxed);
+        println!("aslr disabled by boot option");
+    } else {
+        *RANDOM.lock() = XorShift::new(seed());
+    }
+}
+
+/// 混合各个来源得到随机数种子
+fn seed() -> u64 {
+    // 每个来源乘以不同的奇数常数后异或，避免它们的低位互相抵消
+    (time::read() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (cycle::read() as u64).wrapping_mul(0xbf58_476d_1ce4_e5b9)
+        ^ (hart_id() as u64).wrapping_mul(0x94d0_49bb_1331_11eb)
+        ^ rng_seed().unwrap_or(0)
+}
+
+/// 是否启用随机化
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// 在按地址升序排列的空闲区间 `gaps` 中选择一段 `page_count` 页的位置，返回起始页号
+///
+/// 启用随机化时在所有可行的位置中均匀地随机选择，否则选择最低的位置
+pub fn place(gaps: &[Range<VirtualPageNumber>], page_count: usize) -> Option<VirtualPageNumber> {
+    let candidates = gaps.iter().filter(|gap| gap.len() >= page_count);
+    if !is_enabled() {
+        return candidates.map(|gap| gap.start).next();
+    }
+    // 每个空闲区间中可以作为起点的页面数量
+    let total: usize = candidates
+        .clone()
+        .map(|gap| gap.len() - page_count + 1)
+        .sum();
+    if total == 0 {
+        return None;
+    }
+    let mut index = RANDOM.lock().next_below(total);
+    for gap in candidates {
+        let count = gap.len() - page_count + 1;
+        if index < count {
+            return Some(gap.start + index);
+        }
+        index -= count;
+    }
+    unreachable!()
+}
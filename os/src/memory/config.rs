@@ -10,6 +10,14 @@ pub const PAGE_SIZE: usize = 4096;
 pub const MEMORY_START_ADDRESS: PhysicalAddress = PhysicalAddress(0x8000_0000);
 /// qemu可以访问的内存区域结束地址
 pub const MEMORY_END_ADDRESS: PhysicalAddress = PhysicalAddress(0x8800_0000);
+/// 用户程序可以使用的虚拟地址上界（Sv39 地址空间的低半部分）
+pub const USER_END_ADDRESS: VirtualAddress = VirtualAddress(0x40_0000_0000);
+/// 所有地址空间共享的内核区域起始地址（根页表的最后一项），见 [`kernel_space`]
+///
+/// [`kernel_space`]: crate::memory::mapping::kernel_space
+pub const KERNEL_SPACE_START: VirtualAddress = VirtualAddress(0xffff_ffff_c000_0000);
+/// 共享的内核区域大小（1G）
+pub const KERNEL_SPACE_SIZE: usize = 0x4000_0000;
 /// 内核使用线性映射的偏移量
 pub const KERNEL_MAP_OFFSET: usize = 0xffff_ffff_0000_0000;
 /// MMIO 设备段内存区域起始地址
@@ -2,10 +2,11 @@
 //!
 //! 返回的 [`FrameTracker`] 类型代表一个帧，它在被 drop 时会自动将空间补回分配器中。
 use super::*;
+use crate::lockdep::Mutex;
 use crate::memory::*;
 use algorithm::{Allocator, AllocatorImpl};
 use lazy_static::*;
-use spin::Mutex;
+use spin::Once;
 /*
 lazy_static! {
     /// 帧分配器
@@ -27,6 +28,33 @@ lazy_static! {
         )));
 }
 
+/// 物理页面耗尽时调用的回调，返回是否回收到了内存
+///
+/// 由 `process` 模块注册，用于杀死进程以回收内存（OOM killer）
+static OOM_HANDLER: Once<fn() -> bool> = Once::new();
+
+/// 注册物理页面耗尽时的回调
+pub fn set_oom_handler(handler: fn() -> bool) {
+    OOM_HANDLER.call_once(|| handler);
+}
+
+/// 分配一个物理页面
+///
+/// 如果已经没有空闲的帧，会调用 OOM 回调来回收内存，回收成功则重试，否则返回 `Err`
+pub fn alloc_frame() -> MemoryResult<FrameTracker> {
+    loop {
+        // 回调中会释放帧，所以调用回调之前必须先释放 FRAME_ALLOCATOR 的锁
+        let result = FRAME_ALLOCATOR.lock().alloc();
+        match result {
+            Ok(frame) => return Ok(frame),
+            Err(message) => match OOM_HANDLER.r#try() {
+                Some(handler) if handler() => continue,
+                _ => return Err(message),
+            },
+        }
+    }
+}
+
 /// 基于线段树的帧分配 / 回收
 pub struct FrameAllocator {
     /// 可用区间的起始
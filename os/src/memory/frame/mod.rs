@@ -1,5 +1,5 @@
 pub mod allocator;
 pub mod frame_tracker;
 
-pub use allocator::FRAME_ALLOCATOR;
+pub use allocator::{alloc_frame, set_oom_handler, FRAME_ALLOCATOR};
 pub use frame_tracker::FrameTracker;
@@ -0,0 +1,116 @@
+//! 所有地址空间共享的内核区域
+//!
+//! 根页表中 [`KERNEL_SPACE_START`] 所在的一项（最后 1G 空间）在所有页表中都指向同一个二级页表，
+//! 因此在这里建立或者移除的映射对所有地址空间立即生效，不需要逐个修改进程的页表。
+//!
+//! 目前用来放置线程的内核栈：每一段空间下方都留有一个不映射的保护页，
+//! 栈溢出时会触发缺页异常，而不是悄悄覆盖其他数据。
+
+use super::{
+    super::{
+        address::*,
+        config::*,
+        frame::{alloc_frame, FrameTracker},
+        range::Range,
+        MemoryResult,
+    },
+    mapping::Mapping,
+    page_table::PageTable,
+    page_table_entry::*,
+    segment::*,
+};
+use alloc::vec::Vec;
+use spin::{Mutex, Once};
+
+/// 共享的内核区域
+struct KernelSpace {
+    /// 只使用其中共享的那一项，页表由它持有
+    mapping: Mapping,
+    /// 还没有使用过的最低页号
+    next: VirtualPageNumber,
+    /// 释放后可以重新使用的空间（不包括保护页）
+    free: Vec<Range<VirtualPageNumber>>,
+}
+
+static KERNEL_SPACE: Once<Mutex<KernelSpace>> = Once::new();
+
+/// 根页表中共享的那一项，[`init`] 之后才存在
+static SHARED_ENTRY: Once<PageTableEntry> = Once::new();
+
+/// 建立共享的二级页表
+pub fn init() {
+    KERNEL_SPACE.call_once(|| {
+        let start = VirtualPageNumber::from(KERNEL_SPACE_START);
+        let mut mapping = Mapping::new().unwrap();
+        // 查找页表项时会创建下面的各级页表，之后只会在共享的二级页表之下增加页表
+        mapping.find_entry(start).unwrap();
+        SHARED_ENTRY.call_once(|| mapping.root_entry(start));
+        Mutex::new(KernelSpace {
+            mapping,
+            next: start,
+            free: Vec::new(),
+        })
+    });
+}
+
+/// 在新的根页表中填入共享的一项
+pub fn share(root_table: &mut PageTable) {
+    if let Some(entry) = SHARED_ENTRY.r#try() {
+        root_table.entries[VirtualPageNumber::from(KERNEL_SPACE_START).levels()[0]] = *entry;
+    }
+}
+
+/// 分配一段下方带有保护页的空间，并映射新分配的物理页面
+///
+/// 返回的区间不包括保护页，物理页面交给调用者持有
+pub fn alloc_guarded(
+    page_count: usize,
+) -> MemoryResult<(Range<VirtualAddress>, Vec<FrameTracker>)> {
+    // 分配时可能触发 OOM 回收而释放其他内核栈，因此这里不能持有锁
+    let mut frames = Vec::with_capacity(page_count);
+    for _ in 0..page_count {
+        let mut frame = alloc_frame()?;
+        frame.fill(0);
+        frames.push(frame);
+    }
+    let mut space = KERNEL_SPACE.r#try().unwrap().lock();
+    let pages = match space
+        .free
+        .iter()
+        .position(|pages| pages.len() == page_count)
+    {
+        Some(index) => space.free.swap_remove(index),
+        None => {
+            // 跳过一页作为保护页
+            let start = space.next + 1;
+            let end = start + page_count;
+            if end > VirtualPageNumber::from(KERNEL_SPACE_START) + KERNEL_SPACE_SIZE / PAGE_SIZE {
+                return Err("kernel space exhausted");
+            }
+            space.next = end;
+            Range::from(start..end)
+        }
+    };
+    let segment = Segment {
+        map_type: MapType::Framed,
+        range: pages.into(),
+        flags: Flags::READABLE | Flags::WRITABLE,
+    };
+    // 映射失败时可能留下部分页表项，这段空间不再使用
+    space.mapping.map_frames(&segment, &frames)?;
+    Ok((segment.range, frames))
+}
+
+/// 移除 [`alloc_guarded`] 分配的空间的映射，之后可以重新分配
+///
+/// 调用者应当在此之后再释放物理页面
+pub fn free_guarded(range: Range<VirtualAddress>) {
+    let segment = Segment {
+        map_type: MapType::Framed,
+        range,
+        flags: Flags::READABLE | Flags::WRITABLE,
+    };
+    let mut space = KERNEL_SPACE.r#try().unwrap().lock();
+    space.mapping.unmap(&segment);
+    space.free.push(segment.page_range());
+}
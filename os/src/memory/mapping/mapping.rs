@@ -3,9 +3,10 @@ use super::{
     super::{
         address::*,
         config::PAGE_SIZE,
-        frame::{allocator::FRAME_ALLOCATOR, frame_tracker::FrameTracker},
+        frame::{allocator::alloc_frame, frame_tracker::FrameTracker},
         MemoryResult,
     },
+    kernel_space,
     page_table::*,
     page_table_entry::*,
     segment::*,
@@ -14,6 +15,27 @@ use super::{
 use alloc::{vec, vec::Vec};
 use core::cmp::min;
 use core::ptr::slice_from_raw_parts_mut;
+
+/// 页表中一个有效的叶子页表项
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LeafEntry {
+    /// 虚拟页号（大页时为起始页号）
+    pub vpn: VirtualPageNumber,
+    /// 物理页号（大页时为起始页号）
+    pub ppn: PhysicalPageNumber,
+    /// 标志位
+    pub flags: Flags,
+    /// 页表项所在的级数，0 为根页表（1G 大页），2 为最后一级（4K 页）
+    pub level: usize,
+}
+
+impl LeafEntry {
+    /// 这一项映射的 4K 页面数量
+    pub fn page_count(&self) -> usize {
+        1 << (9 * (2 - self.level))
+    }
+}
+
 #[derive(Default)]
 /// 某个线程的内存映射关系
 pub struct Mapping {
@@ -27,7 +49,9 @@ pub struct Mapping {
 impl Mapping {
     /// 创建一个有根节点的映射
     pub fn new() -> MemoryResult<Mapping> {
-        let root_table = PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?);
+        let mut root_table = PageTableTracker::new(alloc_frame()?);
+        // 所有地址空间共享内核区域
+        kernel_space::share(&mut root_table);
         let root_ppn = root_table.page_number();
         Ok(Mapping {
             page_tables: vec![root_table],
@@ -35,6 +59,11 @@ impl Mapping {
         })
     }
 
+    /// 页表本身所占用的物理页面数量
+    pub fn page_table_frames(&self) -> usize {
+        self.page_tables.len()
+    }
+
     /// 找到给定虚拟页号的三级页表项
     ///
     /// 如果找不到对应的页表项，则会相应创建页表
@@ -46,7 +75,7 @@ impl Mapping {
         for vpn_slice in &vpn.levels()[1..] {
             if entry.is_empty() {
                 // 如果页表不存在，则需要分配一个新的页表
-                let new_table = PageTableTracker::new(FRAME_ALLOCATOR.lock().alloc()?);
+                let new_table = PageTableTracker::new(alloc_frame()?);
                 let new_ppn = new_table.page_number();
                 // 将新页表的页号写入当前的页表项
                 *entry = PageTableEntry::new(new_ppn, Flags::VALID);
@@ -104,7 +133,7 @@ impl Mapping {
                 let mut allocated_pairs = Vec::new();
                 for vpn in segment.page_range().iter() {
                     // 分配物理页面
-                    let mut frame = FRAME_ALLOCATOR.lock().alloc()?;
+                    let mut frame = alloc_frame()?;
                     // 映射，填充 0，记录
                     self.map_one(vpn, frame.page_number(), segment.flags | Flags::VALID)?;
                     frame.fill(0);
@@ -142,6 +171,19 @@ impl Mapping {
         }
     }
 
+    /// 将一组已经分配好的物理页面依次映射到 `segment` 上
+    ///
+    /// 页面的所有权不会转移，用于映射 [`SharedMemory`] 这样由其他对象持有的页面
+    ///
+    /// [`SharedMemory`]: crate::memory::shared_memory::SharedMemory
+    pub fn map_frames(&mut self, segment: &Segment, frames: &[FrameTracker]) -> MemoryResult<()> {
+        assert!(segment.page_range().len() <= frames.len());
+        for (vpn, frame) in segment.page_range().iter().zip(frames.iter()) {
+            self.map_one(vpn, frame.page_number(), segment.flags | Flags::VALID)?;
+        }
+        Ok(())
+    }
+
     /// 移除一段映射
     pub fn unmap(&mut self, segment: &Segment) {
         for vpn in segment.page_range().iter() {
@@ -150,6 +192,9 @@ impl Mapping {
             // 从页表中清除项
             entry.clear();
         }
+        // 页表项被清除后，需要刷新 TLB 中可能残留的旧映射，其他 hart 上也可能有
+        unsafe { llvm_asm!("sfence.vma" :::: "volatile") };
+        crate::smp::shootdown_tlb(self.root_ppn);
     }
 
     /// 查找虚拟地址对应的物理地址
@@ -182,10 +227,92 @@ impl Mapping {
         Some(PhysicalAddress(base + offset))
     }
 
+    /// 遍历所有有效的叶子页表项，按虚拟页号从小到大
+    pub fn iter(&self) -> MappingIter {
+        MappingIter {
+            stack: vec![(self.root_table(), 0)],
+        }
+    }
+
+    /// 查找给定虚拟页号所在的叶子页表项，不会创建页表
+    pub fn find_leaf(&self, vpn: VirtualPageNumber) -> Option<LeafEntry> {
+        let mut table = self.root_table();
+        for (level, index) in vpn.levels().iter().enumerate() {
+            let entry = table.entries[*index];
+            if !entry.flags().contains(Flags::VALID) {
+                return None;
+            }
+            if !entry.has_next_level() || level == 2 {
+                let page_count = 1 << (9 * (2 - level));
+                return Some(LeafEntry {
+                    vpn: VirtualPageNumber(vpn.0 & !(page_count - 1)),
+                    ppn: entry.page_number(),
+                    flags: entry.flags(),
+                    level,
+                });
+            }
+            table = entry.get_next_table();
+        }
+        unreachable!()
+    }
+
+    /// 打印所有映射，连续且标志位相同的页面会合并为一行
+    ///
+    /// 合并时忽略由硬件修改的 ACCESSED 和 DIRTY 位
+    pub fn dump(&self) {
+        let mut entries = self.iter();
+        let mut current = match entries.next() {
+            Some(entry) => entry,
+            None => return,
+        };
+        let mut count = current.page_count();
+        for entry in entries {
+            if entry.level == current.level
+                && entry.flags - (Flags::ACCESSED | Flags::DIRTY)
+                    == current.flags - (Flags::ACCESSED | Flags::DIRTY)
+                && entry.vpn.0 == current.vpn.0 + count
+                && entry.ppn.0 == current.ppn.0 + count
+            {
+                count += entry.page_count();
+            } else {
+                Self::dump_range(&current, count);
+                current = entry;
+                count = entry.page_count();
+            }
+        }
+        Self::dump_range(&current, count);
+    }
+
+    /// 打印从 `first` 开始的连续 `count` 个页面
+    fn dump_range(first: &LeafEntry, count: usize) {
+        println!(
+            "  {:#018x}..{:#018x} -> {:#010x}..{:#010x} {} {}K x{}",
+            VirtualAddress::from(first.vpn).0,
+            VirtualAddress::from(first.vpn + count).0,
+            PhysicalAddress::from(first.ppn).0,
+            PhysicalAddress::from(first.ppn + count).0,
+            first.flags,
+            first.page_count() * PAGE_SIZE / 1024,
+            count / first.page_count()
+        );
+    }
+
+    /// 根页表中 `vpn` 所在的一项
+    pub fn root_entry(&self, vpn: VirtualPageNumber) -> PageTableEntry {
+        self.root_table().entries[vpn.levels()[0]]
+    }
+
+    /// 根页表
+    fn root_table(&self) -> &'static PageTable {
+        PhysicalAddress::from(self.root_ppn).deref_kernel()
+    }
+
     /// 将当前的映射加载到 `satp` 寄存器
     pub fn activate(&self) {
         // satp 低 27 位为页号，高 4 位为模式，8 表示 Sv39
         let new_satp = self.root_ppn.0 | (8 << 60);
+        // 先登记再加载，修改页表的 hart 要么看到登记而通知这里，要么这里加载时已经能看到修改
+        crate::smp::set_active_root(self.root_ppn);
         unsafe {
             // 将 new_satp 的值写到 satp 寄存器
             llvm_asm!("csrw satp, $0" :: "r"(new_satp) :: "volatile");
@@ -194,3 +321,53 @@ impl Mapping {
         }
     }
 }
+
+/// 遍历 [`Mapping`] 中所有有效叶子页表项的迭代器
+pub struct MappingIter {
+    /// 从根页表到当前页表的路径，以及每一级页表中下一个要访问的下标
+    stack: Vec<(&'static PageTable, usize)>,
+}
+
+impl Iterator for MappingIter {
+    type Item = LeafEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let level = self.stack.len().checked_sub(1)?;
+            let (table, index) = {
+                let top = self.stack.last_mut().unwrap();
+                top.1 += 1;
+                (top.0, top.1 - 1)
+            };
+            // 当前页表遍历完，返回上一级
+            if index == PAGE_SIZE / 8 {
+                self.stack.pop();
+                continue;
+            }
+            let entry = table.entries[index];
+            if !entry.flags().contains(Flags::VALID) {
+                continue;
+            }
+            if entry.has_next_level() && level < 2 {
+                self.stack.push((entry.get_next_table(), 0));
+                continue;
+            }
+            // 由每一级的下标拼出虚拟页号（栈中的下标已经指向下一项）
+            let mut vpn = 0;
+            for (_, index) in self.stack.iter() {
+                vpn = (vpn << 9) | (index - 1);
+            }
+            vpn <<= 9 * (2 - level);
+            // Sv39 的虚拟地址需要按第 38 位做符号扩展
+            if vpn & (1 << 26) != 0 {
+                vpn |= ((1 << 52) - 1) & !((1 << 27) - 1);
+            }
+            return Some(LeafEntry {
+                vpn: VirtualPageNumber(vpn),
+                ppn: entry.page_number(),
+                flags: entry.flags(),
+                level,
+            });
+        }
+    }
+}
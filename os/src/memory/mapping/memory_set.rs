@@ -7,11 +7,14 @@ use crate::memory::{
     frame::FrameTracker,
     mapping::{Flags, MapType, Mapping, Segment},
     range::Range,
+    shared_memory::SharedMemory,
     MemoryResult,
 };
-use alloc::{vec, vec::Vec};
+use alloc::{collections::BTreeMap, format, string::String, sync::Arc, vec, vec::Vec};
+use core::{cmp::min, mem::size_of};
 use xmas_elf::{
-    program::{SegmentData, Type},
+    header::{self, Class, Data, Machine},
+    program::{ProgramHeader64, Type},
     ElfFile,
 };
 
@@ -23,6 +26,10 @@ pub struct MemorySet {
     pub segments: Vec<Segment>,
     /// 所有分配的物理页面映射信息
     pub allocated_pairs: Vec<(VirtualPageNumber, FrameTracker)>,
+    /// 映射进来的共享内存，页面由 [`SharedMemory`] 持有
+    pub shared_segments: Vec<(Segment, Arc<SharedMemory>)>,
+    /// 最多可以占用的物理页面数量（包括页表），`None` 表示不限制
+    pub frame_limit: Option<usize>,
 }
 
 impl MemorySet {
@@ -88,47 +95,161 @@ impl MemorySet {
             mapping,
             segments,
             allocated_pairs,
+            shared_segments: Vec::new(),
+            frame_limit: None,
         })
     }
 
     /// 通过 elf 文件创建内存映射（不包括栈）
-    // todo: 有可能不同的字段出现在同一页？
-    pub fn from_elf(file: &ElfFile, is_user: bool) -> MemoryResult<MemorySet> {
-        // 建立带有内核映射的 MemorySet
-        let mut memory_set = MemorySet::new_kernel()?;
+    ///
+    /// 不同的 LOAD 段可能出现在同一页中（例如 .text 的结尾和 .rodata 的开头），
+    /// 这时该页面的权限取各段权限的并集。文件中没有数据的部分（如 .bss）保持为 0。
+    ///
+    /// 所有不合法的 elf 文件都会返回错误，而不会导致内核 panic。
+    ///
+    /// `frame_limit` 在映射各个段之前就生效，程序本身超出限制时同样返回错误
+    pub fn from_elf(
+        file: &ElfFile,
+        is_user: bool,
+        frame_limit: Option<usize>,
+    ) -> MemoryResult<MemorySet> {
+        check_elf_header(file)?;
 
-        // 遍历 elf 文件的所有部分
+        // 检查所有 LOAD 段，并计算每一页的权限
+        let mut loads = Vec::new();
+        let mut page_flags: BTreeMap<VirtualPageNumber, Flags> = BTreeMap::new();
         for program_header in file.program_iter() {
             if program_header.get_type() != Ok(Type::Load) {
                 continue;
             }
-            // 从每个字段读取「起始地址」「大小」和「数据」
-            let start = VirtualAddress(program_header.virtual_addr() as usize);
-            let size = program_header.mem_size() as usize;
-            let data: &[u8] =
-                if let SegmentData::Undefined(data) = program_header.get_data(file).unwrap() {
-                    data
-                } else {
-                    return Err("unsupported elf format");
-                };
+            let start = program_header.virtual_addr() as usize;
+            let mem_size = program_header.mem_size() as usize;
+            let file_size = program_header.file_size() as usize;
+            let offset = program_header.offset() as usize;
+            // 从文件中读取的数据，长度不能超过段在内存中的大小
+            if file_size > mem_size {
+                return Err("elf segment file size exceeds memory size");
+            }
+            let data = offset
+                .checked_add(file_size)
+                .and_then(|end| file.input.get(offset..end))
+                .ok_or("elf segment data out of file")?;
+            // 段必须完全位于用户地址空间内
+            let end = start
+                .checked_add(mem_size)
+                .filter(|end| *end <= USER_END_ADDRESS.0)
+                .ok_or("elf segment out of user address space")?;
+            if mem_size == 0 {
+                continue;
+            }
+            let flags = Flags::user(is_user)
+                | Flags::readable(program_header.flags().is_read())
+                | Flags::writable(program_header.flags().is_write())
+                | Flags::executable(program_header.flags().is_execute());
+            let page_range = Range::from(
+                VirtualPageNumber::floor(VirtualAddress(start))
+                    ..VirtualPageNumber::ceil(VirtualAddress(end)),
+            );
+            for vpn in page_range.iter() {
+                *page_flags.entry(vpn).or_insert_with(Flags::empty) |= flags;
+            }
+            loads.push((VirtualAddress(start), data));
+        }
+
+        // 入口必须位于可执行的页面中
+        let entry = VirtualAddress(file.header.pt2.entry_point() as usize);
+        match page_flags.get(&VirtualPageNumber::floor(entry)) {
+            Some(flags) if flags.contains(Flags::EXECUTABLE) => {}
+            _ => return Err("elf entry point is not in an executable segment"),
+        }
 
-            // 将每一部分作为 Segment 进行映射
+        // 建立带有内核映射的 MemorySet
+        let mut memory_set = MemorySet::new_kernel()?;
+        memory_set.frame_limit = frame_limit;
+
+        // 将连续且权限相同的页面合并为一个 Segment 进行映射，页面均以 0 填充
+        let mut pages = page_flags.into_iter().peekable();
+        while let Some((first, flags)) = pages.next() {
+            let mut last = first;
+            while let Some((vpn, next_flags)) = pages.peek() {
+                if *vpn != last + 1 || *next_flags != flags {
+                    break;
+                }
+                last = *vpn;
+                pages.next();
+            }
             let segment = Segment {
                 map_type: MapType::Framed,
-                range: Range::from(start..(start + size)),
-                flags: Flags::user(is_user)
-                    | Flags::readable(program_header.flags().is_read())
-                    | Flags::writable(program_header.flags().is_write())
-                    | Flags::executable(program_header.flags().is_execute()),
+                range: Range::from(VirtualAddress::from(first)..VirtualAddress::from(last + 1)),
+                flags,
             };
+            memory_set.add_segment(segment, None)?;
+        }
 
-            // 建立映射并复制数据
-            memory_set.add_segment(segment, Some(data))?;
+        // 复制文件中的数据，剩余部分已经是 0
+        for (start, data) in loads {
+            memory_set.write_bytes(start, data)?;
         }
 
         Ok(memory_set)
     }
 
+    /// 通过页表将数据写入 `start` 开始的虚拟地址，不要求当前页表就是自身
+    ///
+    /// 所写入的区域必须已经全部映射
+    pub fn write_bytes(&self, start: VirtualAddress, data: &[u8]) -> MemoryResult<()> {
+        let mut written = 0;
+        while written < data.len() {
+            let address = start + written;
+            let vpn = VirtualPageNumber::floor(address);
+            let leaf = self
+                .mapping
+                .find_leaf(vpn)
+                .ok_or("writing to unmapped memory")?;
+            // 计算这一页中需要写入的区间
+            let page_offset = address.0 % PAGE_SIZE;
+            let length = min(PAGE_SIZE - page_offset, data.len() - written);
+            let page = (leaf.ppn + (vpn - leaf.vpn)).deref_kernel();
+            page[page_offset..page_offset + length]
+                .copy_from_slice(&data[written..written + length]);
+            written += length;
+        }
+        Ok(())
+    }
+
+    /// 查找用户可以访问的虚拟地址对应的物理地址，不要求当前页表就是自身
+    ///
+    /// 没有映射或者不允许用户访问时返回 `None`
+    pub fn translate_user(&self, address: VirtualAddress) -> Option<PhysicalAddress> {
+        let vpn = VirtualPageNumber::floor(address);
+        let leaf = self.mapping.find_leaf(vpn)?;
+        if !leaf.flags.contains(Flags::USER) {
+            return None;
+        }
+        Some(PhysicalAddress::from(leaf.ppn + (vpn - leaf.vpn)) + address.0 % PAGE_SIZE)
+    }
+
+    /// 检查从 `start` 开始 `size` 字节的区域是否全部映射并允许用户读取，`writable` 时还需要可写
+    ///
+    /// 用于在系统调用中访问用户传入的指针之前验证它
+    pub fn is_user_accessible(&self, start: VirtualAddress, size: usize, writable: bool) -> bool {
+        let end = match start.0.checked_add(size) {
+            Some(end) if end <= USER_END_ADDRESS.0 => VirtualAddress(end),
+            _ => return false,
+        };
+        let mut required = Flags::USER | Flags::READABLE;
+        if writable {
+            required |= Flags::WRITABLE;
+        }
+        Range::from(VirtualPageNumber::floor(start)..VirtualPageNumber::ceil(end))
+            .iter()
+            .all(|vpn| {
+                self.mapping
+                    .find_leaf(vpn)
+                    .map_or(false, |leaf| leaf.flags.contains(required))
+            })
+    }
+
     /// 替换 `satp` 以激活页表
     ///
     /// 如果当前页表就是自身，则不会替换，但仍然会刷新 TLB。
@@ -139,7 +260,13 @@ impl MemorySet {
     /// 添加一个 [`Segment`] 的内存映射
     pub fn add_segment(&mut self, segment: Segment, init_data: Option<&[u8]>) -> MemoryResult<()> {
         // 检测 segment 没有重合
-        assert!(!self.overlap_with(segment.page_range()));
+        if self.overlap_with(segment.page_range()) {
+            return Err("segment overlaps with existing mapping");
+        }
+        // 检测是否会超出内存限制（新增页表的数量无法预知，这里只计入数据页面）
+        if segment.map_type == MapType::Framed {
+            self.check_limit(segment.page_range().len())?;
+        }
         // 映射并将新分配的页面保存下来
         self.allocated_pairs
             .extend(self.mapping.map(&segment, init_data)?);
@@ -166,9 +293,196 @@ impl MemorySet {
         Ok(())
     }
 
+    /// 将一段共享内存映射到 `start` 开始的位置
+    ///
+    /// `flags` 只需包括 rwx 和 user 权限
+    pub fn attach_shared(
+        &mut self,
+        shared_memory: Arc<SharedMemory>,
+        start: VirtualAddress,
+        flags: Flags,
+    ) -> MemoryResult<()> {
+        let segment = Segment {
+            map_type: MapType::Framed,
+            range: Range::from(start..(start + shared_memory.page_count() * PAGE_SIZE)),
+            flags,
+        };
+        if self.overlap_with(segment.page_range()) {
+            return Err("shared memory overlaps with existing mapping");
+        }
+        self.check_limit(shared_memory.page_count())?;
+        self.mapping.map_frames(&segment, shared_memory.frames())?;
+        self.shared_segments.push((segment, shared_memory));
+        Ok(())
+    }
+
+    /// 解除从 `start` 开始的共享内存映射
+    pub fn detach_shared(&mut self, start: VirtualAddress) -> MemoryResult<()> {
+        let index = self
+            .shared_segments
+            .iter()
+            .position(|(segment, _)| segment.range.start == start)
+            .ok_or("no shared memory attached at this address")?;
+        let (segment, shared_memory) = self.shared_segments.remove(index);
+        self.mapping.unmap(&segment);
+        SharedMemory::detach(shared_memory);
+        Ok(())
+    }
+
+    /// 映射的数据页面数量，包括按帧分配的页面和共享内存页面
+    pub fn resident_frames(&self) -> usize {
+        self.allocated_pairs.len()
+            + self
+                .shared_segments
+                .iter()
+                .map(|(segment, _)| segment.page_range().len())
+                .sum::<usize>()
+    }
+
+    /// 页表所占用的物理页面数量
+    pub fn page_table_frames(&self) -> usize {
+        self.mapping.page_table_frames()
+    }
+
+    /// 检测再映射 `count` 个页面是否会超出内存限制
+    fn check_limit(&self, count: usize) -> MemoryResult<()> {
+        match self.frame_limit {
+            Some(limit) if self.resident_frames() + self.page_table_frames() + count > limit => {
+                Err("memory limit exceeded")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 释放所有按帧分配的映射和共享内存，只保留线性映射
+    ///
+    /// 用于强制结束进程时立即回收其内存。调用之后不能再回到这个进程的用户态执行
+    pub fn release_framed(&mut self) {
+        let framed_segments: Vec<Segment> = self
+            .segments
+            .iter()
+            .filter(|segment| segment.map_type == MapType::Framed)
+            .copied()
+            .collect();
+        for segment in framed_segments.iter() {
+            self.remove_segment(segment).unwrap();
+        }
+        for (segment, shared_memory) in self.shared_segments.drain(..) {
+            self.mapping.unmap(&segment);
+            SharedMemory::detach(shared_memory);
+        }
+    }
+
+    /// 检查页表和所有 [`Segment`] 是否一致，返回发现的所有问题
+    ///
+    /// - 每个 `Segment` 的所有页面都以其声明的权限映射（忽略由硬件修改的 ACCESSED 和 DIRTY 位）
+    /// - 线性映射指向对应的物理页面，按帧映射指向 `allocated_pairs` 或共享内存中的物理页面
+    /// - 页表中没有不属于任何 `Segment` 的映射
+    pub fn check(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+        // 每个虚拟页面应当映射到的物理页面
+        let mut expected: BTreeMap<VirtualPageNumber, PhysicalPageNumber> = self
+            .allocated_pairs
+            .iter()
+            .map(|(vpn, frame)| (*vpn, frame.page_number()))
+            .collect();
+        for (segment, shared_memory) in self.shared_segments.iter() {
+            for (vpn, frame) in segment.page_range().iter().zip(shared_memory.frames()) {
+                expected.insert(vpn, frame.page_number());
+            }
+        }
+        for segment in self.all_segments() {
+            let flags = segment.flags | Flags::VALID;
+            for vpn in segment.page_range().iter() {
+                let leaf = match self.mapping.find_leaf(vpn) {
+                    Some(leaf) => leaf,
+                    None => {
+                        problems.push(format!("{} in {:x?} is not mapped", vpn, segment.range));
+                        continue;
+                    }
+                };
+                if leaf.flags - (Flags::ACCESSED | Flags::DIRTY) != flags {
+                    problems.push(format!(
+                        "{} is mapped with {} but declared {}",
+                        vpn, leaf.flags, flags
+                    ));
+                }
+                let ppn = leaf.ppn + (vpn - leaf.vpn);
+                let expected_ppn = match segment.map_type {
+                    MapType::Linear => Some(PhysicalPageNumber::from(vpn)),
+                    MapType::Framed => expected.get(&vpn).copied(),
+                };
+                if expected_ppn != Some(ppn) {
+                    problems.push(format!(
+                        "{} is mapped to {} but expected {:x?}",
+                        vpn, ppn, expected_ppn
+                    ));
+                }
+            }
+        }
+        // 检查多余的映射
+        for leaf in self.mapping.iter() {
+            if !self
+                .all_segments()
+                .any(|segment| segment.page_range().contains(leaf.vpn))
+            {
+                problems.push(format!(
+                    "stray mapping {} -> {} ({})",
+                    leaf.vpn, leaf.ppn, leaf.flags
+                ));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// 所有 [`Segment`]，包括共享内存
+    fn all_segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments
+            .iter()
+            .chain(self.shared_segments.iter().map(|(segment, _)| segment))
+    }
+
+    /// 找出 `area` 中既没有映射、也不在 `reserved` 中的所有空闲区间，按地址升序排列
+    ///
+    /// 将所有已占用的区间排序后一次遍历即可得到其间的空隙，而不需要逐个位置检测重叠
+    pub fn free_gaps(
+        &self,
+        area: Range<VirtualPageNumber>,
+        reserved: &[Range<VirtualPageNumber>],
+    ) -> Vec<Range<VirtualPageNumber>> {
+        let mut used: Vec<Range<VirtualPageNumber>> = self
+            .all_segments()
+            .map(|segment| segment.page_range())
+            .chain(reserved.iter().copied())
+            .collect();
+        used.sort_unstable_by_key(|range| range.start);
+        let mut gaps = Vec::new();
+        let mut cursor = area.start;
+        for range in used {
+            if range.start >= area.end {
+                break;
+            }
+            if range.end <= cursor {
+                continue;
+            }
+            if range.start > cursor {
+                gaps.push(Range::from(cursor..range.start));
+            }
+            cursor = range.end;
+        }
+        if cursor < area.end {
+            gaps.push(Range::from(cursor..area.end));
+        }
+        gaps
+    }
+
     /// 检测一段内存区域和已有的是否存在重叠区域
     pub fn overlap_with(&self, range: Range<VirtualPageNumber>) -> bool {
-        for seg in self.segments.iter() {
+        for seg in self.all_segments() {
             if range.overlap_with(&seg.page_range()) {
                 return true;
             }
@@ -176,3 +490,46 @@ impl MemorySet {
         false
     }
 }
+
+/// 进程结束时，解除其所有共享内存的映射
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        for (_, shared_memory) in self.shared_segments.drain(..) {
+            SharedMemory::detach(shared_memory);
+        }
+    }
+}
+
+/// RISC-V 的 e_machine 编号，xmas-elf 中没有对应的枚举值
+const EM_RISCV: u16 = 0xf3;
+
+/// 检查 elf 文件头是否是可以加载的 64 位 RISC-V 可执行文件，以及程序头表是否在文件范围内
+fn check_elf_header(file: &ElfFile) -> MemoryResult<()> {
+    let header = &file.header;
+    if header.pt1.class() != Class::SixtyFour {
+        return Err("elf is not 64-bit");
+    }
+    if header.pt1.data() != Data::LittleEndian {
+        return Err("elf is not little-endian");
+    }
+    if header.pt2.machine().as_machine() != Machine::Other(EM_RISCV) {
+        return Err("elf is not for risc-v");
+    }
+    if header.pt2.type_().as_type() != header::Type::Executable {
+        return Err("elf is not an executable");
+    }
+    // xmas-elf 解析程序头时不检查越界，这里需要提前检查
+    let ph_offset = header.pt2.ph_offset() as usize;
+    let ph_size = header.pt2.ph_count() as usize * header.pt2.ph_entry_size() as usize;
+    if header.pt2.ph_count() > 0 {
+        if header.pt2.ph_entry_size() as usize != size_of::<ProgramHeader64>() || ph_offset % 8 != 0
+        {
+            return Err("elf program header table is malformed");
+        }
+        match ph_offset.checked_add(ph_size) {
+            Some(end) if end <= file.input.len() => {}
+            _ => return Err("elf program header table out of file"),
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,11 @@
+pub mod kernel_space;
 pub mod mapping;
 pub mod memory_set;
 pub mod page_table;
 pub mod page_table_entry;
 pub mod segment;
 
-pub use mapping::Mapping;
+pub use mapping::{LeafEntry, Mapping, MappingIter};
 pub use memory_set::MemorySet;
 pub use page_table::{PageTable, PageTableTracker};
 pub use page_table_entry::{Flags, PageTableEntry};
@@ -83,6 +83,20 @@ impl core::fmt::Debug for PageTableEntry {
     }
 }
 
+/// 以 `VRWXUGAD` 的形式输出，未设置的位输出 `-`
+impl core::fmt::Display for Flags {
+    fn fmt(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        for (i, name) in "VRWXUGAD".chars().enumerate() {
+            if self.bits() & (1 << i) != 0 {
+                write!(formatter, "{}", name)?;
+            } else {
+                write!(formatter, "-")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 macro_rules! implement_flags {
     ($field: ident, $name: ident, $quote: literal) => {
         impl Flags {
@@ -7,11 +7,13 @@
 #![allow(dead_code)]
 
 pub mod address;
+pub mod aslr;
 pub mod config;
 pub mod frame;
 pub mod heap;
 pub mod mapping;
 pub mod range;
+pub mod shared_memory;
 
 /// 一个缩写，模块中一些函数会使用
 pub type MemoryResult<T> = Result<T, &'static str>;
@@ -22,15 +24,24 @@ pub use {
     frame::FRAME_ALLOCATOR,
     mapping::{Flags, MapType, MemorySet, Segment},
     range::Range,
+    shared_memory::SharedMemory,
 };
 
 /// 初始化内存相关的子模块
 ///
 /// - [`heap::init`]
+/// - [`mapping::kernel_space::init`]
+/// - [`init_hart`]
 pub fn init() {
     heap::init();
-    // 允许内核读写用户态内存
-    unsafe { riscv::register::sstatus::set_sum() };
+    mapping::kernel_space::init();
+    init_hart();
 
     println!("mod memory initialized");
 }
+
+/// 每个 hart 各自需要的初始化
+pub fn init_hart() {
+    // 允许内核读写用户态内存
+    unsafe { riscv::register::sstatus::set_sum() };
+}
@@ -0,0 +1,95 @@
+//! 进程间共享内存 [`SharedMemory`]
+//!
+//! 一段共享内存是一个内核对象，它持有若干物理页面（[`FrameTracker`]），
+//! 可以被映射到多个进程的 [`MemorySet`] 中，从而让这些进程看到同一片物理内存。
+//!
+//! ### 引用计数
+//! - 全局表 [`static@SHARED_MEMORY`] 按键值持有一份 `Arc<SharedMemory>`
+//! - 每一次 attach 会在对应的 [`MemorySet`] 中再持有一份
+//!
+//! 每次 detach 时，如果除了全局表之外已经没有任何进程持有它，就将其从全局表中移除，
+//! 最后一个 `Arc` 被 drop 时物理页面随之释放。
+//!
+//! [`MemorySet`]: crate::memory::mapping::MemorySet
+
+use super::{config::PAGE_SIZE, frame::*, MemoryResult};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use lazy_static::*;
+use spin::Mutex;
+
+lazy_static! {
+    /// 所有共享内存，以键值索引
+    pub static ref SHARED_MEMORY: Mutex<BTreeMap<usize, Arc<SharedMemory>>> =
+        Mutex::new(BTreeMap::new());
+}
+
+/// 一段共享内存
+pub struct SharedMemory {
+    /// 键值，进程通过它来找到同一段共享内存
+    pub key: usize,
+    /// 创建时申请的大小（字节）
+    pub size: usize,
+    /// 共享内存所拥有的物理页面
+    frames: Vec<FrameTracker>,
+}
+
+impl SharedMemory {
+    /// 按键值打开一段共享内存，如果不存在则创建
+    ///
+    /// 打开已存在的共享内存时，`size` 不能超过其创建时的大小
+    pub fn open(key: usize, size: usize) -> MemoryResult<Arc<Self>> {
+        if size == 0 {
+            return Err("shared memory size cannot be zero");
+        }
+        if let Some(shared_memory) = Self::get(key) {
+            return if size <= shared_memory.size {
+                Ok(shared_memory)
+            } else {
+                Err("shared memory is smaller than requested")
+            };
+        }
+        // 分配物理页面并清零
+        // 分配时可能触发 OOM 回收而 detach 其他共享内存，因此这里不能持有全局表的锁
+        let page_count = (size + PAGE_SIZE - 1) / PAGE_SIZE;
+        let mut frames = Vec::with_capacity(page_count);
+        for _ in 0..page_count {
+            let mut frame = alloc_frame()?;
+            frame.fill(0);
+            frames.push(frame);
+        }
+        // 如果在分配期间已经有人创建了同一键值的共享内存，则使用已有的
+        let shared_memory = SHARED_MEMORY
+            .lock()
+            .entry(key)
+            .or_insert_with(|| Arc::new(Self { key, size, frames }))
+            .clone();
+        Ok(shared_memory)
+    }
+
+    /// 按键值查找已经存在的共享内存
+    pub fn get(key: usize) -> Option<Arc<Self>> {
+        SHARED_MEMORY.lock().get(&key).cloned()
+    }
+
+    /// 解除一次 attach
+    ///
+    /// 如果除了全局表以外，传入的就是最后一份引用，则将其从全局表中移除。
+    /// 函数返回时 `shared_memory` 被 drop，物理页面随之释放。
+    pub fn detach(shared_memory: Arc<Self>) {
+        let mut table = SHARED_MEMORY.lock();
+        // 全局表中一份，参数一份
+        if Arc::strong_count(&shared_memory) == 2 {
+            table.remove(&shared_memory.key);
+        }
+    }
+
+    /// 所占用的页面数量
+    pub fn page_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 所有物理页面
+    pub fn frames(&self) -> &[FrameTracker] {
+        &self.frames
+    }
+}
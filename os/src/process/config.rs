@@ -1,7 +1,33 @@
 //! 定义一些进程相关的常量
 
+use algorithm::RealtimePolicy;
+
 /// 每个线程的运行栈大小 512 KB
 pub const STACK_SIZE: usize = 0x8_0000;
 
-/// 共用的内核栈大小 512 KB
-pub const KERNEL_STACK_SIZE: usize = 0x8_0000;
+/// 线程的默认优先级
+pub const DEFAULT_PRIORITY: usize = 16;
+
+/// 线程优先级的最大值，优先级越高，分到的时间片越多
+pub const MAX_PRIORITY: usize = 255;
+
+/// 默认的时间片长度 10 ms（单位为微秒），各个调度器可以据此为每个线程决定时间片
+pub const TIME_SLICE: usize = 10_000;
+
+/// 实时线程之间的调度策略
+pub const REALTIME_POLICY: RealtimePolicy = RealtimePolicy::EarliestDeadlineFirst;
+
+/// 每个线程的内核栈大小 64 KB
+pub const KERNEL_STACK_SIZE: usize = 0x1_0000;
+
+/// 一次信号量操作最多增减的计数
+pub const SEMAPHORE_OP_LIMIT: usize = 1024;
+
+/// 用户进程默认最多可以占用的内存（字节），`None` 表示不限制
+pub const USER_MEMORY_LIMIT: Option<usize> = None;
+
+/// 用户进程中线程栈、堆和匿名映射可以放置的最低地址
+pub const USER_MAPPING_START: usize = 0x100_0000;
+
+/// 用户进程堆的最大大小 256 MB，创建进程时会为其预留这么大的虚拟地址空间
+pub const USER_HEAP_LIMIT: usize = 0x1000_0000;
@@ -1,40 +1,74 @@
 //! 内核栈 [`KernelStack`]
 //!
 //! 用户态的线程出现中断时，因为用户栈无法保证可用性，中断处理流程必须在内核栈上进行。
-//! 所以我们创建一个公用的内核栈，即当发生中断时，会将 Context 写到内核栈顶。
+//! 每个线程都有自己的内核栈，因此线程可以在内核中途让出 hart（见 [`switch`]），之后再继续执行。
 //!
-//! ### 线程 [`Context`] 的存放
-//! > 1. 线程初始化时，一个 `Context` 放置在内核栈顶，`sp` 指向 `Context` 的位置
-//! >   （即栈顶 - `size_of::<Context>()`）
-//! > 2. 切换到线程，执行 `__restore` 时，将 `Context` 的数据恢复到寄存器中后，
-//! >   会将 `Context` 出栈（即 `sp += size_of::<Context>()`），
-//! >   然后保存 `sp` 至 `sscratch`（此时 `sscratch` 即为内核栈顶）
-//! > 3. 发生中断时，将 `sscratch` 和 `sp` 互换，入栈一个 `Context` 并保存数据
+//! ### 内核栈的布局（从高地址到低地址）
+//! > 1. 线程所在 hart 的编号：从用户态进入中断时，`interrupt.asm` 从这里把 hart 编号读回 `tp`
+//! > 2. 中断时保存的 [`Context`]：执行 `__restore` 时将其出栈，之后 `sscratch` 即指向这里的顶部
+//! > 3. 内核中的调用栈，以及切换出去时 `__switch` 保存的寄存器
+//! > 4. 一个不映射的保护页
 //!
-//! 容易发现，线程的 `Context` 一定保存在内核栈顶。因此，当线程需要运行时，
-//! 从 [`Thread`] 中取出 `Context` 然后置于内核栈顶即可
+//! 内核栈映射在所有地址空间共享的内核区域中，见 [`kernel_space`]
+//!
+//! [`switch`]: super::switch
+//! [`kernel_space`]: crate::memory::mapping::kernel_space
 
+use super::switch::SwitchFrame;
 use super::*;
+use crate::memory::mapping::kernel_space;
 use core::mem::size_of;
 
-/// 内核栈
-#[repr(align(16))]
-#[repr(C)]
-pub struct KernelStack([u8; KERNEL_STACK_SIZE]);
+/// 栈顶之上保存 hart 编号的空间，保持栈顶 16 字节对齐
+const HART_ID_SIZE: usize = 16;
 
-/// 公用的内核栈
-pub static mut KERNEL_STACK: KernelStack = KernelStack([0; KERNEL_STACK_SIZE]);
+/// 线程的内核栈
+pub struct KernelStack {
+    /// 栈所在的虚拟地址区间，不包括保护页
+    range: Range<VirtualAddress>,
+    /// 栈所使用的物理页面
+    frames: Vec<frame::FrameTracker>,
+}
 
 impl KernelStack {
-    /// 在栈顶加入 Context 并且返回新的栈顶指针
-    pub fn push_context(&mut self, context: Context) -> *mut Context {
-        // 栈顶
-        let stack_top = &self.0 as *const _ as usize + size_of::<Self>();
-        // Context 的位置
-        let push_address = (stack_top - size_of::<Context>()) as *mut Context;
-        unsafe {
-            *push_address = context;
-        }
-        push_address
+    /// 分配一个内核栈
+    pub fn new() -> MemoryResult<Self> {
+        let (range, frames) = kernel_space::alloc_guarded(KERNEL_STACK_SIZE / PAGE_SIZE)?;
+        Ok(Self { range, frames })
+    }
+
+    /// 栈顶，其上保存 hart 编号
+    fn top(&self) -> usize {
+        self.range.end.0 - HART_ID_SIZE
+    }
+
+    /// 记录线程接下来在哪个 hart 上执行
+    pub fn set_hart(&self, hart_id: usize) {
+        unsafe { *(self.top() as *mut usize) = hart_id };
+    }
+
+    /// 栈顶的 [`Context`]
+    ///
+    /// 线程在内核中执行时，这里是进入内核前的状态；新线程则是它的初始状态
+    pub fn context(&self) -> &'static mut Context {
+        unsafe { &mut *((self.top() - size_of::<Context>()) as *mut Context) }
+    }
+
+    /// 为新线程准备内核栈，返回 `__switch` 可以切换到的栈指针
+    ///
+    /// 栈顶放入线程的初始 `context`，下面放一组使 `__switch` 切换过来后进入 `__restore` 的寄存器
+    pub fn init(&self, context: Context) -> usize {
+        let context_address = self.context() as *mut Context;
+        unsafe { *context_address = context };
+        let frame_address = context_address as usize - size_of::<SwitchFrame>();
+        unsafe { *(frame_address as *mut SwitchFrame) = SwitchFrame::new_thread(context_address) };
+        frame_address
+    }
+}
+
+/// 释放时先移除映射，再释放物理页面
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        kernel_space::free_guarded(self.range);
     }
 }
@@ -0,0 +1,104 @@
+//! 内核线程 [`kthread_spawn`] 和 [`kthread_join`]
+//!
+//! 内核线程执行一个 Rust 闭包。闭包被装箱后，其指针作为参数传给入口 [`kthread_entry`]；
+//! 入口返回时跳到 [`kthread_exit`]，由它将线程标记为结束，再用 `ebreak` 交给中断处理来切换线程。
+
+use super::*;
+use crate::kernel::Condvar;
+use alloc::boxed::Box;
+use lazy_static::*;
+
+lazy_static! {
+    /// 所有内核线程共享的进程
+    static ref KERNEL_PROCESS: Arc<RwLock<Process>> = Process::new_kernel().unwrap();
+}
+
+/// 内核线程与等待它的线程之间共享的结果
+struct Packet<T> {
+    /// 闭包的返回值，线程结束之前为 `None`
+    result: Lock<Option<T>>,
+    /// 线程结束时通知等待者
+    finished: Condvar,
+}
+
+/// 内核线程的句柄，用 [`kthread_join`] 等待它结束并取得返回值
+///
+/// 直接丢弃句柄不会影响线程执行
+pub struct JoinHandle<T> {
+    /// 线程
+    thread: Arc<Thread>,
+    /// 线程结束后放入的返回值
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// 对应的线程
+    pub fn thread(&self) -> &Arc<Thread> {
+        &self.thread
+    }
+}
+
+/// 创建一个内核线程执行 `f`，返回它的句柄
+pub fn kthread_spawn<F, T>(f: F) -> MemoryResult<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let packet = Arc::new(Packet {
+        result: Lock::new(None),
+        finished: Condvar::default(),
+    });
+    let their_packet = packet.clone();
+    let main: Box<dyn FnOnce()> = Box::new(move || {
+        let result = f();
+        *their_packet.result.get() = Some(result);
+        their_packet.finished.notify_all();
+    });
+    // `Box<dyn FnOnce()>` 是胖指针，再装箱一次才能放进一个寄存器
+    let argument = Box::into_raw(Box::new(main)) as usize;
+    let thread = match Thread::new(
+        KERNEL_PROCESS.clone(),
+        kthread_entry as usize,
+        Some(&[argument]),
+    ) {
+        Ok(thread) => thread,
+        Err(message) => {
+            // 线程没有创建成功，闭包需要在这里释放
+            drop(unsafe { Box::from_raw(argument as *mut Box<dyn FnOnce()>) });
+            return Err(message);
+        }
+    };
+    // 入口函数返回时进入 kthread_exit
+    thread.kernel_stack.context().set_ra(kthread_exit as usize);
+    PROCESSOR.add_thread(thread.clone());
+    Ok(JoinHandle { thread, packet })
+}
+
+/// 等待内核线程结束，返回其闭包的返回值
+///
+/// 会令当前线程休眠，只能在线程中（内核线程或者系统调用中）调用。
+/// 等待期间将调度份额转让给被等待的线程（见 [`Condvar::wait_for`]）
+pub fn kthread_join<T>(handle: JoinHandle<T>) -> T {
+    let mut result = handle.packet.result.get();
+    loop {
+        if let Some(result) = result.take() {
+            return result;
+        }
+        result = handle.packet.finished.wait_for(&handle.thread, result);
+    }
+}
+
+/// 内核线程的入口，执行 `argument` 指向的闭包
+extern "C" fn kthread_entry(argument: usize) {
+    let main = unsafe { Box::from_raw(argument as *mut Box<dyn FnOnce()>) };
+    main();
+}
+
+/// 内核线程的入口返回后跳到这里
+///
+/// 将当前线程标记为结束，然后制造一个中断，交给中断处理结束线程
+extern "C" fn kthread_exit() -> ! {
+    PROCESSOR.get().current_thread().inner().dead = true;
+    unsafe { llvm_asm!("ebreak" :::: "volatile") };
+    unreachable!()
+}
@@ -1,5 +1,7 @@
 //! 一个关闭中断的互斥锁 [`Lock`]
 
+use crate::lockdep::{self, Acquire};
+use core::any::type_name;
 use spin::{Mutex, MutexGuard};
 
 /// 关闭中断的互斥锁
@@ -8,6 +10,8 @@ pub struct Lock<T>(pub(self) Mutex<T>);
 
 /// 封装 [`MutexGuard`] 来实现 drop 时恢复 sstatus
 pub struct LockGuard<'a, T> {
+    /// 所属的锁，用于暂时释放后重新上锁
+    lock: &'a Lock<T>,
     /// 在 drop 时需要先 drop 掉 [`MutexGuard`] 再恢复 sstatus
     guard: Option<MutexGuard<'a, T>>,
     /// 保存的关中断前 sstatus
@@ -16,7 +20,7 @@ pub struct LockGuard<'a, T> {
 
 impl<T> Lock<T> {
     /// 创建一个新对象
-    pub fn new(obj: T) -> Self {
+    pub const fn new(obj: T) -> Self {
         Self(Mutex::new(obj))
     }
 
@@ -26,20 +30,59 @@ impl<T> Lock<T> {
         unsafe {
             llvm_asm!("csrrci $0, sstatus, 1 << 1" : "=r"(sstatus) ::: "volatile");
         }
+        lockdep::acquire(type_name::<Self>(), self.instance(), Acquire::Exclusive);
         LockGuard {
+            lock: self,
             guard: Some(self.0.lock()),
             sstatus,
         }
     }
 
-    /// 不安全：获得不上锁的对象引用
+    /// 尝试获得上锁的对象，已经被持有时返回 `None`
+    pub fn try_get<'a>(&'a self) -> Option<LockGuard<'a, T>> {
+        let sstatus: usize;
+        unsafe {
+            llvm_asm!("csrrci $0, sstatus, 1 << 1" : "=r"(sstatus) ::: "volatile");
+        }
+        match self.0.try_lock() {
+            Some(guard) => {
+                lockdep::acquire(type_name::<Self>(), self.instance(), Acquire::Try);
+                Some(LockGuard {
+                    lock: self,
+                    guard: Some(guard),
+                    sstatus,
+                })
+            }
+            None => {
+                unsafe { llvm_asm!("csrs sstatus, $0" :: "r"(sstatus & 2) :: "volatile") };
+                None
+            }
+        }
+    }
+
+    /// 锁的地址，用于 [`lockdep`] 区分不同的锁
+    fn instance(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl<'a, T> LockGuard<'a, T> {
+    /// 暂时释放锁，执行 `f` 之后重新上锁
     ///
-    /// 这个只用于 [`PROCESSOR::run()`] 时使用
+    /// 期间中断保持关闭。用于 [`Condvar`] 在线程开始休眠之后才释放锁
     ///
-    /// [`PROCESSOR::run()`]: crate::process::processor::Processor::run
-    pub unsafe fn unsafe_get(&self) -> &'static mut T {
-        let addr = &mut *self.0.lock() as *mut T;
-        &mut *addr
+    /// [`Condvar`]: crate::kernel::Condvar
+    pub fn unlocked<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.guard.take();
+        lockdep::release(self.lock.instance());
+        let result = f();
+        lockdep::acquire(
+            type_name::<Lock<T>>(),
+            self.lock.instance(),
+            Acquire::Exclusive,
+        );
+        self.guard = Some(self.lock.0.lock());
+        result
     }
 }
 
@@ -47,6 +90,7 @@ impl<T> Lock<T> {
 impl<'a, T> Drop for LockGuard<'a, T> {
     fn drop(&mut self) {
         self.guard.take();
+        lockdep::release(self.lock.instance());
         unsafe { llvm_asm!("csrs sstatus, $0" :: "r"(self.sstatus & 2) :: "volatile") };
     }
 }
@@ -2,20 +2,38 @@
 
 mod config;
 mod kernel_stack;
+mod kthread;
 mod lock;
+mod oom;
 #[allow(clippy::module_inception)]
 mod process;
 mod processor;
+mod switch;
 mod thread;
 
 use crate::interrupt::*;
+use crate::lockdep::{Mutex, RwLock};
 use crate::memory::*;
-use alloc::{sync::Arc, vec, vec::Vec};
-use spin::{Mutex, RwLock};
+use alloc::{
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
 
 pub use config::*;
-pub use kernel_stack::KERNEL_STACK;
-pub use lock::Lock;
-pub use process::Process;
-pub use processor::PROCESSOR;
-pub use thread::Thread;
+pub use kernel_stack::KernelStack;
+pub use kthread::{kthread_join, kthread_spawn, JoinHandle};
+pub use lock::{Lock, LockGuard};
+pub use process::{CpuTimer, Process, ProcessID};
+pub use processor::{CurrentProcessor, PROCESSOR};
+pub use thread::{Thread, ThreadID, ThreadStats};
+
+/// 初始化进程管理
+///
+/// - 注册物理页面耗尽时的回调 [`oom::out_of_memory`]
+/// - 根据启动参数初始化用户地址空间的随机化 [`aslr::init`]
+pub fn init() {
+    frame::set_oom_handler(oom::out_of_memory);
+    aslr::init();
+    println!("mod process initialized");
+}
@@ -0,0 +1,49 @@
+//! 物理页面耗尽时的处理（OOM killer）
+//!
+//! 物理页面耗尽时，不再让错误一路传递到 `unwrap()` 而使整个内核 panic，
+//! 而是挑选占用内存最多的用户进程将其杀死，回收其内存后重试分配。
+
+use super::*;
+
+/// 物理页面耗尽时调用，杀死占用内存最多的用户进程
+///
+/// 当前进程和正在被修改（无法加锁）的进程不会被选中。返回是否杀死了进程
+pub(super) fn out_of_memory() -> bool {
+    let current_process = PROCESSOR.get().current_process();
+    let mut victim: Option<(usize, Arc<RwLock<Process>>)> = None;
+    for process in Process::all() {
+        if let Some(current_process) = current_process.as_ref() {
+            if Arc::ptr_eq(current_process, &process) {
+                continue;
+            }
+        }
+        let usage = match process.try_read() {
+            Some(process) if process.is_user && !process.killed => process.memory_usage(),
+            _ => continue,
+        };
+        if victim
+            .as_ref()
+            .map_or(true, |(max_usage, _)| usage > *max_usage)
+        {
+            victim = Some((usage, process));
+        }
+    }
+
+    if let Some((usage, process)) = victim {
+        let id = {
+            let mut process = process.write();
+            process.killed = true;
+            // 立即释放其内存，之后该进程的线程都不会再被调度
+            process.memory_set.release_framed();
+            process.id
+        };
+        PROCESSOR.kill_process(&process);
+        println!(
+            "out of memory: killed process {} which used {} frames",
+            id, usage
+        );
+        true
+    } else {
+        false
+    }
+}
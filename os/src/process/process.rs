@@ -1,32 +1,156 @@
 //! 进程 [`Process`]
 
 use super::*;
+use crate::fs::{INode, INodeExt, ROOT_INODE, STDIN, STDOUT};
+use crate::kernel::process_exited;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
 use xmas_elf::ElfFile;
 
+/// 进程 ID
+pub type ProcessID = usize;
+
+static PROCESS_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// 所有进程，用于 OOM 时挑选要杀死的进程
+    static ref PROCESSES: Mutex<Vec<Weak<RwLock<Process>>>> = Mutex::new(Vec::new());
+}
+
 /// 进程的信息
 pub struct Process {
+    /// 进程 ID
+    pub id: ProcessID,
     /// 是否属于用户态
     pub is_user: bool,
+    /// 是否已经被强制结束，其线程不会再被调度
+    pub killed: bool,
     /// 进程中的线程公用页表 / 内存映射
     pub memory_set: MemorySet,
+    /// 堆的区间，起始位置随机选择，其后预留了 [`USER_HEAP_LIMIT`] 的虚拟地址空间。内核进程没有堆
+    pub heap: Option<Range<VirtualAddress>>,
+    /// 只按用户态执行时间计时的间隔定时器
+    pub virtual_timer: CpuTimer,
+    /// 按用户态和内核态执行时间计时的间隔定时器
+    pub profiling_timer: CpuTimer,
+    /// 打开的文件，以文件描述符为下标，已关闭的位置为 `None`。进程中的线程共用
+    pub descriptors: Vec<Option<Arc<dyn INode>>>,
+}
+
+/// 按进程执行时间倒计时的间隔定时器，时间的单位为 `time` 寄存器的计数
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuTimer {
+    /// 距离到期还需要执行的时间，为 0 表示没有启动
+    pub remaining: usize,
+    /// 到期后重新开始计时的间隔，为 0 表示只触发一次
+    pub interval: usize,
+}
+
+impl CpuTimer {
+    /// 计入执行了 `elapsed` 的时间，返回是否因此到期
+    pub fn charge(&mut self, elapsed: usize) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+        if elapsed < self.remaining {
+            self.remaining -= elapsed;
+            return false;
+        }
+        self.remaining = self.interval;
+        true
+    }
 }
 
 #[allow(unused)]
 impl Process {
     /// 创建一个内核进程
     pub fn new_kernel() -> MemoryResult<Arc<RwLock<Self>>> {
-        Ok(Arc::new(RwLock::new(Self {
-            is_user: false,
-            memory_set: MemorySet::new_kernel()?,
-        })))
+        Ok(Self::register(false, MemorySet::new_kernel()?))
     }
 
     /// 创建进程，从文件中读取代码
-    pub fn from_elf(file: &ElfFile, is_user: bool) -> MemoryResult<Arc<RwLock<Self>>> {
-        Ok(Arc::new(RwLock::new(Self {
+    ///
+    /// `memory_limit` 为进程最多可以占用的内存（字节），`None` 表示不限制。
+    /// 限制在映射程序的各个段之前就生效
+    pub fn from_elf(
+        file: &ElfFile,
+        is_user: bool,
+        memory_limit: Option<usize>,
+    ) -> MemoryResult<Arc<RwLock<Self>>> {
+        let frame_limit = memory_limit.map(|limit| limit / PAGE_SIZE);
+        let memory_set = MemorySet::from_elf(file, is_user, frame_limit)?;
+        let process = Self::register(is_user, memory_set);
+        if is_user {
+            process.write().init_heap()?;
+        }
+        Ok(process)
+    }
+
+    /// 从文件系统中找到名为 `name` 的程序，创建用户进程及其第一个线程
+    ///
+    /// 文件不存在、不合法或者内存不足时返回错误。`memory_limit` 见 [`Process::from_elf`]
+    pub fn spawn(name: &str, memory_limit: Option<usize>) -> MemoryResult<Arc<Thread>> {
+        let app = ROOT_INODE.find(name).map_err(|_| "program not found")?;
+        let data = app.readall().map_err(|_| "failed to read program")?;
+        let elf = ElfFile::new(data.as_slice())?;
+        let process = Self::from_elf(&elf, true, memory_limit)?;
+        Thread::new(process, elf.header.pt2.entry_point() as usize, None)
+    }
+
+    /// 分配 ID 并加入进程列表
+    fn register(is_user: bool, memory_set: MemorySet) -> Arc<RwLock<Self>> {
+        let process = Arc::new(RwLock::new(Self {
+            id: PROCESS_COUNTER.fetch_add(1, Ordering::Relaxed) + 1,
             is_user,
-            memory_set: MemorySet::from_elf(file, is_user)?,
-        })))
+            killed: false,
+            memory_set,
+            heap: None,
+            virtual_timer: CpuTimer::default(),
+            profiling_timer: CpuTimer::default(),
+            descriptors: vec![Some(STDIN.clone()), Some(STDOUT.clone())],
+        }));
+        let mut processes = PROCESSES.lock();
+        processes.retain(|process| process.strong_count() > 0);
+        processes.push(Arc::downgrade(&process));
+        process
+    }
+
+    /// 所有仍然存在的进程
+    pub fn all() -> Vec<Arc<RwLock<Self>>> {
+        PROCESSES
+            .lock()
+            .iter()
+            .filter_map(|process| process.upgrade())
+            .collect()
+    }
+
+    /// 取得文件描述符 `fd` 对应的文件
+    pub fn descriptor(&self, fd: usize) -> Option<Arc<dyn INode>> {
+        self.descriptors.get(fd).cloned().flatten()
+    }
+
+    /// 打开一个文件，优先使用已关闭的最小文件描述符，返回分配的文件描述符
+    pub fn add_descriptor(&mut self, inode: Arc<dyn INode>) -> usize {
+        match self.descriptors.iter().position(Option::is_none) {
+            Some(fd) => {
+                self.descriptors[fd] = Some(inode);
+                fd
+            }
+            None => {
+                self.descriptors.push(Some(inode));
+                self.descriptors.len() - 1
+            }
+        }
+    }
+
+    /// 关闭文件描述符 `fd`，返回原来对应的文件
+    pub fn remove_descriptor(&mut self, fd: usize) -> Option<Arc<dyn INode>> {
+        self.descriptors.get_mut(fd).and_then(Option::take)
+    }
+
+    /// 进程占用的物理页面数量，包括数据页面和页表
+    pub fn memory_usage(&self) -> usize {
+        self.memory_set.resident_frames() + self.memory_set.page_table_frames()
     }
 
     /// 分配一定数量的连续虚拟空间
@@ -42,11 +166,7 @@ impl Process {
         // memory_set 只能按页分配，所以让 size 向上取整页
         let alloc_size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
         // 从 memory_set 中找一段不会发生重叠的空间
-        let mut range = Range::<VirtualAddress>::from(0x1000000..0x1000000 + alloc_size);
-        while self.memory_set.overlap_with(range.into()) {
-            range.start += alloc_size;
-            range.end += alloc_size;
-        }
+        let range = self.find_free_range(alloc_size)?;
         // 分配物理页面，建立映射
         self.memory_set.add_segment(
             Segment {
@@ -59,4 +179,72 @@ impl Process {
         // 返回地址区间（使用参数 size，而非向上取整的 alloc_size）
         Ok(Range::from(range.start..(range.start + size)))
     }
+
+    /// 将一段共享内存映射到进程中，返回其起始地址
+    pub fn attach_shared_memory(
+        &mut self,
+        shared_memory: Arc<SharedMemory>,
+    ) -> MemoryResult<VirtualAddress> {
+        let range = self.find_free_range(shared_memory.page_count() * PAGE_SIZE)?;
+        self.memory_set.attach_shared(
+            shared_memory,
+            range.start,
+            Flags::READABLE | Flags::WRITABLE | Flags::user(self.is_user),
+        )?;
+        Ok(range.start)
+    }
+
+    /// 解除 `address` 处的共享内存映射
+    pub fn detach_shared_memory(&mut self, address: VirtualAddress) -> MemoryResult<()> {
+        self.memory_set.detach_shared(address)
+    }
+
+    /// 为进程预留堆的空间，此时堆的大小为 0
+    fn init_heap(&mut self) -> MemoryResult<()> {
+        let range = self.find_free_range(USER_HEAP_LIMIT)?;
+        let heap = Range::from(range.start..range.start);
+        self.memory_set.add_segment(
+            Segment {
+                map_type: MapType::Framed,
+                range: heap,
+                flags: Flags::READABLE | Flags::WRITABLE | Flags::user(self.is_user),
+            },
+            None,
+        )?;
+        self.heap = Some(heap);
+        Ok(())
+    }
+
+    /// 从 `memory_set` 中找一段给定长度（按页对齐）且不会发生重叠的空间
+    ///
+    /// 为堆预留的空间不会被使用。启用 [`aslr`] 时位置是随机的
+    fn find_free_range(&self, alloc_size: usize) -> MemoryResult<Range<VirtualAddress>> {
+        let area = Range::<VirtualPageNumber>::from(
+            VirtualPageNumber::floor(VirtualAddress(USER_MAPPING_START))
+                ..VirtualPageNumber::floor(USER_END_ADDRESS),
+        );
+        let reserved: Vec<Range<VirtualPageNumber>> = self
+            .heap
+            .iter()
+            .map(|heap| {
+                Range::from(
+                    VirtualPageNumber::floor(heap.start)
+                        ..VirtualPageNumber::ceil(heap.start + USER_HEAP_LIMIT),
+                )
+            })
+            .collect();
+        let gaps = self.memory_set.free_gaps(area, &reserved);
+        let page_count = alloc_size / PAGE_SIZE;
+        let start = aslr::place(&gaps, page_count).ok_or("no free virtual address space")?;
+        Ok(Range::from(
+            VirtualAddress::from(start)..VirtualAddress::from(start + page_count),
+        ))
+    }
+}
+
+/// 进程结束时回收它在内核中登记的资源，见 [`process_exited`]
+impl Drop for Process {
+    fn drop(&mut self) {
+        process_exited(self.id);
+    }
 }
@@ -1,148 +1,665 @@
 //! 实现线程的调度和管理 [`Processor`]
 
+use super::switch::__switch;
 use super::*;
+use crate::kernel::process_exited;
+use crate::smp::{self, hart_id, MAX_HARTS};
 use algorithm::*;
+use alloc::collections::BTreeMap;
 use hashbrown::HashSet;
 use lazy_static::*;
+use riscv::register::time;
 
 lazy_static! {
-    /// 全局的 [`Processor`]
-    pub static ref PROCESSOR: UnsafeWrapper<Processor> = Default::default();
+    /// 每个 hart 各自的 [`Processor`]，以 hart 编号索引
+    static ref PROCESSORS: Vec<Lock<Processor>> =
+        (0..MAX_HARTS).map(|hart| Lock::new(Processor::new(hart))).collect();
 }
 
+/// 当前 hart 的 [`Processor`]，见 [`CurrentProcessor`]
+pub static PROCESSOR: CurrentProcessor = CurrentProcessor;
+
+/// 每个 hart 的调度循环切换到线程时，`__switch` 保存的栈指针
+///
+/// 只有对应的 hart 自己会访问
+static mut SCHEDULER_SP: [usize; MAX_HARTS] = [0; MAX_HARTS];
+
 /// 线程调度和管理
 ///
+/// 每个 hart 有一个 `Processor`，各自带有调度器。线程在同一个 hart 上执行、休眠和唤醒，
+/// 只有空闲的 hart 从其他 hart 取来线程时才会迁移（见 [`CurrentProcessor::run`]）。
+///
 /// 休眠线程会从调度器中移除，单独保存。在它们被唤醒之前，不会被调度器安排。
 ///
+/// 线程的状态保存在它自己的内核栈上。线程让出 hart 时切换回调度循环（见 [`CurrentProcessor::run`]），
+/// 再次被调度时从让出的位置继续执行。
+///
 /// # 用例
 /// ### 初始化并运行第一个线程
 /// ```rust
-/// processor.add_thread(thread);
-/// processor.run();
+/// PROCESSOR.add_thread(thread);
+/// PROCESSOR.run();
 /// unreachable!();
 /// ```
 ///
-/// ### 切换线程（在中断中）
+/// ### 切换线程（在时钟中断中）
 /// ```rust
-/// processor.park_current_thread(context);
-/// processor.prepare_next_thread()
+/// PROCESSOR.get().preempt_current_thread();
+/// PROCESSOR.schedule();
 /// ```
 ///
 /// ### 结束线程（在中断中）
 /// ```rust
-/// processor.kill_current_thread();
-/// processor.prepare_next_thread()
+/// PROCESSOR.exit_current_thread();
 /// ```
 ///
-/// ### 休眠线程（在中断中）
+/// ### 休眠线程（可以在系统调用中途）
 /// ```rust
-/// processor.park_current_thread(context);
-/// processor.sleep_current_thread();
-/// processor.prepare_next_thread()
+/// PROCESSOR.get().sleep_current_thread();
+/// PROCESSOR.schedule();
 /// ```
 ///
 /// ### 唤醒线程
 /// 线程会根据调度器分配执行，不一定会立即执行。
 /// ```rust
-/// processor.wake_thread(thread);
+/// PROCESSOR.wake_thread(thread);
 /// ```
-#[derive(Default)]
 pub struct Processor {
-    /// 当前正在执行的线程
+    /// 所属 hart 的编号
+    hart: usize,
+    /// 当前正在执行的线程，空闲时为 `None`
     current_thread: Option<Arc<Thread>>,
     /// 线程调度器，记录活跃线程
     scheduler: SchedulerImpl<Arc<Thread>>,
+    /// 实时调度类，其中的线程不在 `scheduler` 中，并且总是优先执行
+    realtime: RealtimeScheduler<Arc<Thread>>,
+    /// 可以被调度的线程（包括当前线程），用于负载均衡
+    runnable_threads: HashSet<Arc<Thread>>,
     /// 保存休眠线程
     sleeping_threads: HashSet<Arc<Thread>>,
+    /// 预约了唤醒时间的休眠线程，按（唤醒时间，线程 ID）排序
+    ///
+    /// 线程被提前唤醒或者结束时不从这里移除，到时间后再丢弃（见 [`Processor::wake_expired`]）
+    timers: BTreeMap<(usize, ThreadID), Arc<Thread>>,
+    /// 上一次统计线程执行时间时 `time` 寄存器的值
+    last_switch: usize,
+    /// 切换到另一个线程的次数
+    switches: usize,
+    /// 上一次打印统计报告时的 `switches` 和 `time` 寄存器的值，用于计算切换频率
+    reported: (usize, usize),
+    /// 已经移除、等待丢弃的线程
+    ///
+    /// 这里可能是线程的最后一个引用，丢弃线程可能连带丢弃进程并关闭它的文件，进而唤醒等待这些文件的线程，
+    /// 需要再次给 `Processor` 上锁。因此只把线程放在这里，由这个 hart 的调度循环在不持有任何锁时丢弃
+    /// （见 [`CurrentProcessor::drop_released_threads`]）
+    released_threads: Vec<Arc<Thread>>,
 }
 
-#[allow(unused)]
-impl Processor {
-    /// 获取一个当前线程的 `Arc` 引用
-    pub fn current_thread(&self) -> Arc<Thread> {
-        self.current_thread.as_ref().unwrap().clone()
+/// 访问当前 hart 的 [`Processor`]
+///
+/// 每个 `Processor` 都由 [`Lock`] 保护，因为其他 hart 唤醒线程、结束进程或者取走线程时也会访问它。
+/// [`CurrentProcessor::get`] 得到的锁应当在语句结束时就释放，持有时不能再次上锁；
+/// 需要访问其他 hart 或者需要等待中断的操作由 `CurrentProcessor` 自己完成
+pub struct CurrentProcessor;
+
+impl CurrentProcessor {
+    /// 获得当前 hart 的 [`Processor`] 并上锁
+    pub fn get(&self) -> LockGuard<'static, Processor> {
+        loop {
+            let hart = hart_id();
+            let processor = PROCESSORS[hart].get();
+            // 上锁后中断已经关闭，不会再被换到其他 hart 上；
+            // 内核线程如果在读取编号和上锁之间被换走，则重新来过
+            if hart_id() == hart {
+                return processor;
+            }
+        }
     }
 
-    /// 第一次开始运行
+    /// 当前 hart 的调度循环
     ///
-    /// 从 `current_thread` 中取出 [`Context`]，然后直接调用 `interrupt.asm` 中的 `__restore`
-    /// 来从 `Context` 中继续执行该线程。
+    /// 选出下一个线程，用 `__switch` 切换到它的内核栈，直到它让出 hart 再切换回来。
+    /// 当前 hart 没有可以执行的线程时，先尝试从其他 hart 取来一个，仍然没有则等待中断。
     ///
-    /// 注意调用 `run()` 的线程会就此步入虚无，不再被使用
-    pub fn run(&mut self) -> ! {
-        // interrupt.asm 中的标签
-        extern "C" {
-            fn __restore(context: usize);
+    /// 注意调用 `run()` 的栈从此成为这个 hart 调度循环的栈，不再返回
+    pub fn run(&self) -> ! {
+        let hart = hart_id();
+        loop {
+            let next_thread = self.get().prepare_next_thread();
+            self.drop_released_threads(hart);
+            if let Some(thread) = next_thread {
+                // 切换到线程，直到它让出 hart
+                // 这里持有线程的引用，线程结束后在切换回来时才释放它的内核栈
+                unsafe { __switch(&mut SCHEDULER_SP[hart], thread.kernel_sp()) };
+                continue;
+            }
+            if self.steal_thread() {
+                continue;
+            }
+            if Thread::count() == 0 {
+                // 所有线程都已经结束，则退出
+                panic!("all threads terminated, shutting down");
+            }
+            // 有休眠线程或者其他 hart 上的线程，则等待中断（包括其他 hart 发来的核间中断）
+            // 只在有线程需要按时醒来时预约时钟中断
+            let timeout = self.get().next_timeout();
+            crate::interrupt::wait_for_interrupt(timeout);
         }
-        // 从 current_thread 中取出 Context
-        if self.current_thread.is_none() {
-            panic!("no thread to run, shutting down");
+    }
+
+    /// 当前线程让出 hart，切换回调度循环；线程再次被调度时从这里返回
+    ///
+    /// 调用之前需要先告知 `Processor` 线程接下来的状态（被抢占或者休眠），并且不能持有任何锁
+    pub fn schedule(&self) {
+        let kernel_sp = self.get().current_thread().kernel_sp_ptr();
+        unsafe { __switch(kernel_sp, SCHEDULER_SP[hart_id()]) };
+    }
+
+    /// 结束当前线程并切换回调度循环，不再返回
+    pub fn exit_current_thread(&self) -> ! {
+        let kernel_sp = {
+            let mut processor = self.get();
+            let kernel_sp = processor.current_thread().kernel_sp_ptr();
+            processor.kill_current_thread();
+            kernel_sp
+        };
+        unsafe { __switch(kernel_sp, SCHEDULER_SP[hart_id()]) };
+        unreachable!()
+    }
+
+    /// 添加一个待执行的线程，放到可执行线程最少的 hart 上
+    pub fn add_thread(&self, thread: Arc<Thread>) {
+        let hart = (0..MAX_HARTS)
+            .filter(|&hart| smp::is_online(hart))
+            .min_by_key(|&hart| PROCESSORS[hart].get().runnable_threads.len())
+            .unwrap_or_else(hart_id);
+        PROCESSORS[hart].get().add_thread(thread);
+        if hart != hart_id() {
+            smp::send_ipi(hart);
         }
-        let context = self.current_thread().prepare();
-        // 从此将没有回头
-        unsafe {
-            __restore(context as usize);
+    }
+
+    /// 唤醒一个休眠线程
+    ///
+    /// 线程回到它休眠时所在 hart 的调度器中，如果不是当前 hart，用核间中断通知它。
+    /// 返回是否唤醒了线程，见 [`Processor::wake_thread`]
+    pub fn wake_thread(&self, thread: Arc<Thread>) -> bool {
+        let hart = thread.inner().hart;
+        let woken = PROCESSORS[hart].get().wake_thread(&thread);
+        if !woken {
+            // 线程可能已经随进程被结束，这里或许是它的最后一个引用，交给调度循环丢弃
+            self.get().released_threads.push(thread);
+        } else if hart != hart_id() {
+            smp::send_ipi(hart);
         }
-        unreachable!()
+        woken
+    }
+
+    /// 设置线程的优先级，线程可能在其他 hart 上
+    pub fn set_priority(&self, thread: &Arc<Thread>, priority: usize) {
+        let hart = thread.inner().hart;
+        PROCESSORS[hart].get().set_priority(thread, priority);
+    }
+
+    /// 将线程加入实时调度类，或修改其参数，线程可能在其他 hart 上
+    ///
+    /// 见 [`Processor::set_realtime`]
+    pub fn set_realtime(
+        &self,
+        thread: &Arc<Thread>,
+        params: Option<RealtimeParams>,
+    ) -> Result<(), &'static str> {
+        let hart = thread.inner().hart;
+        PROCESSORS[hart].get().set_realtime(thread, params)
+    }
+
+    /// 强制结束一个进程在所有 hart 上的线程
+    ///
+    /// 见 [`Processor::kill_process`]。进程打开的文件随即关闭，使管道等的另一端能够结束等待；
+    /// 它在内核中登记的其他资源也随即回收，见 [`process_exited`]
+    pub fn kill_process(&self, process: &Arc<RwLock<Process>>) {
+        for hart in 0..MAX_HARTS {
+            PROCESSORS[hart].get().kill_process(process);
+        }
+        // 关闭文件时会唤醒等待的线程，不能持有进程的锁
+        let descriptors = core::mem::take(&mut process.write().descriptors);
+        drop(descriptors);
+        let id = process.read().id;
+        process_exited(id);
+    }
+
+    /// 在调度循环中丢弃 hart 上已经移除的线程，见 [`Processor::released_threads`]
+    fn drop_released_threads(&self, hart: usize) {
+        let threads = core::mem::take(&mut PROCESSORS[hart].get().released_threads);
+        drop(threads);
     }
 
-    /// 激活下一个线程的 `Context`
-    pub fn prepare_next_thread(&mut self) -> *mut Context {
+    /// 打印所有 hart 的调度统计
+    ///
+    /// 见 [`Processor::report`]
+    pub fn report(&self) {
+        println!("[sched] {} threads", Thread::count());
+        for (hart, processor) in PROCESSORS.iter().enumerate() {
+            if smp::is_online(hart) {
+                processor.get().report();
+            }
+        }
+    }
+
+    /// 从可执行线程最多的其他 hart 取来一个线程，返回是否成功
+    ///
+    /// 对其他 hart 只尝试上锁，避免两个 hart 互相取线程时死锁
+    fn steal_thread(&self) -> bool {
+        let current = hart_id();
+        let busiest = (0..MAX_HARTS)
+            .filter(|&hart| hart != current && smp::is_online(hart))
+            .max_by_key(|&hart| {
+                PROCESSORS[hart]
+                    .try_get()
+                    .map_or(0, |processor| processor.runnable_threads.len())
+            });
+        let thread = busiest.and_then(|hart| PROCESSORS[hart].try_get()?.take_waiting_thread());
+        match thread {
+            Some(thread) => {
+                self.get().add_thread(thread);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[allow(unused)]
+impl Processor {
+    /// 创建 hart 编号为 `hart` 的 `Processor`
+    fn new(hart: usize) -> Self {
+        Self {
+            hart,
+            current_thread: None,
+            scheduler: Default::default(),
+            realtime: RealtimeScheduler::new(REALTIME_POLICY),
+            runnable_threads: Default::default(),
+            sleeping_threads: Default::default(),
+            timers: BTreeMap::new(),
+            last_switch: 0,
+            switches: 0,
+            reported: (0, 0),
+            released_threads: Vec::new(),
+        }
+    }
+
+    /// 获取一个当前线程的 `Arc` 引用
+    pub fn current_thread(&self) -> Arc<Thread> {
+        self.current_thread.as_ref().unwrap().clone()
+    }
+
+    /// 获取当前线程，空闲时返回 `None`
+    pub fn try_current_thread(&self) -> Option<Arc<Thread>> {
+        self.current_thread.clone()
+    }
+
+    /// 获取当前线程所属的进程，没有当前线程时返回 `None`
+    pub fn current_process(&self) -> Option<Arc<RwLock<Process>>> {
+        self.current_thread
+            .as_ref()
+            .map(|thread| thread.process.clone())
+    }
+
+    /// 当前线程的优先级，没有当前线程时返回默认优先级
+    pub fn current_priority(&self) -> usize {
+        self.current_thread
+            .as_ref()
+            .map_or(DEFAULT_PRIORITY, |thread| thread.inner().priority)
+    }
+
+    /// 选出并准备下一个线程，没有可以执行的线程时返回 `None`
+    pub fn prepare_next_thread(&mut self) -> Option<Arc<Thread>> {
+        self.account_current_thread(false);
+        self.wake_expired();
         loop {
-            // 向调度器询问下一个线程
-            if let Some(next_thread) = self.scheduler.get_next() {
+            // 优先执行实时线程，再向调度器询问下一个线程
+            let next_thread = match self.realtime.get_next(time::read()) {
+                Some(thread) => Some(thread),
+                None => self.scheduler.get_next(),
+            };
+            if let Some(next_thread) = next_thread {
+                // 所属进程已经被强制结束，则移除该线程
+                if next_thread.process.read().killed {
+                    self.remove_thread(&next_thread);
+                    self.released_threads.push(next_thread);
+                    continue;
+                }
                 // 准备下一个线程
-                let context = next_thread.prepare();
-                self.current_thread = Some(next_thread);
-                return context;
+                next_thread.prepare();
+                self.count_switch(&next_thread);
+                self.set_time_slice(&next_thread);
+                self.current_thread = Some(next_thread.clone());
+                self.last_switch = time::read();
+                return Some(next_thread);
             } else {
-                // 没有活跃线程
-                if self.sleeping_threads.is_empty() {
-                    // 也没有休眠线程，则退出
-                    panic!("all threads terminated, shutting down");
-                } else {
-                    // 有休眠线程，则等待中断
-                    crate::interrupt::wait_for_interrupt();
-                }
+                // 没有活跃线程，当前 hart 进入空闲
+                self.current_thread = None;
+                return None;
             }
         }
     }
 
     /// 添加一个待执行的线程
     pub fn add_thread(&mut self, thread: Arc<Thread>) {
-        if self.current_thread.is_none() {
-            self.current_thread = Some(thread.clone());
+        let priority = {
+            let mut inner = thread.inner();
+            inner.hart = self.hart;
+            inner.priority
+        };
+        self.runnable_threads.insert(thread.clone());
+        self.scheduler.add_thread(thread, priority);
+    }
+
+    /// 交出一个等待执行的线程（不是当前线程，也不是实时线程），用于负载均衡
+    ///
+    /// 只有不少于两个可执行线程时才会交出
+    pub fn take_waiting_thread(&mut self) -> Option<Arc<Thread>> {
+        if self.runnable_threads.len() < 2 {
+            return None;
         }
-        self.scheduler.add_thread(thread, 0);
+        let current_thread = &self.current_thread;
+        let realtime = &self.realtime;
+        let thread = self
+            .runnable_threads
+            .iter()
+            .find(|thread| current_thread.as_ref() != Some(*thread) && !realtime.contains(thread))?
+            .clone();
+        self.runnable_threads.remove(&thread);
+        self.scheduler.remove_thread(&thread);
+        Some(thread)
     }
 
     /// 唤醒一个休眠线程
-    pub fn wake_thread(&mut self, thread: Arc<Thread>) {
-        thread.inner().sleeping = false;
-        self.sleeping_threads.remove(&thread);
-        self.scheduler.add_thread(thread, 0);
+    ///
+    /// 返回是否唤醒了线程。线程可能已经随进程被强制结束，或者已经被唤醒，此时返回 `false`
+    pub fn wake_thread(&mut self, thread: &Arc<Thread>) -> bool {
+        if !self.sleeping_threads.remove(thread) {
+            return false;
+        }
+        {
+            let mut inner = thread.inner();
+            inner.sleeping = false;
+            inner.wakeup_at = None;
+            inner.stats.ready_since = time::read();
+        }
+        self.runnable_threads.insert(thread.clone());
+        if self.realtime.contains(thread) {
+            self.realtime.wake(thread);
+            return true;
+        }
+        // 收回休眠期间转让出去的调度份额
+        self.scheduler.revoke(thread);
+        let priority = thread.inner().priority;
+        self.scheduler.add_thread(thread.clone(), priority);
+        true
     }
 
-    /// 保存当前线程的 `Context`
-    pub fn park_current_thread(&mut self, context: &Context) {
-        self.current_thread().park(*context);
+    /// 将线程加入实时调度类，或修改其参数；`params` 为 `None` 时将其移回普通调度器
+    ///
+    /// 加入实时调度类需要通过可调度性检查
+    pub fn set_realtime(
+        &mut self,
+        thread: &Arc<Thread>,
+        params: Option<RealtimeParams>,
+    ) -> Result<(), &'static str> {
+        let sleeping = thread.inner().sleeping;
+        let was_realtime = self.realtime.contains(thread);
+        match params {
+            Some(params) => {
+                self.realtime.admit(thread.clone(), params, time::read())?;
+                if !was_realtime {
+                    if sleeping {
+                        self.scheduler.forget_thread(thread);
+                        self.realtime.block(thread);
+                    } else {
+                        self.scheduler.remove_thread(thread);
+                    }
+                }
+            }
+            None => {
+                if let Some(stats) = self.realtime.remove(thread) {
+                    report_realtime(thread, stats);
+                    if !sleeping {
+                        let priority = thread.inner().priority;
+                        self.scheduler.add_thread(thread.clone(), priority);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置线程的优先级
+    ///
+    /// 休眠线程不在调度器中，只记录优先级，在唤醒时生效
+    pub fn set_priority(&mut self, thread: &Arc<Thread>, priority: usize) {
+        let sleeping = {
+            let mut inner = thread.inner();
+            inner.priority = priority;
+            inner.sleeping
+        };
+        if !sleeping && !self.realtime.contains(thread) {
+            self.scheduler.set_priority(thread.clone(), priority);
+        }
+    }
+
+    /// 当前线程被时钟中断打断，通知调度器
+    ///
+    /// 返回是否有被打断的线程，hart 空闲时返回 `false`
+    pub fn preempt_current_thread(&mut self) -> bool {
+        self.account_current_thread(false);
+        if let Some(thread) = &self.current_thread {
+            thread.inner().stats.ready_since = self.last_switch;
+            if !self.realtime.contains(thread) {
+                self.scheduler.timer_preempted(thread);
+            }
+            true
+        } else {
+            false
+        }
     }
 
     /// 令当前线程进入休眠
     pub fn sleep_current_thread(&mut self) {
         // 从 current_thread 中取出
         let current_thread = self.current_thread();
+        // 在移出调度器之前统计执行时间
+        self.account_current_thread(false);
         // 记为 sleeping
-        current_thread.inner().sleeping = true;
+        {
+            let mut inner = current_thread.inner();
+            inner.sleeping = true;
+            inner.stats.voluntary_switches += 1;
+        }
         // 从 scheduler 移出到 sleeping_threads 中
-        self.scheduler.remove_thread(&current_thread);
+        if self.realtime.contains(&current_thread) {
+            self.realtime.block(&current_thread);
+        } else {
+            self.scheduler.block_thread(&current_thread);
+        }
+        self.runnable_threads.remove(&current_thread);
         self.sleeping_threads.insert(current_thread);
     }
 
+    /// 令当前线程进入休眠，最晚在 `time` 寄存器到达 `deadline` 时被唤醒
+    pub fn sleep_current_thread_until(&mut self, deadline: usize) {
+        let current_thread = self.current_thread();
+        self.sleep_current_thread();
+        current_thread.inner().wakeup_at = Some(deadline);
+        self.timers
+            .insert((deadline, current_thread.id), current_thread);
+    }
+
+    /// 唤醒到达预约时间的线程
+    ///
+    /// 线程如果已经被提前唤醒，或者又因为其他原因休眠，预约的时间会被清除或者改变，此时直接丢弃
+    fn wake_expired(&mut self) {
+        let now = time::read();
+        while let Some(&(deadline, id)) = self.timers.keys().next() {
+            if deadline > now {
+                break;
+            }
+            let thread = self.timers.remove(&(deadline, id)).unwrap();
+            let expired = thread.inner().wakeup_at == Some(deadline);
+            if expired {
+                self.wake_thread(&thread);
+            }
+            self.released_threads.push(thread);
+        }
+    }
+
+    /// 最近一次需要时钟中断的时间：实时线程进入新的周期，或者休眠线程到达预约时间
+    fn next_timeout(&self) -> Option<usize> {
+        let timer = self.timers.keys().next().map(|&(deadline, _)| deadline);
+        match (self.realtime.next_release(), timer) {
+            (Some(release), Some(timer)) => Some(release.min(timer)),
+            (release, timer) => release.or(timer),
+        }
+    }
+
+    /// 将当前线程自上次统计以来实际执行的时间告知调度器，并计入线程的统计
+    ///
+    /// `user_mode` 表示这段时间线程是否在用户态执行：从用户态进入中断时为 `true`，
+    /// 其他时候（返回用户态之前、切换线程时）为 `false`。返回计入的时间，没有当前线程时为 0
+    pub fn account_current_thread(&mut self, user_mode: bool) -> usize {
+        let now = time::read();
+        let elapsed = match &self.current_thread {
+            Some(thread) => {
+                let elapsed = now.wrapping_sub(self.last_switch);
+                {
+                    let stats = &mut thread.inner().stats;
+                    if user_mode {
+                        stats.user_time += elapsed;
+                    } else {
+                        stats.system_time += elapsed;
+                    }
+                }
+                if self.realtime.contains(thread) {
+                    self.realtime.thread_ran(thread, elapsed);
+                } else {
+                    self.scheduler.thread_ran(thread, elapsed);
+                }
+                elapsed
+            }
+            None => 0,
+        };
+        self.last_switch = now;
+        elapsed
+    }
+
+    /// 记录即将切换到 `next_thread`
+    ///
+    /// 统计它等待调度的时间；如果上一个线程没有休眠或结束，却换成了其他线程，则记为被抢占
+    fn count_switch(&mut self, next_thread: &Arc<Thread>) {
+        let now = time::read();
+        {
+            let stats = &mut next_thread.inner().stats;
+            stats.wait_time += now.wrapping_sub(stats.ready_since);
+            stats.last_hart = self.hart;
+        }
+        if self.current_thread.as_ref() == Some(next_thread) {
+            return;
+        }
+        self.switches += 1;
+        if let Some(previous) = &self.current_thread {
+            let mut inner = previous.inner();
+            if !inner.sleeping {
+                inner.stats.involuntary_switches += 1;
+            }
+        }
+    }
+
+    /// 按照线程的时间片预约下一次时钟中断
+    ///
+    /// 实时线程的时间片为它剩余的预算，其他线程由调度器根据 [`TIME_SLICE`] 决定。
+    /// 如果有实时线程更早进入新的周期，或者休眠线程更早到达预约时间，则提前打断，让调度器重新选择
+    fn set_time_slice(&self, thread: &Arc<Thread>) {
+        let slice = match self.realtime.time_slice(thread) {
+            Some(budget) => budget,
+            None => self.scheduler.time_slice(thread, us_to_ticks(TIME_SLICE)),
+        };
+        let mut timeout = time::read() + slice;
+        if let Some(next_timeout) = self.next_timeout() {
+            timeout = timeout.min(next_timeout);
+        }
+        set_timeout(timeout);
+    }
+
+    /// 打印这个 hart 的调度统计：切换次数和频率，以及运行队列的长度
+    ///
+    /// 频率按上一次打印以来的切换次数计算
+    pub fn report(&mut self) {
+        let now = time::read();
+        let (reported_switches, reported_time) = self.reported;
+        let elapsed = now.wrapping_sub(reported_time).max(1);
+        let rate = (self.switches - reported_switches) as u128 * clock_frequency() as u128
+            / elapsed as u128;
+        println!(
+            "[sched] hart {}: {} runnable, {} sleeping, {} switches ({}/s)",
+            self.hart,
+            self.runnable_threads.len(),
+            self.sleeping_threads.len(),
+            self.switches,
+            rate
+        );
+        self.reported = (self.switches, now);
+    }
+
+    /// 当前线程即将休眠等待 `holder`，将调度份额暂时转让给它
+    ///
+    /// 需要在 [`Processor::sleep_current_thread`] 之前调用，线程被唤醒时自动收回
+    pub fn donate_current_thread(&mut self, holder: &Arc<Thread>) {
+        let current_thread = self.current_thread();
+        self.scheduler.donate(&current_thread, holder);
+    }
+
     /// 终止当前的线程
     pub fn kill_current_thread(&mut self) {
         // 从调度器中移除
         let thread = self.current_thread.take().unwrap();
-        self.scheduler.remove_thread(&thread);
+        self.remove_thread(&thread);
+        self.released_threads.push(thread);
+    }
+
+    /// 将一个可以被调度的线程从所在的调度器中移除
+    fn remove_thread(&mut self, thread: &Arc<Thread>) {
+        self.runnable_threads.remove(thread);
+        match self.realtime.remove(thread) {
+            Some(stats) => report_realtime(thread, stats),
+            None => self.scheduler.remove_thread(thread),
+        }
     }
+
+    /// 强制结束一个进程的所有线程
+    ///
+    /// 休眠的线程立即移除；调度器中的线程会在被调度到时移除（见 [`Processor::prepare_next_thread`]）
+    pub fn kill_process(&mut self, process: &Arc<RwLock<Process>>) {
+        let killed: Vec<_> = self
+            .sleeping_threads
+            .iter()
+            .filter(|thread| Arc::ptr_eq(&thread.process, process))
+            .cloned()
+            .collect();
+        for thread in killed {
+            self.sleeping_threads.remove(&thread);
+            match self.realtime.remove(&thread) {
+                Some(stats) => report_realtime(&thread, stats),
+                None => self.scheduler.forget_thread(&thread),
+            }
+            self.released_threads.push(thread);
+        }
+    }
+}
+
+/// 线程离开实时调度类时，打印其错过截止时间的情况
+fn report_realtime(thread: &Arc<Thread>, stats: RealtimeStats) {
+    println!(
+        "realtime thread {} missed {} deadlines in {} periods",
+        thread.id, stats.misses, stats.periods
+    );
 }
@@ -0,0 +1,56 @@
+# 内核栈之间的切换
+#
+# __switch(current_sp: *mut usize, next_sp: usize)
+# 在当前栈上保存 ra 和 s0 ~ s11，将栈指针写入 current_sp，
+# 然后切换到 next_sp 所指的栈，恢复那里保存的寄存器并返回
+#
+# 其他寄存器按照调用约定由调用者保存；tp 中是 hart 编号，不随栈切换
+
+# 寄存器宽度对应的字节数
+.set    REG_SIZE, 8
+# 保存的寄存器组大小，ra + s0 ~ s11，再补一个使 sp 保持 16 字节对齐
+.set    SWITCH_SIZE, 14
+
+    .section .text
+    .globl __switch
+__switch:
+    addi    sp, sp, -SWITCH_SIZE * REG_SIZE
+    sd      ra, 0 * REG_SIZE(sp)
+    sd      s0, 1 * REG_SIZE(sp)
+    sd      s1, 2 * REG_SIZE(sp)
+    sd      s2, 3 * REG_SIZE(sp)
+    sd      s3, 4 * REG_SIZE(sp)
+    sd      s4, 5 * REG_SIZE(sp)
+    sd      s5, 6 * REG_SIZE(sp)
+    sd      s6, 7 * REG_SIZE(sp)
+    sd      s7, 8 * REG_SIZE(sp)
+    sd      s8, 9 * REG_SIZE(sp)
+    sd      s9, 10 * REG_SIZE(sp)
+    sd      s10, 11 * REG_SIZE(sp)
+    sd      s11, 12 * REG_SIZE(sp)
+    # 保存当前的栈指针
+    sd      sp, 0(a0)
+
+    # 切换到另一个栈
+    mv      sp, a1
+    ld      ra, 0 * REG_SIZE(sp)
+    ld      s0, 1 * REG_SIZE(sp)
+    ld      s1, 2 * REG_SIZE(sp)
+    ld      s2, 3 * REG_SIZE(sp)
+    ld      s3, 4 * REG_SIZE(sp)
+    ld      s4, 5 * REG_SIZE(sp)
+    ld      s5, 6 * REG_SIZE(sp)
+    ld      s6, 7 * REG_SIZE(sp)
+    ld      s7, 8 * REG_SIZE(sp)
+    ld      s8, 9 * REG_SIZE(sp)
+    ld      s9, 10 * REG_SIZE(sp)
+    ld      s10, 11 * REG_SIZE(sp)
+    ld      s11, 12 * REG_SIZE(sp)
+    addi    sp, sp, SWITCH_SIZE * REG_SIZE
+    ret
+
+    .globl __enter_thread
+# 新线程第一次被切换到时从这里开始：s0 中是内核栈顶的 Context，交给 __restore 进入线程
+__enter_thread:
+    mv      a0, s0
+    j       __restore
@@ -0,0 +1,39 @@
+//! 内核栈之间的切换 `__switch`
+//!
+//! 每个 hart 有一个调度循环（见 [`CurrentProcessor::run`]），运行在这个 hart 的启动栈上。
+//! 调度循环选出线程后，用 `__switch` 切换到线程的内核栈；线程需要让出 hart 时，
+//! 再用 `__switch` 切换回调度循环。因此线程可以在内核中的任何位置（例如系统调用中途）让出 hart，
+//! 之后从同一个位置继续执行。
+//!
+//! [`CurrentProcessor::run`]: super::CurrentProcessor::run
+
+use super::*;
+
+global_asm!(include_str!("./switch.asm"));
+
+extern "C" {
+    /// 保存当前的寄存器和栈指针到 `current_sp`，切换到 `next_sp` 所指的栈
+    pub fn __switch(current_sp: *mut usize, next_sp: usize);
+    /// 新线程的入口，见 [`SwitchFrame::new_thread`]
+    fn __enter_thread();
+}
+
+/// `__switch` 在栈上保存的寄存器
+#[repr(C)]
+#[derive(Default)]
+pub struct SwitchFrame {
+    ra: usize,
+    s: [usize; 12],
+    /// 保持 16 字节对齐
+    _padding: usize,
+}
+
+impl SwitchFrame {
+    /// 新线程的寄存器组：切换过来后，以内核栈顶的 `context` 进入 `__restore`
+    pub fn new_thread(context: *mut Context) -> Self {
+        let mut frame = Self::default();
+        frame.ra = __enter_thread as usize;
+        frame.s[0] = context as usize;
+        frame
+    }
+}
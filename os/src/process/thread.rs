@@ -1,13 +1,24 @@
 //! 线程 [`Thread`]
 
 use super::*;
-use crate::fs::*;
+use crate::kernel::sync::deadlock;
+use crate::lockdep::MutexGuard;
+use crate::smp::hart_id;
+use alloc::collections::BTreeMap;
 use core::hash::{Hash, Hasher};
+use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::time;
 
 /// 线程 ID 使用 `isize`，可以用负数表示错误
 pub type ThreadID = isize;
 
-static mut THREAD_COUNTER: ThreadID = 0;
+static THREAD_COUNTER: AtomicIsize = AtomicIsize::new(0);
+
+lazy_static! {
+    /// 所有线程，以线程 ID 索引
+    static ref THREADS: Mutex<BTreeMap<ThreadID, Weak<Thread>>> = Mutex::new(BTreeMap::new());
+}
 
 /// 线程的信息
 pub struct Thread {
@@ -17,43 +28,68 @@ pub struct Thread {
     pub stack: Range<VirtualAddress>,
     /// 所属的进程
     pub process: Arc<RwLock<Process>>,
+    /// 线程的内核栈
+    pub kernel_stack: KernelStack,
+    /// 线程让出 hart 时 `__switch` 保存的内核栈指针
+    kernel_sp: AtomicUsize,
     /// 用 `Mutex` 包装一些可变的变量
     pub inner: Mutex<ThreadInner>,
 }
 
 /// 线程中需要可变的部分
 pub struct ThreadInner {
-    /// 线程执行上下文
-    ///
-    /// 当且仅当线程被暂停执行时，`context` 为 `Some`
-    pub context: Option<Context>,
     /// 是否进入休眠
     pub sleeping: bool,
     /// 是否已经结束
     pub dead: bool,
-    /// 打开的文件
-    pub descriptors: Vec<Arc<dyn INode>>,
+    /// 优先级，休眠和唤醒时保持不变
+    pub priority: usize,
+    /// 所在 hart 的编号，线程在这个 hart 的调度器中执行、休眠和唤醒
+    pub hart: usize,
+    /// 休眠时预约的唤醒时间（`time` 寄存器的值），被唤醒后清除
+    pub wakeup_at: Option<usize>,
+    /// 调度统计，由 [`Processor`] 更新
+    pub stats: ThreadStats,
+}
+
+/// 线程的调度统计，时间的单位为 `time` 寄存器的计数
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadStats {
+    /// 在用户态执行的时间
+    pub user_time: usize,
+    /// 在内核态执行的时间（包括内核线程的全部执行时间）
+    pub system_time: usize,
+    /// 可以执行但在等待调度的时间
+    pub wait_time: usize,
+    /// 主动让出 hart（休眠）的次数
+    pub voluntary_switches: usize,
+    /// 被抢占而换成其他线程的次数
+    pub involuntary_switches: usize,
+    /// 上一次在哪个 hart 上执行
+    pub last_hart: usize,
+    /// 上一次开始等待调度的时间
+    pub ready_since: usize,
 }
 
 impl Thread {
     /// 准备执行一个线程
     ///
-    /// 激活对应进程的页表，并返回其 Context
-    pub fn prepare(&self) -> *mut Context {
+    /// 激活对应进程的页表，并在内核栈上记录线程将在当前 hart 上执行
+    pub fn prepare(&self) {
         // 激活页表
         self.process.write().memory_set.activate();
-        // 取出 Context
-        let parked_frame = self.inner().context.take().unwrap();
-        // 将 Context 放至内核栈顶
-        unsafe { KERNEL_STACK.push_context(parked_frame) }
+        self.kernel_stack.set_hart(hart_id());
+    }
+
+    /// 线程让出 hart 时保存内核栈指针的位置，交给 `__switch` 写入
+    pub fn kernel_sp_ptr(&self) -> *mut usize {
+        // AtomicUsize 与 usize 的内存布局相同，并且允许通过共享引用修改
+        &self.kernel_sp as *const AtomicUsize as *mut usize
     }
 
-    /// 发生时钟中断后暂停线程，保存状态
-    pub fn park(&self, context: Context) {
-        // 检查目前线程内的 context 应当为 None
-        assert!(self.inner().context.is_none());
-        // 将 Context 保存到线程中
-        self.inner().context.replace(context);
+    /// 线程上一次让出 hart 时的内核栈指针，调度时交给 `__switch` 切换过去
+    pub fn kernel_sp(&self) -> usize {
+        self.kernel_sp.load(Ordering::Acquire)
     }
 
     /// 创建一个线程
@@ -62,6 +98,8 @@ impl Thread {
         entry_point: usize,
         arguments: Option<&[usize]>,
     ) -> MemoryResult<Arc<Thread>> {
+        // 分配内核栈
+        let kernel_stack = KernelStack::new()?;
         // 让所属进程分配并映射一段空间，作为线程的栈
         let stack = process
             .write()
@@ -75,30 +113,60 @@ impl Thread {
             process.read().is_user,
         );
 
+        // 将 Context 放在内核栈顶，第一次被调度时从这里进入线程
+        let kernel_sp = kernel_stack.init(context);
+
+        // 继承当前线程的优先级
+        let priority = PROCESSOR.get().current_priority();
+
         // 打包成线程
         let thread = Arc::new(Thread {
-            id: unsafe {
-                THREAD_COUNTER += 1;
-                THREAD_COUNTER
-            },
+            id: THREAD_COUNTER.fetch_add(1, Ordering::Relaxed) + 1,
             stack,
             process,
+            kernel_stack,
+            kernel_sp: AtomicUsize::new(kernel_sp),
             inner: Mutex::new(ThreadInner {
-                context: Some(context),
                 sleeping: false,
                 dead: false,
-                descriptors: vec![STDIN.clone(), STDOUT.clone()],
+                priority,
+                hart: hart_id(),
+                wakeup_at: None,
+                stats: ThreadStats {
+                    last_hart: hart_id(),
+                    ready_since: time::read(),
+                    ..ThreadStats::default()
+                },
             }),
         });
+        THREADS.lock().insert(thread.id, Arc::downgrade(&thread));
 
         Ok(thread)
     }
 
-    pub fn inner(&self) -> spin::MutexGuard<ThreadInner> {
+    /// 通过线程 ID 查找线程
+    pub fn find(id: ThreadID) -> Option<Arc<Thread>> {
+        THREADS.lock().get(&id).and_then(|thread| thread.upgrade())
+    }
+
+    /// 还没有结束的线程数量
+    pub fn count() -> usize {
+        THREADS.lock().len()
+    }
+
+    pub fn inner(&self) -> MutexGuard<ThreadInner> {
         self.inner.lock()
     }
 }
 
+/// 线程结束时从线程表和死锁检测的等待图中移除
+impl Drop for Thread {
+    fn drop(&mut self) {
+        THREADS.lock().remove(&self.id);
+        deadlock::forget(self.id);
+    }
+}
+
 /// 通过线程 ID 来判等
 impl PartialEq for Thread {
     fn eq(&self, other: &Self) -> bool {
@@ -127,7 +195,7 @@ impl core::fmt::Debug for Thread {
             .debug_struct("Thread")
             .field("thread_id", &self.id)
             .field("stack", &self.stack)
-            .field("context", &self.inner().context)
+            .field("context", self.kernel_stack.context())
             .finish()
     }
 }
@@ -16,6 +16,26 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// SBI v0.2 起的扩展调用，返回 `(error, value)`
+#[inline(always)]
+fn sbi_call_extension(
+    extension: usize,
+    function: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+) -> (isize, usize) {
+    let (error, value);
+    unsafe {
+        llvm_asm!("ecall"
+            : "={x10}" (error), "={x11}" (value)
+            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x16}" (function), "{x17}" (extension)
+            : "memory"
+            : "volatile");
+    }
+    (error, value)
+}
+
 const SBI_SET_TIMER: usize = 0;
 const SBI_CONSOLE_PUTCHAR: usize = 1;
 const SBI_CONSOLE_GETCHAR: usize = 2;
@@ -26,6 +46,10 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
+/// Hart State Management 扩展
+const SBI_EXT_HSM: usize = 0x48534D;
+const SBI_HSM_HART_START: usize = 0;
+
 /// 向控制台输出一个字符
 ///
 /// 需要注意我们不能直接使用 Rust 中的 char 类型
@@ -50,3 +74,37 @@ pub fn shutdown() -> ! {
 pub fn set_timer(time: usize) {
     sbi_call(SBI_SET_TIMER, time, 0, 0);
 }
+
+/// 让 `hart_id` 从物理地址 `start_address` 开始执行，`opaque` 会放在它的 `a1` 寄存器中
+///
+/// 返回是否启动成功（hart 不存在、已经在运行或者固件不支持 HSM 时会失败）
+pub fn hart_start(hart_id: usize, start_address: usize, opaque: usize) -> bool {
+    let (error, _) = sbi_call_extension(
+        SBI_EXT_HSM,
+        SBI_HSM_HART_START,
+        hart_id,
+        start_address,
+        opaque,
+    );
+    error == 0
+}
+
+/// 向 `hart_mask` 中的 hart 发送核间中断
+pub fn send_ipi(hart_mask: usize) {
+    sbi_call(SBI_SEND_IPI, &hart_mask as *const usize as usize, 0, 0);
+}
+
+/// 清除当前 hart 待处理的核间中断
+pub fn clear_ipi() {
+    sbi_call(SBI_CLEAR_IPI, 0, 0, 0);
+}
+
+/// 让 `hart_mask` 中的 hart 刷新全部 TLB
+pub fn remote_sfence_vma(hart_mask: usize) {
+    sbi_call(
+        SBI_REMOTE_SFENCE_VMA,
+        &hart_mask as *const usize as usize,
+        0,
+        usize::MAX,
+    );
+}
@@ -0,0 +1,106 @@
+//! 多核（SMP）支持
+//!
+//! - 内核中 `tp` 寄存器始终保存当前 hart 的编号（见 `entry.asm` 和 `interrupt.asm`），
+//!   通过 [`hart_id`] 读取
+//! - 第一个进入 `rust_main` 的 hart 负责初始化，完成后通过 SBI HSM 扩展启动其他 hart；
+//!   对于不支持 HSM、所有 hart 同时进入 `_start` 的固件，其他 hart 会等待初始化完成
+//! - hart 之间用核间中断（IPI）通知有线程被唤醒，修改页表后通知正在使用它的其他 hart 刷新 TLB
+
+use crate::memory::*;
+use crate::sbi;
+use alloc::vec::Vec;
+use core::sync::atomic::{spin_loop_hint, AtomicBool, AtomicUsize, Ordering};
+use lazy_static::*;
+
+/// 最多支持的 hart 数量，需要和 `entry.asm` 中的 `MAX_HARTS` 保持一致
+pub const MAX_HARTS: usize = 4;
+
+/// 是否已经有 hart 负责初始化
+static BOOT_HART_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// 初始化是否已经完成
+static BOOTED: AtomicBool = AtomicBool::new(false);
+
+/// 已经开始运行的 hart，每一位表示一个 hart
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    /// 每个 hart 当前加载的根页表的页号，以 hart 编号索引，还没有加载时为 0
+    static ref ACTIVE_ROOTS: Vec<AtomicUsize> =
+        (0..MAX_HARTS).map(|_| AtomicUsize::new(0)).collect();
+}
+
+/// 当前 hart 的编号
+pub fn hart_id() -> usize {
+    let hart_id: usize;
+    unsafe { llvm_asm!("mv $0, tp" : "=r"(hart_id) ::: "volatile") };
+    hart_id
+}
+
+/// 尝试成为负责初始化的 hart，只有第一个调用者返回 `true`
+pub fn claim_boot_hart() -> bool {
+    !BOOT_HART_CLAIMED.swap(true, Ordering::AcqRel)
+}
+
+/// 等待负责初始化的 hart 完成初始化
+pub fn wait_for_boot() {
+    while !BOOTED.load(Ordering::Acquire) {
+        spin_loop_hint();
+    }
+}
+
+/// 将当前 hart 标记为已经开始运行
+pub fn set_online() {
+    ONLINE_HARTS.fetch_or(1 << hart_id(), Ordering::AcqRel);
+}
+
+/// 某个 hart 是否已经开始运行
+pub fn is_online(hart_id: usize) -> bool {
+    ONLINE_HARTS.load(Ordering::Acquire) & (1 << hart_id) != 0
+}
+
+/// 初始化完成后，启动其他 hart
+///
+/// 其他 hart 同样从 `_start` 开始执行，`dtb_pa` 会作为第二个参数传给它们的 `rust_main`。
+/// 已经在运行或者不存在的 hart 会启动失败，直接忽略即可
+pub fn start_secondary_harts(dtb_pa: PhysicalAddress) {
+    extern "C" {
+        /// `entry.asm` 中的入口
+        fn _start();
+    }
+    set_online();
+    BOOTED.store(true, Ordering::Release);
+    // 其他 hart 启动时还没有开启分页，需要使用入口的物理地址
+    let start_address = PhysicalAddress::from(VirtualAddress(_start as usize));
+    for hart in (0..MAX_HARTS).filter(|&hart| hart != hart_id()) {
+        sbi::hart_start(hart, start_address.0, dtb_pa.0);
+    }
+}
+
+/// 用核间中断通知另一个 hart
+pub fn send_ipi(hart_id: usize) {
+    sbi::send_ipi(1 << hart_id);
+}
+
+/// 记录当前 hart 即将加载以 `root_ppn` 为根的页表，由 [`Mapping::activate`] 在写入 `satp` 之前调用
+///
+/// [`Mapping::activate`]: crate::memory::mapping::Mapping::activate
+pub fn set_active_root(root_ppn: PhysicalPageNumber) {
+    ACTIVE_ROOTS[hart_id()].store(root_ppn.0, Ordering::SeqCst);
+}
+
+/// 修改以 `root_ppn` 为根的页表后，让其他正在使用它的 hart 刷新 TLB
+///
+/// 只通知当前加载了同一页表，即当前线程属于同一个进程的 hart。
+/// 其他 hart 之后加载这个页表时会在 [`Mapping::activate`] 中刷新 TLB，不会用到旧的映射
+///
+/// [`Mapping::activate`]: crate::memory::mapping::Mapping::activate
+pub fn shootdown_tlb(root_ppn: PhysicalPageNumber) {
+    let others = (0..MAX_HARTS)
+        .filter(|&hart| hart != hart_id() && is_online(hart))
+        .filter(|&hart| ACTIVE_ROOTS[hart].load(Ordering::SeqCst) == root_ppn.0)
+        .fold(0, |mask, hart| mask | 1 << hart);
+    if others != 0 {
+        sbi::remote_sfence_vma(others);
+    }
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::sync::Mutex;
+use user_lib::{sys_futex, sys_shmat, sys_shmdt, sys_shmget, sys_spawn, FUTEX_WAIT, FUTEX_WAKE};
+
+/// 与 `futex_child` 共用的共享内存键值
+const KEY: usize = 0xf07;
+/// 每个进程加一的次数
+const ROUNDS: usize = 1000;
+
+/// 放在共享内存中的数据，全部为 0 时是合法的初始状态
+#[repr(C)]
+struct Shared {
+    counter: Mutex<usize>,
+    /// `futex_child` 完成后置 1 并唤醒
+    done: AtomicU32,
+}
+
+/// futex：值不符时立即返回，以及放在共享内存中的 [`Mutex`] 在两个进程之间互斥
+#[no_mangle]
+pub fn main() -> isize {
+    let word = AtomicU32::new(1);
+    assert_eq!(sys_futex(&word, FUTEX_WAIT, 0), -1);
+    assert_eq!(sys_futex(&word, FUTEX_WAKE, 1), 0);
+
+    let id = sys_shmget(KEY, size_of::<Shared>());
+    assert!(id >= 0);
+    let address = sys_shmat(id as usize);
+    assert!(address > 0);
+    let shared = unsafe { &*(address as *const Shared) };
+
+    let pid = sys_spawn("futex_child", 0);
+    assert!(pid > 0);
+    for _ in 0..ROUNDS {
+        *shared.counter.lock() += 1;
+    }
+    while shared.done.load(Ordering::Acquire) == 0 {
+        sys_futex(&shared.done, FUTEX_WAIT, 0);
+    }
+    let counter = *shared.counter.lock();
+    println!("futex: counter is {}", counter);
+    assert_eq!(counter, 2 * ROUNDS);
+    assert_eq!(sys_shmdt(address as usize), 0);
+    println!("futex: passed");
+    0
+}
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::mem::size_of;
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::sync::Mutex;
+use user_lib::{sys_futex, sys_shmat, sys_shmdt, sys_shmget, FUTEX_WAKE};
+
+/// 与 `futex` 共用的键值和数据布局
+const KEY: usize = 0xf07;
+const ROUNDS: usize = 1000;
+
+#[repr(C)]
+struct Shared {
+    counter: Mutex<usize>,
+    done: AtomicU32,
+}
+
+/// 由 `futex` 启动：与父进程竞争同一个锁加一，完成后通过 futex 唤醒父进程
+#[no_mangle]
+pub fn main() -> isize {
+    let id = sys_shmget(KEY, size_of::<Shared>());
+    assert!(id >= 0);
+    let address = sys_shmat(id as usize);
+    assert!(address > 0);
+    let shared = unsafe { &*(address as *const Shared) };
+    for _ in 0..ROUNDS {
+        *shared.counter.lock() += 1;
+    }
+    shared.done.store(1, Ordering::Release);
+    sys_futex(&shared.done, FUTEX_WAKE, 1);
+    println!("futex_child: done");
+    assert_eq!(sys_shmdt(address as usize), 0);
+    0
+}
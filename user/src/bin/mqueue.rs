@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const NAME: &str = "/mqueue_test";
+
+/// 消息队列：按优先级取出、非阻塞打开、容量上限和删除
+#[no_mangle]
+pub fn main() -> isize {
+    // 超过上限的队列无法创建
+    assert_eq!(sys_mq_open(NAME, MQ_CREATE, 0x10000, 32), -1);
+    assert_eq!(sys_mq_open(NAME, MQ_CREATE, 4, 0), -1);
+
+    let fd = sys_mq_open(NAME, MQ_CREATE, 4, 32);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    for &(message, priority) in &[("low", 1), ("high", 5), ("middle", 3), ("high again", 5)] {
+        assert_eq!(sys_mq_send(fd, message.as_bytes(), priority, 0), 0);
+    }
+
+    // 队列已满，非阻塞的描述符立即失败
+    let nonblocking = sys_mq_open(NAME, MQ_NONBLOCK, 0, 0);
+    assert!(nonblocking >= 0);
+    let nonblocking = nonblocking as usize;
+    assert_eq!(sys_mq_send(nonblocking, b"overflow", 0, 0), -1);
+
+    let mut buffer = [0u8; 32];
+    let mut priority = 0;
+    for &expected in &["high", "high again", "middle", "low"] {
+        let length = sys_mq_receive(fd, &mut buffer, &mut priority, 0);
+        assert!(length >= 0);
+        let message = core::str::from_utf8(&buffer[..length as usize]).unwrap();
+        println!("received {:?} with priority {}", message, priority);
+        assert_eq!(message, expected);
+    }
+    assert_eq!(
+        sys_mq_receive(nonblocking, &mut buffer, &mut priority, 0),
+        -1
+    );
+    // 等待 10ms 后超时
+    assert_eq!(sys_mq_receive(fd, &mut buffer, &mut priority, 10_000), -1);
+
+    // 删除后名字不再可用，已经打开的描述符仍然有效
+    assert_eq!(sys_mq_unlink(NAME), 0);
+    assert_eq!(sys_mq_unlink(NAME), -1);
+    assert_eq!(sys_mq_open(NAME, 0, 0, 0), -1);
+    assert_eq!(sys_mq_send(fd, b"still open", 0, 0), 0);
+    assert_eq!(
+        sys_mq_receive(nonblocking, &mut buffer, &mut priority, 0),
+        10
+    );
+
+    sys_close(fd);
+    sys_close(nonblocking);
+    println!("mqueue test passed");
+    0
+}
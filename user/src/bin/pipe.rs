@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sys_close, sys_dup, sys_pipe, sys_spawn, sys_write, STDIN};
+
+const MESSAGE: &str = "hello from the other end of the pipe";
+
+/// 通过管道把数据交给另一个进程：将管道的读端放在标准输入上，再启动 `pipe_reader`，
+/// 子进程继承文件描述符后从标准输入读到文件末尾
+#[no_mangle]
+pub fn main() -> isize {
+    let mut fds = [0usize; 2];
+    assert_eq!(sys_pipe(&mut fds), 0);
+    let [reader, writer] = fds;
+
+    // 写端关闭之前写入全部数据，数据不超过管道的容量，不会阻塞
+    assert_eq!(
+        sys_write(writer, MESSAGE.as_bytes()),
+        MESSAGE.len() as isize
+    );
+    assert_eq!(sys_close(writer), 0);
+
+    // 用读端取代标准输入
+    assert_eq!(sys_close(STDIN), 0);
+    assert_eq!(sys_dup(reader), STDIN as isize);
+    assert_eq!(sys_close(reader), 0);
+
+    let pid = sys_spawn("pipe_reader", 0);
+    println!("spawned pipe_reader: pid {}", pid);
+    assert!(pid > 0);
+
+    // 子进程持有自己的读端，这里关闭后它仍然可以读完数据
+    assert_eq!(sys_close(STDIN), 0);
+    0
+}
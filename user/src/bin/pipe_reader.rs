@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{sys_read, STDIN};
+
+/// 从标准输入读到文件末尾（所有写端都已关闭），由 `pipe` 启动
+#[no_mangle]
+pub fn main() -> isize {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 16];
+    loop {
+        match sys_read(STDIN, &mut buffer) {
+            0 => break,
+            length if length > 0 => data.extend_from_slice(&buffer[..length as usize]),
+            _ => panic!("failed to read stdin"),
+        }
+    }
+    let text = core::str::from_utf8(&data).unwrap();
+    println!("pipe_reader received {} bytes: {}", data.len(), text);
+    assert_eq!(text, "hello from the other end of the pipe");
+    0
+}
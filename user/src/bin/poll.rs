@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    sys_close, sys_pipe, sys_poll, sys_read, sys_select, sys_write, PollFd, POLLIN, POLLNVAL,
+    POLLOUT,
+};
+
+/// 用一个管道检查 poll 和 select：写入前读端不可读、超时返回 0，写入后可读，写端关闭后读端可读到文件末尾
+#[no_mangle]
+pub fn main() -> isize {
+    let mut fds = [0usize; 2];
+    assert_eq!(sys_pipe(&mut fds), 0);
+    let [reader, writer] = fds;
+
+    // 管道为空：读端没有事件，写端可以写入
+    let mut items = [
+        PollFd {
+            fd: reader,
+            events: POLLIN,
+            revents: 0,
+        },
+        PollFd {
+            fd: writer,
+            events: POLLOUT,
+            revents: 0,
+        },
+    ];
+    assert_eq!(sys_poll(&mut items[..1], 10_000), 0);
+    assert_eq!(sys_poll(&mut items, 0), 1);
+    assert_eq!(items[0].revents, 0);
+    assert_eq!(items[1].revents, POLLOUT);
+    println!("poll: empty pipe times out");
+
+    // 写入之后读端可读
+    assert_eq!(sys_write(writer, b"ping"), 4);
+    assert_eq!(sys_poll(&mut items[..1], -1), 1);
+    assert_eq!(items[0].revents, POLLIN);
+
+    // select 使用位图，返回时只保留就绪的文件描述符
+    let mut read = 1 << reader;
+    let mut write = 1 << writer;
+    assert_eq!(sys_select(&mut read, &mut write, 0), 2);
+    assert_eq!((read, write), (1 << reader, 1 << writer));
+    println!("poll: select reports both ends ready");
+
+    let mut buffer = [0u8; 4];
+    assert_eq!(sys_read(reader, &mut buffer), 4);
+    assert_eq!(&buffer, b"ping");
+    let mut read = 1 << reader;
+    let mut write = 0;
+    assert_eq!(sys_select(&mut read, &mut write, 10_000), 0);
+    assert_eq!(read, 0);
+
+    // 写端关闭后读端可读（读到文件末尾）
+    assert_eq!(sys_close(writer), 0);
+    assert_eq!(sys_poll(&mut items[..1], -1), 1);
+    assert_eq!(items[0].revents, POLLIN);
+    assert_eq!(sys_read(reader, &mut buffer), 0);
+
+    // 已经关闭的文件描述符
+    assert_eq!(sys_poll(&mut items[1..], 0), 1);
+    assert_eq!(items[1].revents, POLLNVAL);
+    assert_eq!(sys_close(reader), 0);
+    println!("poll: passed");
+    0
+}
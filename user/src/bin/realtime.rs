@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// 把当前线程加入实时调度类，检查可调度性检查和参数验证，再移回普通调度器
+#[no_mangle]
+pub fn main() -> isize {
+    // 每 10ms 执行 2ms
+    let attr = RealtimeAttr {
+        period: 10_000,
+        budget: 2_000,
+        deadline: 10_000,
+    };
+    assert_eq!(sys_sched_setattr(0, Some(&attr)), 0);
+
+    // 预算超过截止时间的参数不合法
+    let invalid = RealtimeAttr {
+        period: 10_000,
+        budget: 5_000,
+        deadline: 4_000,
+    };
+    assert_eq!(sys_sched_setattr(0, Some(&invalid)), -1);
+
+    // 实时线程执行一段时间，每个周期用完预算后让出 hart
+    let mut sum = 0usize;
+    for i in 0..2_000_000 {
+        sum = sum.wrapping_add(i);
+    }
+    let mut usage = Rusage::default();
+    sys_getrusage(0, &mut usage);
+    println!(
+        "realtime thread: sum {}, user {}us, waited {}us, preempted {} times",
+        sum, usage.user_time, usage.wait_time, usage.involuntary_switches
+    );
+
+    assert_eq!(sys_sched_setattr(0, None), 0);
+    println!("realtime test passed");
+    0
+}
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sys_semclaim, sys_semget, sys_semop, sys_spawn};
+
+/// 与 `semaphore_holder` 共用的键值
+const KEY: usize = 0x5e4;
+/// `semaphore_holder` 获得计数后通知的信号量
+const READY_KEY: usize = 0x5e5;
+
+/// 信号量：一次性的 P 操作、银行家算法的声明，以及进程结束时归还计数
+#[no_mangle]
+pub fn main() -> isize {
+    let id = sys_semget(KEY, 2) as usize;
+    let ready = sys_semget(READY_KEY, 0) as usize;
+
+    // 超过单次操作的上限
+    assert_eq!(sys_semop(id, isize::MIN), -1);
+
+    // 声明最大需求后，超过声明的请求直接失败
+    assert_eq!(sys_semclaim(id, 2), 0);
+    assert_eq!(sys_semop(id, -3), -1);
+    assert_eq!(sys_semop(id, -2), 0);
+    assert_eq!(sys_semop(id, -1), -1);
+    assert_eq!(sys_semop(id, 2), 0);
+    assert_eq!(sys_semclaim(id, 0), 0);
+
+    // 子进程获得全部计数后不释放就退出，它的计数在退出时归还
+    let pid = sys_spawn("semaphore_holder", 0);
+    assert!(pid > 0);
+    assert_eq!(sys_semop(ready, -1), 0);
+    println!(
+        "process {} holds the semaphore, waiting for it to exit",
+        pid
+    );
+    assert_eq!(sys_semop(id, -2), 0);
+    assert_eq!(sys_semop(id, 2), 0);
+    println!("semaphore test passed");
+    0
+}
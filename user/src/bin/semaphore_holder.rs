@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sys_semclaim, sys_semget, sys_semop};
+
+/// 与 `semaphore` 共用的键值
+const KEY: usize = 0x5e4;
+const READY_KEY: usize = 0x5e5;
+
+/// 由 `semaphore` 启动：经银行家算法获得全部计数，通知后不释放就退出
+#[no_mangle]
+pub fn main() -> isize {
+    let id = sys_semget(KEY, 0) as usize;
+    let ready = sys_semget(READY_KEY, 0) as usize;
+    assert_eq!(sys_semclaim(id, 2), 0);
+    assert_eq!(sys_semop(id, -2), 0);
+    println!("semaphore_holder exits without releasing");
+    assert_eq!(sys_semop(ready, 1), 0);
+    0
+}
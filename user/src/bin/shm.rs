@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::slice::from_raw_parts_mut;
+use user_lib::{sys_semget, sys_semop, sys_shmat, sys_shmdt, sys_shmget, sys_spawn};
+
+/// 与 `shm_child` 共用的键值
+const KEY: usize = 0x54d;
+/// `shm_child` 写回结果后通知的信号量
+const DONE_KEY: usize = 0x54e;
+/// 共享内存中 `usize` 的数量，正好一页
+const LENGTH: usize = 512;
+
+/// 共享内存：创建后内容为 0，写入的数据对另一个进程可见，解除映射后不能再次解除
+#[no_mangle]
+pub fn main() -> isize {
+    assert_eq!(sys_shmget(KEY, 0), -1);
+    let id = sys_shmget(KEY, LENGTH * 8);
+    assert!(id >= 0);
+    // 已经存在时不能要求更大的空间
+    assert_eq!(sys_shmget(KEY, LENGTH * 16), -1);
+    assert_eq!(sys_shmget(KEY, LENGTH), id);
+
+    let address = sys_shmat(id as usize);
+    assert!(address > 0);
+    let data = unsafe { from_raw_parts_mut(address as *mut usize, LENGTH) };
+    assert!(data.iter().all(|value| *value == 0));
+    for (i, value) in data.iter_mut().enumerate() {
+        *value = i;
+    }
+
+    // 子进程求和后写入第一项
+    let done = sys_semget(DONE_KEY, 0) as usize;
+    let pid = sys_spawn("shm_child", 0);
+    assert!(pid > 0);
+    assert_eq!(sys_semop(done, -1), 0);
+    println!("shm: process {} wrote {}", pid, data[0]);
+    assert_eq!(data[0], LENGTH * (LENGTH - 1) / 2);
+
+    assert_eq!(sys_shmdt(address as usize), 0);
+    assert_eq!(sys_shmdt(address as usize), -1);
+    println!("shm: passed");
+    0
+}
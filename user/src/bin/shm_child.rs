@@ -0,0 +1,28 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::slice::from_raw_parts_mut;
+use user_lib::{sys_semget, sys_semop, sys_shmat, sys_shmdt, sys_shmget};
+
+/// 与 `shm` 共用的键值
+const KEY: usize = 0x54d;
+const DONE_KEY: usize = 0x54e;
+const LENGTH: usize = 512;
+
+/// 由 `shm` 启动：映射同一段共享内存，将所有数据的和写入第一项
+#[no_mangle]
+pub fn main() -> isize {
+    let id = sys_shmget(KEY, LENGTH * 8);
+    assert!(id >= 0);
+    let address = sys_shmat(id as usize);
+    assert!(address > 0);
+    let data = unsafe { from_raw_parts_mut(address as *mut usize, LENGTH) };
+    data[0] = data.iter().sum();
+    println!("shm_child: sum is {}", data[0]);
+    assert_eq!(sys_shmdt(address as usize), 0);
+    assert_eq!(sys_semop(sys_semget(DONE_KEY, 0) as usize, 1), 0);
+    0
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// 本地套接字：流式连接、数据报，以及关闭和删除绑定的路径
+#[no_mangle]
+pub fn main() -> isize {
+    let mut buffer = [0u8; 32];
+
+    // 流式套接字
+    let server = sys_socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(sys_bind(server, "/socket_stream"), 0);
+    assert_eq!(sys_listen(server, 1), 0);
+    let client = sys_socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(sys_connect(client, "/socket_stream"), 0);
+    let connection = sys_accept(server);
+    assert!(connection >= 0);
+    let connection = connection as usize;
+    assert_eq!(sys_send(client, b"ping"), 4);
+    assert_eq!(sys_recv(connection, &mut buffer), 4);
+    assert_eq!(&buffer[..4], b"ping");
+    // 对方关闭后读到末尾
+    assert_eq!(sys_close(client), 0);
+    assert_eq!(sys_recv(connection, &mut buffer), 0);
+    assert_eq!(sys_close(connection), 0);
+
+    // 关闭后路径仍然被占用，但不能再连接，删除后可以重新绑定
+    assert_eq!(sys_close(server), 0);
+    let client = sys_socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(sys_connect(client, "/socket_stream"), -1);
+    let server = sys_socket(AF_UNIX, SOCK_STREAM) as usize;
+    assert_eq!(sys_bind(server, "/socket_stream"), -1);
+    assert_eq!(sys_unlink("/socket_stream"), 0);
+    assert_eq!(sys_unlink("/socket_stream"), -1);
+    assert_eq!(sys_bind(server, "/socket_stream"), 0);
+    assert_eq!(sys_unlink("/socket_stream"), 0);
+    sys_close(client);
+    sys_close(server);
+
+    // 数据报套接字保留边界
+    let receiver = sys_socket(AF_UNIX, SOCK_DGRAM) as usize;
+    assert_eq!(sys_bind(receiver, "/socket_dgram"), 0);
+    let sender = sys_socket(AF_UNIX, SOCK_DGRAM) as usize;
+    assert_eq!(sys_connect(sender, "/socket_dgram"), 0);
+    assert_eq!(sys_send(sender, b"first"), 5);
+    assert_eq!(sys_send(sender, b"second"), 6);
+    assert_eq!(sys_recv(receiver, &mut buffer), 5);
+    assert_eq!(sys_recv(receiver, &mut buffer), 6);
+    assert_eq!(&buffer[..6], b"second");
+    // 接收方关闭后发送失败
+    assert_eq!(sys_close(receiver), 0);
+    assert_eq!(sys_send(sender, b"lost"), -1);
+    assert_eq!(sys_unlink("/socket_dgram"), 0);
+    sys_close(sender);
+
+    println!("socket test passed");
+    0
+}
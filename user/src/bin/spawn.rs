@@ -0,0 +1,36 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::config::USER_HEAP_SIZE;
+use user_lib::sys_spawn;
+
+/// 用内存限制启动子进程：限制小于程序本身（包括静态的堆空间）时加载失败
+#[no_mangle]
+pub fn main() -> isize {
+    let pid = sys_spawn("hello_world", 0);
+    println!("spawn hello_world without limit: pid {}", pid);
+    assert!(pid > 0);
+
+    let pid = sys_spawn("hello_world", USER_HEAP_SIZE / 2);
+    println!(
+        "spawn hello_world with {} bytes: {}",
+        USER_HEAP_SIZE / 2,
+        pid
+    );
+    assert_eq!(pid, -1);
+
+    let pid = sys_spawn("hello_world", USER_HEAP_SIZE * 4);
+    println!(
+        "spawn hello_world with {} bytes: pid {}",
+        USER_HEAP_SIZE * 4,
+        pid
+    );
+    assert!(pid > 0);
+
+    assert_eq!(sys_spawn("no_such_program", 0), -1);
+    println!("spawn test passed");
+    0
+}
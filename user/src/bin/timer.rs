@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    sys_close, sys_getitimer, sys_poll, sys_read, sys_setitimer, sys_sigpending, sys_sigtimedwait,
+    sys_timerfd_create, sys_timerfd_settime, ITimerVal, PollFd, ITIMER_PROF, ITIMER_REAL,
+    ITIMER_VIRTUAL, POLLIN, SIGALRM, SIGPROF, SIGVTALRM,
+};
+
+/// 检查间隔定时器、信号和定时器文件：
+/// 周期性的 `ITIMER_REAL` 多次发送 `SIGALRM`，按执行时间计时的定时器在忙等时到期，定时器文件到期后可读
+#[no_mangle]
+pub fn main() -> isize {
+    let mut old = ITimerVal::default();
+
+    // 每 10ms 到期一次，等到三次 SIGALRM 后停止
+    let periodic = ITimerVal {
+        interval: 10_000,
+        value: 10_000,
+    };
+    assert_eq!(sys_setitimer(ITIMER_REAL, &periodic, &mut old), 0);
+    assert_eq!(old.value, 0);
+    for _ in 0..3 {
+        assert_eq!(sys_sigtimedwait(1 << SIGALRM, 1_000_000), SIGALRM as isize);
+    }
+    let mut current = ITimerVal::default();
+    assert_eq!(sys_getitimer(ITIMER_REAL, &mut current), 0);
+    assert_eq!(current.interval, 10_000);
+    assert_eq!(
+        sys_setitimer(ITIMER_REAL, &ITimerVal::default(), &mut old),
+        0
+    );
+    assert_eq!(old.interval, 10_000);
+    // 停止之前可能又到期了一次，先取走；之后不再收到信号
+    sys_sigtimedwait(1 << SIGALRM, 1);
+    assert_eq!(sys_sigtimedwait(1 << SIGALRM, 30_000), -1);
+    println!("timer: received SIGALRM three times");
+
+    // 按执行时间计时的定时器只在进程执行时倒计时，忙等直到两个信号都到达
+    let once = ITimerVal {
+        interval: 0,
+        value: 5_000,
+    };
+    assert_eq!(sys_setitimer(ITIMER_VIRTUAL, &once, &mut old), 0);
+    assert_eq!(sys_setitimer(ITIMER_PROF, &once, &mut old), 0);
+    let expected = (1 << SIGVTALRM) | (1 << SIGPROF);
+    while sys_sigpending() as usize & expected != expected {}
+    assert_eq!(sys_sigtimedwait(expected, 0), SIGVTALRM as isize);
+    assert_eq!(sys_sigtimedwait(expected, 0), SIGPROF as isize);
+    assert_eq!(sys_sigpending() as usize & expected, 0);
+    println!("timer: cpu timers expired");
+
+    // 定时器文件到期后可读，读到到期次数
+    let fd = sys_timerfd_create();
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut items = [PollFd {
+        fd,
+        events: POLLIN,
+        revents: 0,
+    }];
+    assert_eq!(sys_poll(&mut items, 0), 0);
+    let timeout = ITimerVal {
+        interval: 0,
+        value: 20_000,
+    };
+    assert_eq!(sys_timerfd_settime(fd, &timeout, &mut old), 0);
+    assert_eq!(sys_poll(&mut items, 1_000_000), 1);
+    let mut expirations = [0u8; 8];
+    assert_eq!(sys_read(fd, &mut expirations), 8);
+    assert_eq!(u64::from_le_bytes(expirations), 1);
+    assert_eq!(sys_close(fd), 0);
+    println!("timer: passed");
+    0
+}
@@ -2,6 +2,7 @@
 //!
 //! - 动态内存分配（允许使用 alloc，但总大小固定）
 //! - 错误处理（打印信息并退出程序）
+//! - 多线程同步（基于 futex 的 [`sync`] 模块）
 
 #![no_std]
 #![feature(llvm_asm)]
@@ -10,6 +11,7 @@
 #![feature(linkage)]
 
 pub mod config;
+pub mod sync;
 pub mod syscall;
 
 #[macro_use]
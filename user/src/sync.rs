@@ -0,0 +1,234 @@
+//! 基于 futex 的同步原语
+//!
+//! 没有竞争时只进行原子操作，不会进入内核；只有需要等待或者唤醒其他线程时才调用 [`sys_futex`]。
+//! futex 以变量背后的物理地址为键，所以放在共享内存中的原语也可以在进程之间使用
+//!
+//! - [`Mutex`]：互斥锁
+//! - [`Condvar`]：与 [`Mutex`] 配合使用的条件变量
+//! - [`Once`]：只执行一次的初始化
+
+use crate::syscall::{sys_futex, FUTEX_WAIT, FUTEX_WAKE};
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// [`Mutex`] 空闲
+const UNLOCKED: u32 = 0;
+/// [`Mutex`] 被持有，没有等待者
+const LOCKED: u32 = 1;
+/// [`Mutex`] 被持有，可能有等待者，释放时需要唤醒
+const CONTENDED: u32 = 2;
+
+/// 互斥锁
+pub struct Mutex<T> {
+    /// [`UNLOCKED`]、[`LOCKED`] 或 [`CONTENDED`]
+    state: AtomicU32,
+    /// 被保护的数据
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+/// [`Mutex`] 的守卫，drop 时释放锁
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    /// 创建一个新的互斥锁
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 获得锁，锁被占用时在内核中休眠等待
+    pub fn lock(&self) -> MutexGuard<T> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            self.lock_contended();
+        }
+        MutexGuard { mutex: self }
+    }
+
+    /// 尝试获得锁，锁被占用时返回 `None`
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
+
+    /// 有竞争时获得锁
+    ///
+    /// 标记为 [`CONTENDED`] 之后再休眠，这样持有者释放时一定会唤醒。
+    /// 自己无法得知是否还有其他等待者，所以获得锁时也保持 [`CONTENDED`]
+    fn lock_contended(&self) {
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            sys_futex(&self.state, FUTEX_WAIT, CONTENDED as usize);
+        }
+    }
+
+    /// 释放锁，有等待者时唤醒其中一个
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            sys_futex(&self.state, FUTEX_WAKE, 1);
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+/// 条件变量
+///
+/// 等待者记下当前的序号再释放锁，通知者增加序号后唤醒；
+/// 如果在释放锁和开始休眠之间收到通知，序号已经改变，[`FUTEX_WAIT`] 会直接返回
+#[derive(Default)]
+pub struct Condvar {
+    /// 每次通知时增加的序号，作为 futex 使用
+    sequence: AtomicU32,
+    /// 正在等待的线程数量，为 0 时通知不需要进入内核
+    waiters: AtomicU32,
+}
+
+impl Condvar {
+    /// 创建一个新的条件变量
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+            waiters: AtomicU32::new(0),
+        }
+    }
+
+    /// 释放锁并休眠，被唤醒后重新获得锁并返回
+    ///
+    /// 可能会在没有通知的情况下返回，调用者需要在循环中重新检查条件
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex;
+        let sequence = self.sequence.load(Ordering::SeqCst);
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        drop(guard);
+        sys_futex(&self.sequence, FUTEX_WAIT, sequence as usize);
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+        // 可能还有其他线程被同时唤醒，直接按有竞争的情况上锁，保证之后释放时会唤醒它们
+        mutex.lock_contended();
+        MutexGuard { mutex }
+    }
+
+    /// 唤醒一个等待此条件变量的线程
+    pub fn notify_one(&self) {
+        self.sequence.fetch_add(1, Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) != 0 {
+            sys_futex(&self.sequence, FUTEX_WAKE, 1);
+        }
+    }
+
+    /// 唤醒所有等待此条件变量的线程
+    pub fn notify_all(&self) {
+        self.sequence.fetch_add(1, Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) != 0 {
+            sys_futex(&self.sequence, FUTEX_WAKE, usize::MAX);
+        }
+    }
+}
+
+/// [`Once`] 还没有执行
+const INCOMPLETE: u32 = 0;
+/// [`Once`] 正在执行，没有等待者
+const RUNNING: u32 = 1;
+/// [`Once`] 正在执行，可能有等待者，完成时需要唤醒
+const RUNNING_CONTENDED: u32 = 2;
+/// [`Once`] 已经执行完毕
+const COMPLETE: u32 = 3;
+
+/// 只执行一次的初始化
+pub struct Once {
+    /// [`INCOMPLETE`]、[`RUNNING`]、[`RUNNING_CONTENDED`] 或 [`COMPLETE`]
+    state: AtomicU32,
+}
+
+impl Once {
+    /// 创建一个还没有执行的 [`Once`]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(INCOMPLETE),
+        }
+    }
+
+    /// 是否已经执行完毕
+    pub fn is_completed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == COMPLETE
+    }
+
+    /// 第一次调用时执行 `f`，其他线程同时调用时休眠等待它执行完毕
+    pub fn call_once(&self, f: impl FnOnce()) {
+        if self.is_completed() {
+            return;
+        }
+        match self.state.compare_exchange(
+            INCOMPLETE,
+            RUNNING,
+            Ordering::Acquire,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                f();
+                if self.state.swap(COMPLETE, Ordering::Release) == RUNNING_CONTENDED {
+                    sys_futex(&self.state, FUTEX_WAKE, usize::MAX);
+                }
+            }
+            Err(_) => self.wait(),
+        }
+    }
+
+    /// 等待正在执行的线程完成
+    fn wait(&self) {
+        loop {
+            match self.state.compare_exchange(
+                RUNNING,
+                RUNNING_CONTENDED,
+                Ordering::Acquire,
+                Ordering::Acquire,
+            ) {
+                Ok(_) | Err(RUNNING_CONTENDED) => {
+                    sys_futex(&self.state, FUTEX_WAIT, RUNNING_CONTENDED as usize);
+                }
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+impl Default for Once {
+    fn default() -> Self {
+        Self::new()
+    }
+}
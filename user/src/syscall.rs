@@ -1,20 +1,62 @@
 //! 系统调用
 
+use core::sync::atomic::AtomicU32;
+
 pub const STDIN: usize = 0;
 pub const STDOUT: usize = 1;
 
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PSELECT: usize = 72;
+const SYSCALL_PPOLL: usize = 73;
+const SYSCALL_TIMERFD_CREATE: usize = 85;
+const SYSCALL_TIMERFD_SETTIME: usize = 86;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
+const SYSCALL_RT_SIGPENDING: usize = 136;
+const SYSCALL_RT_SIGTIMEDWAIT: usize = 137;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_GET_PRIORITY: usize = 141;
+const SYSCALL_GETRUSAGE: usize = 165;
+const SYSCALL_MQ_OPEN: usize = 180;
+const SYSCALL_MQ_UNLINK: usize = 181;
+const SYSCALL_MQ_TIMEDSEND: usize = 182;
+const SYSCALL_MQ_TIMEDRECEIVE: usize = 183;
+const SYSCALL_SEMGET: usize = 190;
+const SYSCALL_SEMCLAIM: usize = 191;
+const SYSCALL_SEMOP: usize = 193;
+const SYSCALL_SHMGET: usize = 194;
+const SYSCALL_SHMAT: usize = 196;
+const SYSCALL_SHMDT: usize = 197;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_BIND: usize = 200;
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_ACCEPT: usize = 202;
+const SYSCALL_CONNECT: usize = 203;
+const SYSCALL_SENDTO: usize = 206;
+const SYSCALL_RECVFROM: usize = 207;
+const SYSCALL_SCHED_SETATTR: usize = 274;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_UNLINK: usize = 401;
 
 /// 将参数放在对应寄存器中，并执行 `ecall`
 fn syscall(id: usize, arg0: usize, arg1: usize, arg2: usize) -> isize {
+    syscall5(id, arg0, arg1, arg2, 0, 0)
+}
+
+/// 与 [`syscall`] 相同，但使用 5 个参数
+fn syscall5(id: usize, arg0: usize, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> isize {
     // 返回值
     let mut ret;
     unsafe {
         llvm_asm!("ecall"
             : "={x10}" (ret)
-            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x17}" (id)
+            : "{x10}" (arg0), "{x11}" (arg1), "{x12}" (arg2), "{x13}" (arg3), "{x14}" (arg4), "{x17}" (id)
             : "memory"      // 如果汇编可能改变内存，则需要加入 memory 选项
             : "volatile"); // 防止编译器做激进的优化（如调换指令顺序等破坏 SBI 调用行为的优化）
     }
@@ -22,18 +64,15 @@ fn syscall(id: usize, arg0: usize, arg1: usize, arg2: usize) -> isize {
 }
 
 /// 读取字符
+///
+/// 暂时没有数据时，线程会在内核中休眠等待
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
-    loop {
-        let ret = syscall(
-            SYSCALL_READ,
-            fd,
-            buffer as *const [u8] as *const u8 as usize,
-            buffer.len(),
-        );
-        if ret > 0 {
-            return ret;
-        }
-    }
+    syscall(
+        SYSCALL_READ,
+        fd,
+        buffer as *const [u8] as *const u8 as usize,
+        buffer.len(),
+    )
 }
 
 /// 打印字符串
@@ -46,8 +85,424 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     )
 }
 
+/// 创建一个管道，读端和写端的文件描述符依次写入 `fds`
+///
+/// 所有写端关闭后，读到末尾时返回 0；所有读端关闭后，写入的进程会被终止
+pub fn sys_pipe(fds: &mut [usize; 2]) -> isize {
+    syscall(SYSCALL_PIPE, fds as *mut [usize; 2] as usize, 0, 0)
+}
+
+/// 复制文件描述符，返回当前未使用的最小的文件描述符
+///
+/// 出现错误返回 -1
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, fd, 0, 0)
+}
+
+/// 关闭文件描述符
+///
+/// 出现错误返回 -1
+pub fn sys_close(fd: usize) -> isize {
+    syscall(SYSCALL_CLOSE, fd, 0, 0)
+}
+
 /// 退出并返回数值
 pub fn sys_exit(code: isize) -> ! {
     syscall(SYSCALL_EXIT, code as usize, 0, 0);
     unreachable!()
 }
+
+/// futex 操作：值仍然与预期相同时休眠等待
+pub const FUTEX_WAIT: usize = 0;
+/// futex 操作：唤醒最多给定数量的等待者
+pub const FUTEX_WAKE: usize = 1;
+
+/// 对 `futex` 进行 [`FUTEX_WAIT`] 或 [`FUTEX_WAKE`] 操作
+///
+/// [`FUTEX_WAIT`] 在值已经不等于 `value` 时返回 -1；[`FUTEX_WAKE`] 返回唤醒的线程数量
+pub fn sys_futex(futex: &AtomicU32, op: usize, value: usize) -> isize {
+    syscall(SYSCALL_FUTEX, futex as *const AtomicU32 as usize, op, value)
+}
+
+/// 设置线程的优先级，`tid` 为 0 时表示当前线程
+///
+/// 出现错误返回 -1
+pub fn sys_set_priority(tid: isize, priority: usize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, tid as usize, priority, 0)
+}
+
+/// 获取线程的优先级，`tid` 为 0 时表示当前线程
+///
+/// 出现错误返回 -1
+pub fn sys_get_priority(tid: isize) -> isize {
+    syscall(SYSCALL_GET_PRIORITY, tid as usize, 0, 0)
+}
+
+/// 线程的调度统计，时间的单位为微秒
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Rusage {
+    /// 在用户态执行的时间
+    pub user_time: usize,
+    /// 在内核态执行的时间
+    pub system_time: usize,
+    /// 可以执行但在等待调度的时间
+    pub wait_time: usize,
+    /// 主动让出 hart 的次数
+    pub voluntary_switches: usize,
+    /// 被抢占的次数
+    pub involuntary_switches: usize,
+    /// 上一次在哪个 hart 上执行
+    pub last_hart: usize,
+}
+
+/// 获取线程的调度统计，`tid` 为 0 时表示当前线程
+///
+/// 出现错误返回 -1
+pub fn sys_getrusage(tid: isize, usage: &mut Rusage) -> isize {
+    syscall(
+        SYSCALL_GETRUSAGE,
+        tid as usize,
+        usage as *mut Rusage as usize,
+        0,
+    )
+}
+
+/// 可以读取
+pub const POLLIN: usize = 0x1;
+/// 可以写入
+pub const POLLOUT: usize = 0x4;
+/// 出现错误
+pub const POLLERR: usize = 0x8;
+/// 文件描述符无效
+pub const POLLNVAL: usize = 0x20;
+
+/// [`sys_poll`] 等待的一项
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PollFd {
+    /// 文件描述符
+    pub fd: usize,
+    /// 关心的事件
+    pub events: usize,
+    /// 发生的事件，由内核填写
+    pub revents: usize,
+}
+
+/// 等待 `fds` 中任何一项关心的事件发生，最多等待 `timeout` 微秒（为负数时一直等待）
+///
+/// 返回有事件发生的项数，超时返回 0
+///
+/// 使用 Linux 中 `ppoll` 的编号，但参数不同：`timeout` 直接以微秒给出，没有信号掩码
+pub fn sys_poll(fds: &mut [PollFd], timeout: isize) -> isize {
+    syscall(
+        SYSCALL_PPOLL,
+        fds.as_mut_ptr() as usize,
+        fds.len(),
+        timeout as usize,
+    )
+}
+
+/// 等待 `read` 中的文件描述符可以读取或 `write` 中的文件描述符可以写入，最多等待 `timeout` 微秒（为负数时一直等待）
+///
+/// 两个集合都是文件描述符的位图，返回时只保留就绪的文件描述符。返回就绪的文件描述符数量，超时返回 0，出现错误返回 -1
+///
+/// 使用 Linux 中 `pselect6` 的编号，但参数不同：没有 `nfds`、异常集合和信号掩码
+pub fn sys_select(read: &mut usize, write: &mut usize, timeout: isize) -> isize {
+    syscall(
+        SYSCALL_PSELECT,
+        read as *mut usize as usize,
+        write as *mut usize as usize,
+        timeout as usize,
+    )
+}
+
+/// 按实际时间计时的间隔定时器，到期时发送 [`SIGALRM`]
+pub const ITIMER_REAL: usize = 0;
+/// 按进程在用户态执行的时间计时的间隔定时器，到期时发送 [`SIGVTALRM`]
+pub const ITIMER_VIRTUAL: usize = 1;
+/// 按进程在用户态和内核态执行的时间计时的间隔定时器，到期时发送 [`SIGPROF`]
+pub const ITIMER_PROF: usize = 2;
+
+/// [`ITIMER_REAL`] 到期
+pub const SIGALRM: usize = 14;
+/// [`ITIMER_VIRTUAL`] 到期
+pub const SIGVTALRM: usize = 26;
+/// [`ITIMER_PROF`] 到期
+pub const SIGPROF: usize = 27;
+
+/// 间隔定时器的设置，单位为微秒
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ITimerVal {
+    /// 到期后重新开始计时的间隔，为 0 表示只触发一次
+    pub interval: usize,
+    /// 距离到期的时间，为 0 表示没有启动
+    pub value: usize,
+}
+
+/// 读取间隔定时器 `which` 的设置
+///
+/// 出现错误返回 -1
+pub fn sys_getitimer(which: usize, value: &mut ITimerVal) -> isize {
+    syscall(
+        SYSCALL_GETITIMER,
+        which,
+        value as *mut ITimerVal as usize,
+        0,
+    )
+}
+
+/// 设置间隔定时器 `which`，`value` 为 0 时停止，之前的设置写入 `old`
+///
+/// 出现错误返回 -1
+pub fn sys_setitimer(which: usize, new: &ITimerVal, old: &mut ITimerVal) -> isize {
+    syscall(
+        SYSCALL_SETITIMER,
+        which,
+        new as *const ITimerVal as usize,
+        old as *mut ITimerVal as usize,
+    )
+}
+
+/// 在 `seconds` 秒后向当前进程发送 [`SIGALRM`]，为 0 时取消
+///
+/// 通过 [`ITIMER_REAL`] 实现，返回之前的定时还剩下的秒数（向上取整）
+pub fn sys_alarm(seconds: usize) -> isize {
+    let new = ITimerVal {
+        interval: 0,
+        value: seconds * 1_000_000,
+    };
+    let mut old = ITimerVal::default();
+    sys_setitimer(ITIMER_REAL, &new, &mut old);
+    ((old.value + 999_999) / 1_000_000) as isize
+}
+
+/// 返回当前进程的待处理信号，第 n 位表示信号 n
+pub fn sys_sigpending() -> isize {
+    syscall(SYSCALL_RT_SIGPENDING, 0, 0, 0)
+}
+
+/// 等待 `mask` 中的任何一个信号（第 n 位表示信号 n），最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 取走编号最小的待处理信号并返回其编号，超时返回 -1
+pub fn sys_sigtimedwait(mask: usize, timeout: usize) -> isize {
+    syscall(SYSCALL_RT_SIGTIMEDWAIT, mask, timeout, 0)
+}
+
+/// 创建一个定时器文件，返回文件描述符
+///
+/// 到期时可读，用 [`sys_read`] 读取 8 字节，得到自上次读取以来的到期次数，还没有到期时休眠等待
+pub fn sys_timerfd_create() -> isize {
+    syscall(SYSCALL_TIMERFD_CREATE, 0, 0, 0)
+}
+
+/// 设置定时器文件，`value` 为 0 时停止，之前的设置写入 `old`
+///
+/// 出现错误返回 -1
+pub fn sys_timerfd_settime(fd: usize, new: &ITimerVal, old: &mut ITimerVal) -> isize {
+    syscall(
+        SYSCALL_TIMERFD_SETTIME,
+        fd,
+        new as *const ITimerVal as usize,
+        old as *mut ITimerVal as usize,
+    )
+}
+
+/// 消息队列不存在时创建
+pub const MQ_CREATE: usize = 1;
+/// 以非阻塞方式打开消息队列
+pub const MQ_NONBLOCK: usize = 2;
+
+/// 按名字打开一个消息队列，返回文件描述符
+///
+/// `flags` 包含 [`MQ_CREATE`] 时，队列不存在则创建，最多容纳 `capacity` 条长度不超过 `max_size` 的消息。
+/// 两者有内核规定的上限。出现错误返回 -1
+pub fn sys_mq_open(name: &str, flags: usize, capacity: usize, max_size: usize) -> isize {
+    syscall5(
+        SYSCALL_MQ_OPEN,
+        name.as_ptr() as usize,
+        name.len(),
+        flags,
+        capacity,
+        max_size,
+    )
+}
+
+/// 删除名为 `name` 的消息队列，已经打开的描述符仍然可以使用
+///
+/// 队列不存在时返回 -1
+pub fn sys_mq_unlink(name: &str) -> isize {
+    syscall(SYSCALL_MQ_UNLINK, name.as_ptr() as usize, name.len(), 0)
+}
+
+/// 以 `priority` 发送一条消息，队列已满时最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 出现错误或超时返回 -1
+pub fn sys_mq_send(fd: usize, message: &[u8], priority: usize, timeout: usize) -> isize {
+    syscall5(
+        SYSCALL_MQ_TIMEDSEND,
+        fd,
+        message.as_ptr() as usize,
+        message.len(),
+        priority,
+        timeout,
+    )
+}
+
+/// 取出优先级最高的消息，队列为空时最多等待 `timeout` 微秒（为 0 时一直等待）
+///
+/// 返回消息的长度，并将优先级写入 `priority`。`buffer` 不能小于队列的最大消息长度，出现错误或超时返回 -1
+pub fn sys_mq_receive(fd: usize, buffer: &mut [u8], priority: &mut usize, timeout: usize) -> isize {
+    syscall5(
+        SYSCALL_MQ_TIMEDRECEIVE,
+        fd,
+        buffer.as_mut_ptr() as usize,
+        buffer.len(),
+        priority as *mut usize as usize,
+        timeout,
+    )
+}
+
+/// 按键值打开一个信号量，不存在则以 `value` 为初始计数创建
+///
+/// 返回信号量的标识
+pub fn sys_semget(key: usize, value: usize) -> isize {
+    syscall(SYSCALL_SEMGET, key, value, 0)
+}
+
+/// 声明当前进程对信号量最多同时需要 `max` 个计数，为 0 时取消声明
+///
+/// 声明之后，对该信号量的 P 操作按银行家算法分配，不会使系统进入不安全状态；超过声明的 P 操作返回 -1
+pub fn sys_semclaim(id: usize, max: usize) -> isize {
+    syscall(SYSCALL_SEMCLAIM, id, max, 0)
+}
+
+/// 对信号量进行操作：`op` 为正数时增加计数，为负数时减少计数，计数不足时休眠等待
+///
+/// 出现错误返回 -1
+pub fn sys_semop(id: usize, op: isize) -> isize {
+    syscall(SYSCALL_SEMOP, id, op as usize, 0)
+}
+
+/// 按键值打开一段共享内存，不存在则创建
+///
+/// 返回共享内存的标识，出现错误返回 -1
+pub fn sys_shmget(key: usize, size: usize) -> isize {
+    syscall(SYSCALL_SHMGET, key, size, 0)
+}
+
+/// 将共享内存映射到当前进程，返回映射的起始地址
+///
+/// 出现错误返回 -1
+pub fn sys_shmat(id: usize) -> isize {
+    syscall(SYSCALL_SHMAT, id, 0, 0)
+}
+
+/// 解除 `address` 处的共享内存映射
+pub fn sys_shmdt(address: usize) -> isize {
+    syscall(SYSCALL_SHMDT, address, 0, 0)
+}
+
+/// 本地（Unix 域）套接字
+pub const AF_UNIX: usize = 1;
+/// 流式套接字
+pub const SOCK_STREAM: usize = 1;
+/// 数据报套接字
+pub const SOCK_DGRAM: usize = 2;
+
+/// 创建一个套接字，返回文件描述符
+///
+/// 出现错误返回 -1
+pub fn sys_socket(domain: usize, socket_type: usize) -> isize {
+    syscall(SYSCALL_SOCKET, domain, socket_type, 0)
+}
+
+/// 将套接字绑定到路径上
+///
+/// 出现错误返回 -1
+pub fn sys_bind(fd: usize, path: &str) -> isize {
+    syscall(SYSCALL_BIND, fd, path.as_ptr() as usize, path.len())
+}
+
+/// 开始接受连接，最多排队 `backlog` 个
+///
+/// 出现错误返回 -1
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    syscall(SYSCALL_LISTEN, fd, backlog, 0)
+}
+
+/// 取出一个连接，没有时休眠等待，返回新连接的文件描述符
+///
+/// 出现错误返回 -1
+pub fn sys_accept(fd: usize) -> isize {
+    syscall(SYSCALL_ACCEPT, fd, 0, 0)
+}
+
+/// 连接到绑定在路径上的套接字
+///
+/// 出现错误返回 -1
+pub fn sys_connect(fd: usize, path: &str) -> isize {
+    syscall(SYSCALL_CONNECT, fd, path.as_ptr() as usize, path.len())
+}
+
+/// 通过套接字发送数据，返回发送的长度
+///
+/// 出现错误返回 -1
+pub fn sys_send(fd: usize, buffer: &[u8]) -> isize {
+    syscall(SYSCALL_SENDTO, fd, buffer.as_ptr() as usize, buffer.len())
+}
+
+/// 通过套接字接收数据，返回接收的长度，流式套接字的对方关闭后返回 0
+///
+/// 出现错误返回 -1
+pub fn sys_recv(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_RECVFROM,
+        fd,
+        buffer.as_mut_ptr() as usize,
+        buffer.len(),
+    )
+}
+
+/// 实时调度参数，单位为微秒
+#[repr(C)]
+pub struct RealtimeAttr {
+    /// 周期
+    pub period: usize,
+    /// 每个周期内的执行时间
+    pub budget: usize,
+    /// 相对于周期开始的截止时间，不能超过周期
+    pub deadline: usize,
+}
+
+/// 设置线程的实时调度参数，`tid` 为 0 时表示当前线程
+///
+/// `attr` 为 `None` 时将线程移回普通调度器。无法通过可调度性检查时返回 -1
+pub fn sys_sched_setattr(tid: isize, attr: Option<&RealtimeAttr>) -> isize {
+    let attr = match attr {
+        Some(attr) => attr as *const RealtimeAttr as usize,
+        None => 0,
+    };
+    syscall(SYSCALL_SCHED_SETATTR, tid as usize, attr, 0)
+}
+
+/// 在新的进程中执行名为 `name` 的程序，返回新进程的 ID
+///
+/// `memory_limit` 为新进程最多可以占用的内存（字节），为 0 时使用内核的默认限制。
+/// 程序不存在或者加载时就超出限制则返回 -1
+pub fn sys_spawn(name: &str, memory_limit: usize) -> isize {
+    syscall(
+        SYSCALL_SPAWN,
+        name.as_ptr() as usize,
+        name.len(),
+        memory_limit,
+    )
+}
+
+/// 删除套接字绑定的路径，之后可以再次绑定
+///
+/// 套接字关闭后路径仍然被占用，需要用它删除。路径没有绑定时返回 -1
+pub fn sys_unlink(path: &str) -> isize {
+    syscall(SYSCALL_UNLINK, path.as_ptr() as usize, path.len(), 0)
+}